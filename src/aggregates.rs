@@ -0,0 +1,163 @@
+//! Aggregation of numeric column values, used by row groups, pivot table and footer.
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Aggregate {
+    Count,
+    #[default]
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub const ALL: [Aggregate; 5] = [
+        Aggregate::Count,
+        Aggregate::Sum,
+        Aggregate::Avg,
+        Aggregate::Min,
+        Aggregate::Max,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+/// Running aggregates of the numeric values of a column.
+#[derive(Clone, Copy, Debug)]
+pub struct Aggregates {
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for Aggregates {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl Aggregates {
+    #[inline]
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Undo `add`. Returns false when removed value was min or max, aggregates then have to be
+    /// computed again from all values.
+    pub fn remove(&mut self, value: f64) -> bool {
+        self.count -= 1;
+        self.sum -= value;
+        value > self.min && value < self.max
+    }
+
+    /// None when there is no value to aggregate.
+    pub fn get(&self, aggregate: Aggregate) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(match aggregate {
+            Aggregate::Count => self.count as f64,
+            Aggregate::Sum => self.sum,
+            Aggregate::Avg => self.sum / self.count as f64,
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+        })
+    }
+
+    pub fn format(&self, aggregate: Aggregate) -> String {
+        self.get(aggregate).map_or(String::new(), format_number)
+    }
+
+    /// All aggregates, one per line.
+    pub fn summary(&self) -> String {
+        Aggregate::ALL
+            .iter()
+            .map(|aggregate| format!("{}: {}", aggregate.as_str(), self.format(*aggregate)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Up to 4 decimals, without trailing zeros.
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let formatted = format!("{:.4}", value);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregates::{format_number, Aggregate, Aggregates};
+
+    #[test]
+    fn test_add_and_get() {
+        let mut aggregates = Aggregates::default();
+        assert_eq!(aggregates.get(Aggregate::Sum), None);
+        assert_eq!(aggregates.format(Aggregate::Max), "");
+        for value in [3.0, -1.5, 10.0, 4.5] {
+            aggregates.add(value);
+        }
+        assert_eq!(aggregates.get(Aggregate::Count), Some(4.0));
+        assert_eq!(aggregates.get(Aggregate::Sum), Some(16.0));
+        assert_eq!(aggregates.get(Aggregate::Avg), Some(4.0));
+        assert_eq!(aggregates.get(Aggregate::Min), Some(-1.5));
+        assert_eq!(aggregates.get(Aggregate::Max), Some(10.0));
+        assert_eq!(
+            aggregates.summary(),
+            "count: 4\nsum: 16\navg: 4\nmin: -1.5\nmax: 10"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut aggregates = Aggregates::default();
+        for value in [3.0, -1.5, 10.0, 4.5] {
+            aggregates.add(value);
+        }
+        assert!(aggregates.remove(3.0));
+        assert_eq!(aggregates.get(Aggregate::Count), Some(3.0));
+        assert_eq!(aggregates.get(Aggregate::Sum), Some(13.0));
+        assert_eq!(aggregates.get(Aggregate::Min), Some(-1.5));
+        // Min and max can not be known without the other values
+        let (mut removed_max, mut removed_min) = (aggregates, aggregates);
+        assert!(!removed_max.remove(10.0));
+        assert!(!removed_min.remove(-1.5));
+
+        let mut aggregates = Aggregates::default();
+        aggregates.add(7.0);
+        assert!(!aggregates.remove(7.0));
+        assert_eq!(aggregates.get(Aggregate::Avg), None);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(12.0), "12");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(1.0 / 3.0), "0.3333");
+        assert_eq!(format_number(2.10004), "2.1");
+        assert_eq!(format_number(1e16), "10000000000000000");
+    }
+}
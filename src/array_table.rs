@@ -1,39 +1,64 @@
-use crate::components::cell_text::CellText;
+use crate::aggregates::{Aggregate, Aggregates};
+use crate::chart::{Chart, ChartKind};
+use crate::components::cell_text::{CellText, HIGHLIGHT_COLOR};
 use crate::components::icon;
 use crate::components::icon::ButtonWithIcon;
 use crate::components::popover::PopupMenu;
 use crate::components::table::{CellLocation, TableBody, TableRow};
-use crate::fonts::{COPY, FILTER, PENCIL, PLUS, SEARCH, TABLE, TABLE_CELLS, THUMBTACK};
-use crate::panels::{SearchReplacePanel, SearchReplaceResponse, PANEL_REPLACE};
-use crate::parser::{replace_occurrences, row_number_entry, search_occurrences};
+use crate::computed_column::{to_cell_value, value_type, ComputedColumn};
+use crate::fonts::{
+    ARROW_DOWN, ARROW_UP, ARROW_UP_1_9, CALCULATOR, CARET_DOWN, CARET_RIGHT, CHART_SIMPLE, CLONE,
+    COPY, FILE_CIRCLE_PLUS, FILTER, LAYER_GROUP, PENCIL, PLUS, SEARCH, SORT, SORT_DOWN, SORT_UP,
+    TABLE, TABLE_CELLS, THUMBTACK, TRASH_CAN,
+};
+use crate::formatting::{Formatting, FormattingRule};
+use crate::panels::{
+    ChartPanel, ChartResponse, ColumnsPanel, ColumnsResponse, ComputedColumnsPanel,
+    ComputedColumnsResponse, FormattingPanel, FormattingResponse, PivotPanel, PivotResponse,
+    ReplaceMode, ReplaceScope, SearchReplaceAction, SearchReplacePanel, SearchReplaceResponse,
+    PANEL_CHART, PANEL_COLUMNS, PANEL_COMPUTED_COLUMNS, PANEL_FORMATTING, PANEL_PIVOT,
+    PANEL_REPLACE,
+};
+use crate::parser::{
+    column_id, count_matching_values, rename_key, rename_pointer_keys, rename_serialized_keys,
+    replace_occurrences, row_number_entry, row_pointer, search_keys, search_occurrences,
+    search_regex, template_value,
+};
+use crate::pivot::Pivot;
+use crate::query::{Query, QueryResult};
+use crate::replace_panel::{ReplaceOccurrence, ReplacePreview, Transform};
+use crate::schema::{RowValidator, SchemaError};
 use crate::subtable_window::SubTable;
 use crate::{
     concat_string, set_open, ArrayResponse, Window, ACTIVE_COLOR, SHORTCUT_COPY, SHORTCUT_DELETE,
-    SHORTCUT_REPLACE,
+    SHORTCUT_MOVE_ROWS_DOWN, SHORTCUT_MOVE_ROWS_UP, SHORTCUT_REPLACE,
 };
 use eframe::egui::scroll_area::ScrollBarVisibility;
 use eframe::egui::style::Spacing;
 use eframe::egui::{
-    Align, Context, CursorIcon, Id, Key, Label, Sense, Style, TextEdit, Ui, Vec2, Widget,
-    WidgetText,
+    Align, Color32, Context, CursorIcon, Id, Key, Label, Response, RichText, Sense, Stroke, Style,
+    TextEdit, Ui, Vec2, Widget, WidgetText,
 };
-use eframe::epaint::text::TextWrapMode;
 use egui::{EventFilter, InputState, Modifiers, Rangef, TextBuffer};
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use json_flat_parser::serializer::serialize_to_json_with_option;
 use json_flat_parser::{
     FlatJsonValue, JSONParser, JsonArrayEntries, ParseOptions, ParseResult, PointerKey, ValueType,
 };
 use rayon::iter::IntoParallelIterator;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::IntoParallelRefMutIterator;
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSliceMut;
+use regex_lite::Regex;
+use serde_json::Value;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::mem;
-use std::ops::Sub;
+use std::ops::{Range, Sub};
 use std::string::ToString;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -106,6 +131,58 @@ impl ScrollToRowMode {
     }
 }
 
+/// Cell or column header matching the searched term.
+pub struct SearchMatch {
+    // None when the key of the column matches, the match is on its header
+    pub table_row_index: Option<usize>,
+    // Column name, key of the cell value in its row
+    pub column: String,
+}
+
+/// Rows sharing the same values for the group by columns.
+struct RowGroup {
+    key: Vec<Option<String>>,
+    rows: Vec<usize>,
+    collapsed: bool,
+    // Aggregates of numeric columns, by column name
+    aggregates: HashMap<String, Aggregates>,
+}
+
+/// View settings kept when the table is rebuilt from a new version of the same file, also saved in
+/// the workspace file next to it.
+pub struct ViewState {
+    pub depth: u8,
+    pub(crate) pinned_columns: Vec<String>,
+    pub(crate) columns_filter: HashMap<String, Vec<String>>,
+    // Name and expression
+    pub(crate) computed_columns: Vec<(String, String)>,
+    pub(crate) sort: Option<(String, bool)>,
+    pub(crate) group_by: Vec<String>,
+    pub(crate) formatting_rules: Vec<FormattingRule>,
+    pub(crate) id_column: Option<String>,
+    pub(crate) column_order: Vec<String>,
+    pub(crate) hidden_columns: Vec<String>,
+    pub(crate) column_widths: Vec<(String, f32)>,
+    pub(crate) scroll_y: f32,
+}
+
+/// Content of an inserted row.
+#[derive(Clone, Copy)]
+enum NewRow {
+    Empty,
+    // Copy of the row next to which the new row is inserted
+    Duplicate,
+    // Keys of the row next to which the new row is inserted, with default values
+    Template,
+}
+
+/// Rows dragged by their row number.
+struct RowDrag {
+    rows: Vec<usize>,
+    // Table row index rows are dropped before, None when pointer is not over rows
+    drop_at: Option<usize>,
+}
+
 pub struct ArrayTable<'array> {
     table_id: Id,
     all_columns: Vec<Column<'array>>,
@@ -116,6 +193,9 @@ pub struct ArrayTable<'array> {
     parse_result: Option<ParseResult<String>>,
     pub nodes: Vec<JsonArrayEntries<String>>,
     filtered_nodes: Vec<usize>,
+    // Index in pointer of the next inserted row. Rows keep the pointer they were parsed or
+    // inserted at, so inserting or deleting a row does not rewrite pointers of following rows
+    next_row_pointer_index: usize,
     scroll_y: f32,
     pub columns_filter: HashMap<String, Vec<String>>,
     pub hovered_row_index: Option<usize>,
@@ -128,8 +208,12 @@ pub struct ArrayTable<'array> {
     cache: RefCell<crate::components::cache::CacheStorage>,
     seed1: usize, // seed for Id
     seed2: usize, // seed for Id
-    pub matching_rows: Vec<usize>,
-    pub matching_row_selected: usize,
+    // Cells matching searched term, with the options of the search
+    pub matching_cells: Vec<SearchMatch>,
+    pub matching_cell_selected: usize,
+    pub search_mode: ReplaceMode,
+    pub search_error: Option<String>,
+    search_regex: Option<Regex>,
     pub matching_columns: Vec<usize>,
     pub matching_column_selected: usize,
     pub scroll_to_column: String,
@@ -139,17 +223,79 @@ pub struct ArrayTable<'array> {
     pub scroll_to_row_mode: ScrollToRowMode,
     pub focused_cell: Option<CellLocation>,
 
+    // Schema validation
+    schema_validator: Option<RowValidator>,
+    // Errors by row index of invalid rows
+    pub schema_errors: BTreeMap<usize, Vec<SchemaError>>,
+    pub only_invalid_rows: bool,
+
+    // Virtual columns, also listed in all_columns
+    computed_columns: Vec<ComputedColumn>,
+    // Computed column whose values are being written in rows, they can not be edited otherwise
+    materialized_column: Option<String>,
+    // Column name and whether order is descending
+    pub sort: Option<(String, bool)>,
+
+    // Group rows by value of these columns
+    pub group_by: Vec<String>,
+    groups: Vec<RowGroup>,
+    // Group index of group header rows, by table row index
+    group_headers: HashMap<usize, usize>,
+    pub group_aggregate: Aggregate,
+
+    // Row index of selected rows, and of the last clicked row to extend selection from
+    pub selected_rows: BTreeSet<usize>,
+    selection_anchor: Option<usize>,
+    row_drag: Option<RowDrag>,
+
+    // Aggregates of numeric columns over selected rows, or filtered rows when there is no selection
+    pub show_aggregates_footer: bool,
+    // None when they have to be computed again
+    footer_aggregates: Option<HashMap<String, Aggregates>>,
+    // Columns to compute again, after an edit changed their min or max
+    stale_footer_columns: HashSet<String>,
+
+    // Rows grow to fit text wrapped at column width, up to MAX_WRAPPED_LINES
+    pub wrap_rows: bool,
+    // Height of each table row and top of each row with spacing followed by the table height,
+    // rows are measured when they are about to be drawn
+    row_heights: Vec<f32>,
+    row_offsets: Vec<f32>,
+    // Width of columns of both tables drawn last frame, by column name
+    drawn_column_widths: HashMap<String, f32>,
+
+    // Conditional formatting rules of cells and rows
+    formatting: Formatting,
+
+    // Numeric column set to max value + 1 in inserted rows
+    pub id_column: Option<String>,
+
+    // Column names in chosen order, columns missing from it come after in default order
+    pub column_order: Vec<String>,
+    pub hidden_columns: HashSet<String>,
+    // Widths of resized or fitted columns by column name
+    pub column_widths: HashMap<String, f32>,
+
+    // JSONPath or jq query
+    pub query: String,
+    pub query_error: Option<String>,
+    // Index of rows matching applied query, in ascending order
+    query_rows: Option<Vec<usize>>,
+
     // Visibility information
     pub first_visible_index: usize,
     pub last_visible_index: usize,
     pub first_visible_offset: f32,
     pub last_visible_offset: f32,
+    // Table row index of rows drawn last frame
+    visible_rows: Range<usize>,
 
     // Handle interaction
     pub next_frame_reset_scroll: bool,
+    next_frame_fit_columns: bool,
     pub changed_scroll_to_column_value: bool,
     pub changed_matching_column_selected: bool,
-    pub changed_matching_row_selected: bool,
+    pub changed_matching_cell_selected: bool,
     pub changed_arrow_horizontal_scroll: bool,
     pub changed_arrow_vertical_scroll: bool,
     pub was_editing: bool,
@@ -164,13 +310,21 @@ pub struct ArrayTable<'array> {
 
     opened_windows: BTreeSet<String>,
     search_replace_panel: SearchReplacePanel<'array>,
+    computed_columns_panel: ComputedColumnsPanel,
+    pivot_panel: PivotPanel,
+    chart_panel: ChartPanel,
+    formatting_panel: FormattingPanel,
+    columns_panel: ColumnsPanel,
 }
 
 impl super::View<ArrayResponse> for ArrayTable<'_> {
     fn ui(&mut self, ui: &mut egui::Ui) -> ArrayResponse {
         let mut array_response = ArrayResponse::default();
         self.windows(ui.ctx(), &mut array_response);
+        self.update_footer_aggregates();
+        self.update_formatting_ranges();
         let parent_height_available = ui.available_rect_before_wrap().height();
+        self.update_row_heights(ui, parent_height_available);
         let parent_width_available = ui.available_rect_before_wrap().width();
         ui.interact(
             ui.available_rect_before_wrap(),
@@ -296,22 +450,26 @@ impl Hash for CachePointerKey {
 impl<'array>
     crate::components::cache::ComputerMut<
         (&Column<'array>, &String),
-        &Vec<JsonArrayEntries<String>>,
+        &ArrayTable<'array>,
         IndexSet<String>,
     > for CacheFilterOptions
 {
     fn compute(
         &mut self,
-        (column, parent_pointer): (&Column<'array>, &String),
-        nodes: &Vec<JsonArrayEntries<String>>,
+        (column, _parent_pointer): (&Column<'array>, &String),
+        table: &ArrayTable<'array>,
     ) -> IndexSet<String> {
         let mut unique_values = IndexSet::new();
-        if ArrayTable::is_filterable(column) {
-            nodes
+        if let Some(computed_column) = table.computed_column(&column.name) {
+            if ArrayTable::is_filterable(column) {
+                unique_values.extend(computed_column.values.iter().flatten().cloned());
+            }
+        } else if ArrayTable::is_filterable(column) {
+            table
+                .nodes
                 .iter()
-                .enumerate()
-                .map(|(i, row)| {
-                    ArrayTable::get_pointer_for_column(parent_pointer, &&row.entries, i, column)
+                .map(|row| {
+                    ArrayTable::get_pointer_for_column(&&row.entries, column)
                         .filter(|entry| entry.value.is_some())
                         .map(|entry| entry.value.clone().unwrap())
                 })
@@ -356,16 +514,17 @@ impl<'array>
             &table.column_selected
         };
         ArrayTable::get_pointer_index(
-            &table.parent_pointer,
             columns,
             &table.nodes()[cache_pointer_key.row_index].entries(),
             cache_pointer_key.index,
-            cache_pointer_key.row_index,
         )
     }
 }
 
 pub const NON_NULL_FILTER_VALUE: &str = "__non_null";
+const MAX_WRAPPED_LINES: usize = 6;
+// Longer values are cut when drawn in a cell
+const MAX_DRAWN_VALUE_LEN: usize = 1000;
 
 impl<'array> ArrayTable<'array> {
     pub fn new(
@@ -382,10 +541,12 @@ impl<'array> ArrayTable<'array> {
             all_columns,
             max_depth: depth,
             filtered_nodes: (0..nodes.len()).collect::<Vec<usize>>(),
+            next_row_pointer_index: nodes.len(),
             nodes,
             parse_result,
             // states
             next_frame_reset_scroll: false,
+            next_frame_fit_columns: false,
             column_pinned: vec![Column::new("/#".to_string(), ValueType::Number)],
             scroll_y: 0.0,
             hovered_row_index: None,
@@ -394,8 +555,11 @@ impl<'array> ArrayTable<'array> {
             seed2: Id::new(format!("{}pinned", &parent_pointer.pointer)).value() as usize,
             parent_pointer,
             windows: vec![],
-            matching_rows: vec![],
-            matching_row_selected: 0,
+            matching_cells: vec![],
+            matching_cell_selected: 0,
+            search_mode: ReplaceMode::default(),
+            search_error: None,
+            search_regex: None,
             matching_columns: vec![],
             matching_column_selected: 0,
             scroll_to_column: "".to_string(),
@@ -407,7 +571,7 @@ impl<'array> ArrayTable<'array> {
             scroll_to_row_number: 0,
             scroll_to_column_number: 0,
             changed_scroll_to_row_value: None,
-            changed_matching_row_selected: false,
+            changed_matching_cell_selected: false,
             changed_matching_column_selected: false,
             changed_arrow_horizontal_scroll: false,
             changed_arrow_vertical_scroll: false,
@@ -415,13 +579,47 @@ impl<'array> ArrayTable<'array> {
             editing_value: RefCell::new(String::new()),
             is_sub_table: false,
             focused_cell: None,
+            schema_validator: None,
+            schema_errors: BTreeMap::new(),
+            only_invalid_rows: false,
+            computed_columns: vec![],
+            materialized_column: None,
+            sort: None,
+            group_by: vec![],
+            groups: vec![],
+            group_headers: HashMap::new(),
+            group_aggregate: Aggregate::default(),
+            selected_rows: BTreeSet::new(),
+            selection_anchor: None,
+            row_drag: None,
+            show_aggregates_footer: false,
+            footer_aggregates: None,
+            stale_footer_columns: HashSet::new(),
+            wrap_rows: false,
+            row_heights: vec![],
+            row_offsets: vec![],
+            drawn_column_widths: HashMap::new(),
+            formatting: Formatting::default(),
+            id_column: None,
+            column_order: vec![],
+            hidden_columns: HashSet::new(),
+            column_widths: HashMap::new(),
+            query: String::new(),
+            query_error: None,
+            query_rows: None,
             first_visible_index: 0,
+            visible_rows: 0..0,
             last_visible_index: 0,
             first_visible_offset: 0.0,
             last_visible_offset: 0.0,
             cache: Default::default(),
             opened_windows: Default::default(),
             search_replace_panel: Default::default(),
+            computed_columns_panel: Default::default(),
+            pivot_panel: Default::default(),
+            chart_panel: Default::default(),
+            formatting_panel: Default::default(),
+            columns_panel: Default::default(),
             was_editing: false,
         }
     }
@@ -432,43 +630,155 @@ impl<'array> ArrayTable<'array> {
             let mut opened = true;
             let maybe_response = window.show(ctx, &mut opened);
             if let Some(maybe_inner_response) = maybe_response {
-                if let Some(response) = maybe_inner_response {
-                    for entry in response.edited_value {
-                        updated_values.push((entry, window.id(), false));
-                    }
+                if let Some(response) = maybe_inner_response.filter(|_| !window.detached) {
+                    updated_values.push((
+                        window.id(),
+                        window.row_pointer().to_string(),
+                        response.edited_value,
+                    ));
                 }
             }
             if !opened {
                 closed_windows.push(window.name().clone());
             }
         }
-        for updated_value in updated_values {
-            if self.update_value(updated_value.0.clone(), updated_value.1, updated_value.2) {
-                array_response.edited_value.push(updated_value.0.clone())
-            }
+        for (row_index, row_pointer, edited_value) in updated_values {
+            self.update_from_sub_table(row_index, &row_pointer, edited_value, array_response);
         }
         self.windows.retain(|w| !closed_windows.contains(w.name()));
 
         let mut is_open = self
             .opened_windows
             .contains(self.search_replace_panel.name());
+        self.search_replace_panel
+            .set_selected_rows_count(self.selected_rows.len());
         let response = self.search_replace_panel.show(ctx, &mut is_open);
         set_open(
             &mut self.opened_windows,
             self.search_replace_panel.name(),
             is_open,
         );
-        if let Some(search_replace_response) = response {
-            self.replace_columns(search_replace_response, array_response);
+        match response {
+            Some(SearchReplaceAction::Preview(search_replace_response)) => {
+                match self.replace_preview(search_replace_response) {
+                    Ok(preview) => self.search_replace_panel.preview = Some(preview),
+                    Err(error) => self.search_replace_panel.pattern_error = Some(error),
+                }
+            }
+            Some(SearchReplaceAction::RenameKeys(search_replace_response)) => {
+                match self.rename_keys(search_replace_response, array_response) {
+                    Ok(renamed_rows_count) => {
+                        self.search_replace_panel.renamed_rows_count = Some(renamed_rows_count)
+                    }
+                    Err(error) => self.search_replace_panel.pattern_error = Some(error),
+                }
+            }
+            Some(SearchReplaceAction::CountMatches(search_replace_response)) => {
+                self.search_replace_panel.match_count =
+                    self.count_matching_values(search_replace_response);
+            }
+            Some(SearchReplaceAction::Transform {
+                columns,
+                transform,
+                scope,
+            }) => {
+                self.search_replace_panel.preview =
+                    Some(self.transform_preview(&columns, &transform, scope));
+            }
+            Some(SearchReplaceAction::Apply) => {
+                if let Some(preview) = self.search_replace_panel.preview.take() {
+                    self.replace_columns(preview, array_response);
+                    self.search_replace_panel.recount_matches();
+                }
+            }
+            None => {}
+        }
+
+        let mut is_open = self
+            .opened_windows
+            .contains(self.computed_columns_panel.name());
+        let response = self.computed_columns_panel.show(ctx, &mut is_open);
+        set_open(
+            &mut self.opened_windows,
+            self.computed_columns_panel.name(),
+            is_open,
+        );
+        match response {
+            Some(ComputedColumnsResponse::Add { name, expression }) => {
+                self.computed_columns_panel.error =
+                    self.add_computed_column(&name, &expression).err();
+                if self.computed_columns_panel.error.is_none() {
+                    self.computed_columns_panel.clear_form();
+                }
+            }
+            Some(ComputedColumnsResponse::Remove(name)) => self.remove_computed_column(&name),
+            Some(ComputedColumnsResponse::Materialize(name)) => {
+                self.materialize_computed_column(&name, array_response)
+            }
+            None => {}
+        }
+
+        let mut is_open = self.opened_windows.contains(self.pivot_panel.name());
+        let response = self.pivot_panel.show(ctx, &mut is_open);
+        set_open(&mut self.opened_windows, self.pivot_panel.name(), is_open);
+        if let Some(pivot_response) = response {
+            self.pivot_panel.pivot = Some(self.pivot(pivot_response));
+            self.pivot_panel.error = None;
+        }
+
+        let mut is_open = self.opened_windows.contains(self.chart_panel.name());
+        let response = self.chart_panel.show(ctx, &mut is_open);
+        set_open(&mut self.opened_windows, self.chart_panel.name(), is_open);
+        match response {
+            Some(ChartResponse::Plot {
+                kind,
+                column,
+                y_column,
+            }) => self.chart_panel.chart = Some(self.chart(kind, column, y_column)),
+            Some(ChartResponse::Filter { column, values }) => {
+                self.set_column_filter(column, values)
+            }
+            None => {}
+        }
+
+        let mut is_open = self.opened_windows.contains(self.columns_panel.name());
+        let response = self.columns_panel.show(ctx, &mut is_open);
+        set_open(&mut self.opened_windows, self.columns_panel.name(), is_open);
+        match response {
+            Some(ColumnsResponse::Layout(columns)) => self.set_column_layout(columns),
+            Some(ColumnsResponse::Reset) => {
+                self.column_order.clear();
+                self.hidden_columns.clear();
+                self.set_column_layout(vec![]);
+                self.columns_panel.set_columns(self.column_layout());
+            }
+            None => {}
+        }
+
+        let mut is_open = self.opened_windows.contains(self.formatting_panel.name());
+        let response = self.formatting_panel.show(ctx, &mut is_open);
+        set_open(
+            &mut self.opened_windows,
+            self.formatting_panel.name(),
+            is_open,
+        );
+        if let Some(formatting_response) = response {
+            match formatting_response {
+                FormattingResponse::Add(rule) => {
+                    self.formatting.add_rule(rule);
+                    self.formatting_panel.error = None;
+                    self.formatting_panel.clear_form();
+                }
+                FormattingResponse::Remove(index) => self.formatting.remove_rule(index),
+            }
+            self.formatting_panel.set_rules(&self.formatting.rules);
         }
     }
 
     pub fn update_selected_columns(&mut self, depth: u8) -> Option<usize> {
         self.cache.borrow_mut().update();
         if depth <= self.last_parsed_max_depth {
-            let mut column_selected = Self::selected_columns(&self.all_columns, depth);
-            column_selected.retain(|c| !self.column_pinned.contains(c));
-            self.column_selected = column_selected;
+            self.select_columns(depth);
             if self.column_selected.is_empty() {
                 self.column_selected.push(Column {
                     name: Cow::from(""),
@@ -489,9 +799,18 @@ impl<'array> ArrayTable<'array> {
             )
             .unwrap();
             self.all_columns = new_columns;
-            let mut column_selected = Self::selected_columns(&self.all_columns, depth);
-            column_selected.retain(|c| !self.column_pinned.contains(c));
-            self.column_selected = column_selected;
+            let computed_columns = self
+                .computed_columns
+                .iter()
+                .map(|c| self.computed_column_header(c))
+                .collect::<Vec<Column<'array>>>();
+            self.all_columns.extend(computed_columns);
+            if self.is_grouped() {
+                self.aggregate_groups();
+            }
+            self.footer_aggregates = None;
+            self.formatting.clear_ranges();
+            self.select_columns(depth);
             self.nodes = new_json_array;
             self.last_parsed_max_depth = depth;
             self.parse_result.as_mut().unwrap().parsing_max_depth = depth;
@@ -508,6 +827,68 @@ impl<'array> ArrayTable<'array> {
         self.update_selected_columns(depth)
     }
 
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            depth: self.max_depth,
+            pinned_columns: self
+                .column_pinned
+                .iter()
+                .map(|c| c.name.to_string())
+                .collect(),
+            columns_filter: self.columns_filter.clone(),
+            computed_columns: self
+                .computed_columns
+                .iter()
+                .map(|c| (c.name.clone(), c.expression_source.clone()))
+                .collect(),
+            sort: self.sort.clone(),
+            group_by: self.group_by.clone(),
+            formatting_rules: self.formatting.rules.clone(),
+            id_column: self.id_column.clone(),
+            column_order: self.column_order.clone(),
+            hidden_columns: self.hidden_columns.iter().cloned().collect(),
+            column_widths: self
+                .column_widths
+                .iter()
+                .map(|(name, width)| (name.clone(), *width))
+                .collect(),
+            scroll_y: self.scroll_y,
+        }
+    }
+
+    /// Returns new max depth when restored depth required to parse json deeper.
+    pub fn restore_view_state(&mut self, view_state: ViewState) -> Option<usize> {
+        let new_max_depth = if view_state.depth != self.max_depth {
+            self.update_max_depth(view_state.depth)
+        } else {
+            None
+        };
+        for (name, expression) in view_state.computed_columns {
+            let _ = self.add_computed_column(&name, &expression);
+        }
+        self.column_order = view_state.column_order;
+        self.hidden_columns = view_state.hidden_columns.into_iter().collect();
+        self.column_widths = view_state.column_widths.into_iter().collect();
+        self.select_columns(self.max_depth);
+        for name in view_state.pinned_columns {
+            if let Some(index) = self.column_selected.iter().position(|c| c.name == name) {
+                let column = self.column_selected.remove(index);
+                self.column_pinned.push(column);
+            }
+        }
+        self.columns_filter = view_state.columns_filter;
+        self.sort = view_state.sort;
+        self.group_by = view_state.group_by;
+        self.formatting = Formatting::new(view_state.formatting_rules);
+        self.formatting_panel.set_rules(&self.formatting.rules);
+        self.id_column = view_state.id_column;
+        self.do_filter_column();
+        self.next_frame_reset_scroll = false;
+        self.scroll_y = view_state.scroll_y;
+        self.cache.borrow_mut().evict();
+        new_max_depth
+    }
+
     fn selected_columns(all_columns: &Vec<Column<'array>>, depth: u8) -> Vec<Column<'array>> {
         let mut column_selected: Vec<Column<'array>> = vec![];
         for col in Self::visible_columns(all_columns, depth) {
@@ -516,6 +897,109 @@ impl<'array> ArrayTable<'array> {
         column_selected
     }
 
+    /// Columns of this depth which are not pinned, in chosen order and without hidden ones.
+    fn select_columns(&mut self, depth: u8) {
+        let mut column_selected = Self::selected_columns(&self.all_columns, depth);
+        column_selected
+            .retain(|c| !self.column_pinned.contains(c) && !self.hidden_columns.contains(&*c.name));
+        Self::order_columns(&self.column_order, &mut column_selected);
+        self.column_selected = column_selected;
+    }
+
+    /// Sort columns by chosen order, keeping default order of columns missing from it.
+    fn order_columns(column_order: &[String], columns: &mut [Column<'array>]) {
+        if !column_order.is_empty() {
+            columns.sort_by_key(|c| {
+                column_order
+                    .iter()
+                    .position(|name| *name == c.name)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+    }
+
+    /// Name and visibility of columns of the columns panel, in table order.
+    fn column_layout(&self) -> Vec<(String, bool)> {
+        let mut columns = Self::selected_columns(&self.all_columns, self.max_depth);
+        columns.retain(|c| !c.name.is_empty() && !self.column_pinned.contains(c));
+        Self::order_columns(&self.column_order, &mut columns);
+        columns
+            .into_iter()
+            .map(|c| {
+                let visible = !self.hidden_columns.contains(&*c.name);
+                (c.name.to_string(), visible)
+            })
+            .collect()
+    }
+
+    /// Columns of other depths keep their place in the order and their visibility.
+    fn set_column_layout(&mut self, columns: Vec<(String, bool)>) {
+        let listed = columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<HashSet<String>>();
+        let mut column_order = columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>();
+        column_order.extend(
+            self.column_order
+                .iter()
+                .filter(|name| !listed.contains(*name))
+                .cloned(),
+        );
+        self.column_order = column_order;
+        self.hidden_columns.retain(|name| !listed.contains(name));
+        self.hidden_columns.extend(
+            columns
+                .into_iter()
+                .filter(|(_, visible)| !visible)
+                .map(|(name, _)| name),
+        );
+        self.select_columns(self.max_depth);
+        self.focused_cell = None;
+        self.cache.borrow_mut().evict();
+    }
+
+    /// Width of the widest drawn value of the column or of its name.
+    fn fit_column_width(&self, ui: &Ui, column: &Column) -> f32 {
+        let name = column.name.as_ref();
+        let computed_column = self.computed_column(name);
+        let rows = self.visible_rows.start.min(self.filtered_nodes.len())
+            ..self.visible_rows.end.min(self.filtered_nodes.len());
+        let content_width = rows
+            .filter(|table_row_index| !self.group_headers.contains_key(table_row_index))
+            .filter_map(|table_row_index| {
+                let row_index = self.filtered_nodes[table_row_index];
+                Self::cell_value(&self.nodes, computed_column, row_index, name)
+            })
+            .map(|value| CellText::text_width(ui, Self::drawn_text(value)))
+            .fold(CellText::text_width(ui, name), f32::max);
+        content_width + ui.spacing().item_spacing.x * 2.0
+    }
+
+    /// Fit every column but the row number one to its drawn values.
+    fn fit_columns(&mut self, ui: &Ui) {
+        let widths = self
+            .column_pinned
+            .iter()
+            .skip(1)
+            .chain(self.column_selected.iter())
+            .filter(|column| !column.name.is_empty())
+            .map(|column| (column.name.to_string(), self.fit_column_width(ui, column)))
+            .collect::<Vec<(String, f32)>>();
+        self.column_widths.extend(widths);
+    }
+
+    pub fn fit_all_columns(&mut self) {
+        self.next_frame_fit_columns = true;
+    }
+
+    pub fn open_columns_panel(&mut self) {
+        self.columns_panel.set_columns(self.column_layout());
+        set_open(&mut self.opened_windows, PANEL_COLUMNS, true);
+    }
+
     pub fn all_columns(&self) -> &Vec<Column<'array>> {
         &self.all_columns
     }
@@ -550,6 +1034,10 @@ impl<'array> ArrayTable<'array> {
         pinned_column_table: bool,
     ) -> ArrayResponse {
         use crate::components::table::{Column, TableBuilder};
+        if self.next_frame_fit_columns {
+            self.next_frame_fit_columns = false;
+            self.fit_columns(ui);
+        }
         let parent_height = ui.available_rect_before_wrap().height();
         let mut array_response = ArrayResponse::default();
         let mut table = TableBuilder::new(ui)
@@ -586,12 +1074,7 @@ impl<'array> ArrayTable<'array> {
                     if changed_scroll_to_row_value.elapsed().as_millis() >= 300 {
                         self.changed_scroll_to_row_value = None;
                         if !self.scroll_to_row.is_empty() {
-                            self.matching_rows =
-                                search_occurrences(&self.nodes, &self.scroll_to_row.to_lowercase());
-                            self.matching_row_selected = 0;
-                            if !self.matching_rows.is_empty() {
-                                self.changed_matching_row_selected = true;
-                            }
+                            self.search();
                         }
                     }
                 }
@@ -601,12 +1084,13 @@ impl<'array> ArrayTable<'array> {
             self.changed_arrow_vertical_scroll = false;
             table = table.scroll_to_row(self.scroll_to_row_number, Some(Align::Center));
         }
-        if self.changed_matching_row_selected {
-            self.changed_matching_row_selected = false;
-            table = table.scroll_to_row(
-                self.matching_rows[self.matching_row_selected],
-                Some(Align::Center),
-            );
+        if self.changed_matching_cell_selected {
+            self.changed_matching_cell_selected = false;
+            if let Some(table_row_index) =
+                self.matching_cells[self.matching_cell_selected].table_row_index
+            {
+                table = table.scroll_to_row(table_row_index, Some(Align::Center));
+            }
         }
         table = table.vertical_scroll_offset(self.scroll_y);
 
@@ -616,25 +1100,32 @@ impl<'array> ArrayTable<'array> {
             self.column_selected.len()
         };
         let columns = self.columns(pinned_column_table);
+        let saved_width = |i: usize| self.column_widths.get(columns[i].name.as_ref()).copied();
         if columns_count <= 3 {
             for i in 0..columns_count {
                 if pinned_column_table && i == 0 {
                     table = table.column(Column::initial(40.0).clip(true).resizable(true));
                 } else {
-                    table = table.column(Column::remainder().clip(true).resizable(true));
+                    table = table.column(
+                        Column::remainder()
+                            .clip(true)
+                            .resizable(true)
+                            .width(saved_width(i)),
+                    );
                 }
             }
         } else {
-            for i in 0..columns_count {
+            for (i, column) in columns.iter().enumerate() {
                 if pinned_column_table && i == 0 {
                     table = table.column(Column::initial(40.0).clip(true).resizable(true));
                 } else if i == columns_count - 1 {
                     table = table.column(Column::remainder().clip(false).resizable(true).range(Rangef::new(240.0, f32::INFINITY)));
                 } else {
                     table = table.column(
-                        Column::initial((columns[i].name.len() + 3).max(10) as f32 * text_width)
+                        Column::initial((column.name.len() + 3).max(10) as f32 * text_width)
                             .clip(true)
-                            .resizable(true),
+                            .resizable(true)
+                            .width(saved_width(i)),
                     );
                 }
                 // table = table.column(Column::initial(10.0).clip(true).resizable(true));
@@ -642,9 +1133,9 @@ impl<'array> ArrayTable<'array> {
             }
         }
 
-        let request_repaint = false;
-        let search_highlight_row = if !self.matching_rows.is_empty() {
-            Some(self.matching_rows[self.matching_row_selected])
+        let mut request_repaint = false;
+        let search_highlight_row = if !self.matching_cells.is_empty() {
+            self.matching_cells[self.matching_cell_selected].table_row_index
         } else {
             None
         };
@@ -655,25 +1146,64 @@ impl<'array> ArrayTable<'array> {
                 is_pinned_column_table,
             },
         ));
-        let table_response = table
-            .header(text_height * 2.0, |header| {
-                self.header(pinned_column_table, header);
-            })
-            .body(
-                self.hovered_row_index,
-                search_highlight_row,
-                focused_cell,
-                |body| {
-                    self.body(
-                        text_height,
-                        pinned_column_table,
-                        &mut array_response,
-                        request_repaint,
-                        body,
-                    );
-                },
-            );
+        let mut table = table.header(text_height * 2.0, |header| {
+            self.header(pinned_column_table, header);
+        });
+        if self.show_aggregates_footer {
+            let footer_cells = self.footer_cells(pinned_column_table);
+            table = table.footer(text_height * 4.0, move |mut footer| {
+                footer.cols(true, |ui, col_index| {
+                    let (text, tooltip) = &footer_cells[col_index];
+                    let response = ui.label(RichText::new(text).small());
+                    Some(if tooltip.is_empty() {
+                        response
+                    } else {
+                        response.on_hover_text(tooltip)
+                    })
+                });
+            });
+        }
+        let table_response = table.body(
+            self.hovered_row_index,
+            search_highlight_row,
+            focused_cell,
+            |body| {
+                self.body(
+                    text_height,
+                    pinned_column_table,
+                    &mut array_response,
+                    &mut request_repaint,
+                    body,
+                );
+            },
+        );
 
+        if pinned_column_table {
+            self.drop_dragged_rows(ui, &mut array_response);
+        }
+        let columns = self.columns(pinned_column_table);
+        let drawn_widths = columns
+            .iter()
+            .zip(table_response.column_widths.iter())
+            .map(|(column, width)| (column.name.to_string(), *width))
+            .collect::<Vec<(String, f32)>>();
+        // Row number column keeps its width
+        let mut widths = table_response
+            .resized_columns
+            .into_iter()
+            .filter(|(column_index, _)| !pinned_column_table || *column_index > 0)
+            .map(|(column_index, width)| (columns[column_index].name.to_string(), width))
+            .collect::<Vec<(String, f32)>>();
+        if let Some(column_index) = table_response
+            .fit_column
+            .filter(|column_index| !pinned_column_table || *column_index > 0)
+        {
+            let column = &columns[column_index];
+            widths.push((column.name.to_string(), self.fit_column_width(ui, column)));
+            ui.ctx().request_repaint();
+        }
+        self.drawn_column_widths.extend(drawn_widths);
+        self.column_widths.extend(widths);
         let table_scroll_output = table_response.scroll_area_output;
         if self.scroll_y != table_scroll_output.state.offset.y {
             self.scroll_y = table_scroll_output.state.offset.y;
@@ -697,11 +1227,19 @@ impl<'array> ArrayTable<'array> {
         let mut clicked_filter_column_value: Option<(String, String)> = None;
         let mut pinned_column: Option<usize> = None;
         let mut clicked_replace_column: Option<usize> = None;
+        let mut clicked_sort_column: Option<String> = None;
+        let mut clicked_group_column: Option<String> = None;
+        let mut clicked_chart_column: Option<String> = None;
+        let mut clicked_computed_column = false;
         header.cols(true, |ui, index| {
             let columns = self.columns(pinned_column_table);
             let column = columns.get(index).unwrap();
             let name = column.name.as_str();
-            let strong = Label::new(WidgetText::RichText(egui::RichText::from(name)));
+            let mut text = RichText::from(name);
+            if self.is_matching_key(name) {
+                text = text.color(Color32::BLACK).background_color(HIGHLIGHT_COLOR);
+            }
+            let strong = Label::new(WidgetText::RichText(text));
             let label = Label::new(name);
             let response = ui.vertical(|ui| {
                 let response = ui.add(strong).on_hover_ui(|ui| {
@@ -710,7 +1248,7 @@ impl<'array> ArrayTable<'array> {
 
                 if !pinned_column_table || index > 0 {
                     ui.horizontal(|ui| {
-                        if column.name.eq("") {
+                        if column.name.is_empty() {
                             return;
                         }
                         let response = icon::button(
@@ -726,6 +1264,43 @@ impl<'array> ArrayTable<'array> {
                         if response.clicked() {
                             pinned_column = Some(index);
                         }
+                        let sort_icon = match self.sort {
+                            Some((ref sorted_column, false)) if sorted_column == name => {
+                                Some(SORT_UP)
+                            }
+                            Some((ref sorted_column, true)) if sorted_column == name => {
+                                Some(SORT_DOWN)
+                            }
+                            _ => None,
+                        };
+                        let response = icon::button(
+                            ui,
+                            sort_icon.unwrap_or(SORT),
+                            Some("Sort rows by this column"),
+                            sort_icon.map(|_| ACTIVE_COLOR),
+                        );
+                        if response.clicked() {
+                            clicked_sort_column = Some(name.to_string());
+                        }
+                        let is_grouped_by = self.group_by.iter().any(|c| c == name);
+                        let response = icon::button(
+                            ui,
+                            LAYER_GROUP,
+                            Some(if is_grouped_by {
+                                "Ungroup rows by this column"
+                            } else {
+                                "Group rows by this column"
+                            }),
+                            is_grouped_by.then_some(ACTIVE_COLOR),
+                        );
+                        if response.clicked() {
+                            clicked_group_column = Some(name.to_string());
+                        }
+                        let response =
+                            icon::button(ui, CHART_SIMPLE, Some("Chart of this column"), None);
+                        if response.clicked() {
+                            clicked_chart_column = Some(name.to_string());
+                        }
                         let column_id = Id::new(name);
                         let checked_filtered_values = self.columns_filter.get(column.name.as_str());
                         PopupMenu::new(column_id.with("filter")).show_ui(
@@ -760,8 +1335,8 @@ impl<'array> ArrayTable<'array> {
                                             CacheFilterOptions,
                                         >>();
 
-                                    let values = cache
-                                        .get((column, &self.parent_pointer.pointer), &self.nodes);
+                                    let values =
+                                        cache.get((column, &self.parent_pointer.pointer), self);
                                     if !values.is_empty() {
                                         let checked_filtered_values =
                                             self.columns_filter.get(column.name.as_str());
@@ -783,7 +1358,13 @@ impl<'array> ArrayTable<'array> {
                             },
                         );
 
-                        if SearchReplacePanel::can_be_replaced(column) {
+                        if let Some(computed_column) = self.computed_column(name) {
+                            let tooltip =
+                                concat_string!("Computed: ", computed_column.expression_source);
+                            if icon::button(ui, CALCULATOR, Some(&tooltip), None).clicked() {
+                                clicked_computed_column = true;
+                            }
+                        } else if SearchReplacePanel::can_be_replaced(column) {
                             let response =
                                 icon::button(ui, SEARCH, Some("Replace in column"), None);
                             if response.clicked() {
@@ -802,12 +1383,25 @@ impl<'array> ArrayTable<'array> {
                 let column = self.column_pinned.remove(pinned_column);
                 self.column_selected.push(column);
                 self.column_selected.sort();
+                Self::order_columns(&self.column_order, &mut self.column_selected);
             } else {
                 let column = self.column_selected.remove(pinned_column);
                 self.column_pinned.push(column);
             }
             self.cache.borrow_mut().evict();
         }
+        if let Some(column) = clicked_sort_column {
+            self.toggle_sort(column);
+        }
+        if let Some(column) = clicked_group_column {
+            self.toggle_group_by(column);
+        }
+        if let Some(column) = clicked_chart_column {
+            self.open_chart_panel(Some(column));
+        }
+        if clicked_computed_column {
+            self.open_computed_columns_panel();
+        }
         if let Some(replace_column) = clicked_replace_column {
             let column = self.columns(pinned_column_table)[replace_column].clone();
             self.open_replace_panel(Some(column));
@@ -825,7 +1419,7 @@ impl<'array> ArrayTable<'array> {
         text_height: f32,
         pinned_column_table: bool,
         array_response: &mut ArrayResponse,
-        mut request_repaint: bool,
+        request_repaint: &mut bool,
         body: TableBody,
     ) {
         // Mutation after interaction
@@ -834,12 +1428,67 @@ impl<'array> ArrayTable<'array> {
         let mut focused_changed = false;
         let mut updated_value: Option<(PointerKey, String)> = None;
         let mut filter_by_value: Option<(String, String)> = None; // col name, value
-        let mut insert_row_at_index: Option<(usize, u8)> = None; // table_row_index, 0 = above, 1 = below
+        let mut insert_row_at_index: Option<(usize, u8, NewRow)> = None; // table_row_index, 0 = above, 1 = below
+        let mut delete_row_at_index: Option<usize> = None; // table_row_index
+        let mut toggled_id_column: Option<String> = None;
+        let mut toggled_group: Option<usize> = None;
+        let mut collapse_all_groups: Option<bool> = None;
+        let mut clicked_row: Option<(usize, Modifiers)> = None;
+        let mut moved_rows: Option<(usize, bool)> = None; // row_index, up
+        let mut drag_started_row: Option<usize> = None;
+        let mut row_drop_at: Option<usize> = None;
+        let can_move_rows = self.can_move_rows();
         let columns = self.columns(pinned_column_table);
-        let hover_data = body.rows(text_height, self.filtered_nodes.len(), |mut row| {
+        let mut visible_rows: Option<Range<usize>> = None;
+        let add_row_content = |mut row: TableRow<'_, '_>| {
             let table_row_index = row.index();
+            visible_rows
+                .get_or_insert(table_row_index..table_row_index)
+                .end = table_row_index + 1;
+            if let Some(group_index) = self.group_headers.get(&table_row_index) {
+                let group = &self.groups[*group_index];
+                row.cols(false, |ui, col_index| {
+                    let column_name = columns[col_index].name.as_ref();
+                    let response = if pinned_column_table && col_index == 0 {
+                        let (icon, tooltip) = if group.collapsed {
+                            (CARET_RIGHT, "Expand group")
+                        } else {
+                            (CARET_DOWN, "Collapse group")
+                        };
+                        icon::button(ui, icon, Some(tooltip), None)
+                    } else if let Some(position) =
+                        self.group_by.iter().position(|c| c == column_name)
+                    {
+                        let value = group.key[position].as_deref().unwrap_or("null");
+                        let label = format!("{} ({})", value, group.rows.len());
+                        ui.add(Label::new(RichText::new(label).strong()).sense(Sense::click()))
+                    } else if let Some(aggregates) = group.aggregates.get(column_name) {
+                        let label =
+                            RichText::new(aggregates.format(self.group_aggregate)).italics();
+                        ui.add(Label::new(label).sense(Sense::click()))
+                            .on_hover_text(aggregates.summary())
+                    } else {
+                        ui.interact(
+                            ui.available_rect_before_wrap(),
+                            Id::new((self.seed1, *group_index, col_index, pinned_column_table)),
+                            Sense::click(),
+                        )
+                    };
+                    if response.clicked() {
+                        toggled_group = Some(*group_index);
+                    }
+                    Some(response)
+                });
+                return;
+            }
             let row_index = self.filtered_nodes[table_row_index];
             let node = self.nodes().get(row_index);
+            row.set_selected(self.selected_rows.contains(&row_index));
+            if self.formatting.has_row_rules() {
+                row.set_background(self.formatting.row_background(|column| {
+                    Self::cell_value(&self.nodes, self.computed_column(column), row_index, column)
+                }));
+            }
 
             if let Some(row_data) = node.as_ref() {
                 row.cols(false, |ui, col_index| {
@@ -853,6 +1502,43 @@ impl<'array> ArrayTable<'array> {
                     let index =
                         self.get_pointer_index_from_cache(pinned_column_table, row_data, col_index);
                     let mut editing_index = self.editing_index.borrow_mut();
+                    if let Some(computed_column) = self.computed_column(&columns[col_index].name) {
+                        // Read only cell
+                        if *editing_index == Some((col_index, row_index, pinned_column_table)) {
+                            *editing_index = None;
+                        }
+                        let response = match computed_column.value(row_index) {
+                            Some(value) => {
+                                let style = self
+                                    .formatting
+                                    .cell_style(&computed_column.name, Some(value));
+                                CellText::new(Self::drawn_text(value))
+                                    .color(style.color)
+                                    .background(style.background)
+                                    .highlights(self.search_highlights(value))
+                                    .wrap(self.wrap_rows.then_some(MAX_WRAPPED_LINES))
+                                    .ui(ui, cell_id)
+                            }
+                            None => ui.interact(
+                                ui.available_rect_before_wrap(),
+                                Id::new(cell_id),
+                                Sense::click(),
+                            ),
+                        };
+                        if response.secondary_clicked() || response.clicked() {
+                            focused_cell = Some(CellLocation {
+                                column_index: col_index,
+                                row_index: table_row_index,
+                                is_pinned_column_table: pinned_column_table,
+                            });
+                            ui.ctx().memory_mut(|m| m.request_focus(self.table_id));
+                            focused_changed = true;
+                        }
+                        if response.clicked() {
+                            clicked_row = Some((row_index, ui.input(|i| i.modifiers)));
+                        }
+                        return Some(response);
+                    }
                     if editing_index.is_some()
                         && editing_index.unwrap() == (col_index, row_index, pinned_column_table)
                     {
@@ -868,8 +1554,7 @@ impl<'array> ArrayTable<'array> {
                         {
                             let pointer = PointerKey {
                                 pointer: Self::pointer_key(
-                                    &self.parent_pointer.pointer,
-                                    row_index,
+                                    &row_data.entries,
                                     &columns.get(col_index).as_ref().unwrap().name,
                                 ),
                                 value_type: columns[col_index].value_type,
@@ -891,15 +1576,57 @@ impl<'array> ArrayTable<'array> {
                         let entry = &row_data.entries()[index];
 
                         if pinned_column_table && col_index == 0 {
-                            let label = Label::new(row_index.to_string());
-                            return Some(label.ui(ui));
+                            let mut response = Label::new(row_index.to_string())
+                                .sense(if can_move_rows {
+                                    Sense::click_and_drag()
+                                } else {
+                                    Sense::click()
+                                })
+                                .ui(ui);
+                            if response.clicked() {
+                                clicked_row = Some((row_index, ui.input(|i| i.modifiers)));
+                            }
+                            if can_move_rows {
+                                response = response.on_hover_cursor(CursorIcon::Grab);
+                                if response.drag_started() {
+                                    drag_started_row = Some(row_index);
+                                }
+                            }
+                            if self.row_drag.is_some() {
+                                // Drop before or after this row depending on pointer position
+                                let rect = ui.max_rect();
+                                if let Some(pointer) = ui
+                                    .ctx()
+                                    .pointer_interact_pos()
+                                    .filter(|pointer| rect.y_range().contains(pointer.y))
+                                {
+                                    let after = pointer.y > rect.center().y;
+                                    row_drop_at = Some(table_row_index + after as usize);
+                                    let y = if after {
+                                        rect.bottom() - 1.0
+                                    } else {
+                                        rect.top() + 1.0
+                                    };
+                                    ui.painter().hline(
+                                        rect.x_range(),
+                                        y,
+                                        ui.visuals().selection.stroke,
+                                    );
+                                }
+                            }
+                            return Some(
+                                self.highlight_schema_errors(ui, response, row_index, None),
+                            );
                         } else if let Some(value) = entry.value.as_ref() {
                             if !matches!(entry.pointer.value_type, ValueType::Null) {
-                                let label = if value.len() > 1000 {
-                                    CellText::new(&value[0..1000])
-                                } else {
-                                    CellText::new(value)
-                                };
+                                let style = self
+                                    .formatting
+                                    .cell_style(&columns[col_index].name, Some(value));
+                                let label = CellText::new(Self::drawn_text(value))
+                                    .color(style.color)
+                                    .background(style.background)
+                                    .highlights(self.search_highlights(value))
+                                    .wrap(self.wrap_rows.then_some(MAX_WRAPPED_LINES));
 
                                 let mut response = label.ui(ui, cell_id);
 
@@ -919,6 +1646,9 @@ impl<'array> ArrayTable<'array> {
 
                                     focused_changed = true;
                                 }
+                                if response.clicked() {
+                                    clicked_row = Some((row_index, ui.input(|i| i.modifiers)));
+                                }
 
                                 if response.hovered() {
                                     ui.ctx().set_cursor_icon(CursorIcon::Cell);
@@ -933,7 +1663,12 @@ impl<'array> ArrayTable<'array> {
                                         });
                                     });
                                 };
-                                return Some(response);
+                                return Some(self.highlight_schema_errors(
+                                    ui,
+                                    response,
+                                    row_index,
+                                    Some(columns[col_index].name.as_ref()),
+                                ));
                             }
                         }
                     }
@@ -954,6 +1689,9 @@ impl<'array> ArrayTable<'array> {
                         ui.ctx().memory_mut(|m| m.request_focus(self.table_id));
                         focused_changed = true;
                     }
+                    if response.clicked() {
+                        clicked_row = Some((row_index, ui.input(|i| i.modifiers)));
+                    }
 
                     if response.hovered() {
                         ui.ctx().set_cursor_icon(CursorIcon::Cell);
@@ -961,16 +1699,39 @@ impl<'array> ArrayTable<'array> {
                     if updated_value.is_some() {
                         ui.ctx().memory_mut(|m| m.request_focus(self.table_id));
                     }
-                    Some(response)
+                    Some(self.highlight_schema_errors(
+                        ui,
+                        response,
+                        row_index,
+                        Some(columns[col_index].name.as_ref()),
+                    ))
                 });
             }
-        });
+        };
+        // Rows filtered or inserted this frame are measured next frame
+        let rows_count = self.filtered_nodes.len();
+        let hover_data = if self.wrap_rows && self.row_offsets.len() == rows_count + 1 {
+            body.heterogeneous_rows(&self.row_offsets, add_row_content)
+        } else {
+            body.rows(text_height, rows_count, add_row_content)
+        };
         // Context menu
         if let Some(ref hover_cell) = hover_data.hovered_cell {
             if let Some(ref response) = hover_data.response_rows {
                 response.context_menu(|ui| {
                     let table_row_index = hover_cell.row_index;
                     let col_index = hover_cell.column_index;
+                    if self.is_group_header(table_row_index) {
+                        if ui.button("Expand all groups").clicked() {
+                            collapse_all_groups = Some(false);
+                            ui.close_menu();
+                        }
+                        if ui.button("Collapse all groups").clicked() {
+                            collapse_all_groups = Some(true);
+                            ui.close_menu();
+                        }
+                        return;
+                    }
                     let row_index = self.filtered_nodes.get(table_row_index);
                     if let Some(row_index) = row_index {
                         let row_index = *row_index;
@@ -1023,16 +1784,74 @@ impl<'array> ArrayTable<'array> {
                             // Context menu: insert row above
                             let button = ButtonWithIcon::new("Insert row above", PLUS);
                             if ui.add(button).clicked() {
-                                insert_row_at_index = Some((table_row_index, 0));
+                                insert_row_at_index = Some((table_row_index, 0, NewRow::Empty));
                                 ui.close_menu();
                             }
 
                             // Context menu: insert row below
                             let button = ButtonWithIcon::new("Insert row below", PLUS);
                             if ui.add(button).clicked() {
-                                insert_row_at_index = Some((table_row_index, 1));
+                                insert_row_at_index = Some((table_row_index, 1, NewRow::Empty));
+                                ui.close_menu();
+                            }
+                            // Context menu: delete row
+                            let button = ButtonWithIcon::new("Delete row", TRASH_CAN);
+                            if ui.add(button).clicked() {
+                                delete_row_at_index = Some(table_row_index);
+                                ui.close_menu();
+                            }
+                            // Context menu: move rows
+                            let button = ButtonWithIcon::new("Move up", ARROW_UP)
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_MOVE_ROWS_UP));
+                            if ui.add_enabled(can_move_rows, button).clicked() {
+                                moved_rows = Some((row_index, true));
+                                ui.close_menu();
+                            }
+                            let button = ButtonWithIcon::new("Move down", ARROW_DOWN)
+                                .shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_MOVE_ROWS_DOWN));
+                            if ui.add_enabled(can_move_rows, button).clicked() {
+                                moved_rows = Some((row_index, false));
+                                ui.close_menu();
+                            }
+                            // Context menu: duplicate row
+                            let button = ButtonWithIcon::new("Duplicate row", CLONE);
+                            if ui.add(button).clicked() {
+                                insert_row_at_index = Some((table_row_index, 1, NewRow::Duplicate));
+                                ui.close_menu();
+                            }
+                            // Context menu: insert row from template
+                            let button =
+                                ButtonWithIcon::new("Insert row from template", FILE_CIRCLE_PLUS);
+                            if ui
+                                .add(button)
+                                .on_hover_text(
+                                    "Same keys as this row, with empty strings, 0, false or null",
+                                )
+                                .clicked()
+                            {
+                                insert_row_at_index = Some((table_row_index, 1, NewRow::Template));
                                 ui.close_menu();
                             }
+                            // Context menu: auto-increment column
+                            let column = &columns[col_index];
+                            if matches!(column.value_type, ValueType::Number)
+                                && self.computed_column(&column.name).is_none()
+                            {
+                                let is_id_column =
+                                    self.id_column.as_deref() == Some(column.name.as_str());
+                                let button = ButtonWithIcon::new(
+                                    if is_id_column {
+                                        "Stop auto-incrementing this column"
+                                    } else {
+                                        "Auto-increment this column in new rows"
+                                    },
+                                    ARROW_UP_1_9,
+                                );
+                                if ui.add(button).clicked() {
+                                    toggled_id_column = Some(column.name.to_string());
+                                    ui.close_menu();
+                                }
+                            }
                             // Context menu: Open array or object in subtable
                             if let Some(entry) = edit_entry {
                                 let is_array =
@@ -1051,7 +1870,7 @@ impl<'array> ArrayTable<'array> {
                                     if ui.add(button).clicked() {
                                         ui.close_menu();
                                         let content = edit_value.clone();
-                                        subtable = Self::open_subtable(row_index, entry, content);
+                                        subtable = self.open_subtable(row_index, entry, content);
                                     }
                                 }
                             }
@@ -1063,11 +1882,20 @@ impl<'array> ArrayTable<'array> {
                                 if ui.add(button).clicked() {
                                     ui.close_menu();
                                     let root_node = row_data.entries.last().unwrap();
+                                    let pointer = PointerKey {
+                                        pointer: self.absolute_pointer(
+                                            row_index,
+                                            &root_node.pointer.pointer,
+                                        ),
+                                        ..root_node.pointer.clone()
+                                    };
+                                    let row_pointer = pointer.pointer.clone();
                                     subtable = Some(SubTable::new(
-                                        root_node.pointer.clone(),
+                                        pointer,
                                         root_node.value.as_ref().unwrap().clone(),
                                         ValueType::Object(true, 0),
                                         row_index,
+                                        row_pointer,
                                         root_node.pointer.depth,
                                     ));
                                 }
@@ -1076,7 +1904,9 @@ impl<'array> ArrayTable<'array> {
                             if let Some(entry) = edit_entry {
                                 ui.separator();
                                 if ui.button("Copy pointer").clicked() {
-                                    ui.ctx().copy_text(entry.pointer.pointer.clone());
+                                    ui.ctx().copy_text(
+                                        self.absolute_pointer(row_index, &entry.pointer.pointer),
+                                    );
                                     ui.close_menu();
                                 }
                             }
@@ -1089,14 +1919,48 @@ impl<'array> ArrayTable<'array> {
         if focused_changed {
             self.focused_cell = focused_cell;
         }
+        if let Some((row_index, modifiers)) = clicked_row {
+            self.select_row(row_index, modifiers);
+        }
         if let Some(subtable) = subtable {
             self.windows.push(subtable);
         }
         if let Some((column_name, filter_value)) = filter_by_value {
             self.on_filter_column_value((column_name, filter_value));
         }
-        if let Some((table_row_index, above_or_below)) = insert_row_at_index {
-            self.insert_new_row(table_row_index, above_or_below);
+        if let Some(group_index) = toggled_group {
+            self.toggle_group(group_index);
+        }
+        if let Some(collapsed) = collapse_all_groups {
+            self.collapse_all_groups(collapsed);
+        }
+        if let Some(column) = toggled_id_column {
+            self.id_column = if self.id_column.as_ref() == Some(&column) {
+                None
+            } else {
+                Some(column)
+            };
+        }
+        if let Some((table_row_index, above_or_below, new_row)) = insert_row_at_index {
+            self.insert_new_row(table_row_index, above_or_below, new_row, array_response);
+        }
+        if let Some(table_row_index) = delete_row_at_index {
+            self.delete_row(table_row_index, array_response);
+        }
+        if let Some((row_index, up)) = moved_rows {
+            let rows = self.rows_to_move(row_index);
+            self.move_rows_by_one(rows, up, array_response);
+        }
+        if let Some(row_index) = drag_started_row {
+            self.row_drag = Some(RowDrag {
+                rows: self.rows_to_move(row_index),
+                drop_at: None,
+            });
+        }
+        if let Some(ref mut row_drag) = self.row_drag {
+            if pinned_column_table {
+                row_drag.drop_at = row_drop_at;
+            }
         }
         if let Some((pointer, value)) = updated_value {
             let editing_index = mem::take(&mut *self.editing_index.borrow_mut());
@@ -1112,9 +1976,10 @@ impl<'array> ArrayTable<'array> {
         }
         if self.hovered_row_index != hover_data.hovered_row {
             self.hovered_row_index = hover_data.hovered_row;
-            request_repaint = true;
+            *request_repaint = true;
         }
         array_response.hover_data = hover_data;
+        self.visible_rows = visible_rows.unwrap_or_default();
     }
 
     fn edit_cell(
@@ -1127,33 +1992,9 @@ impl<'array> ArrayTable<'array> {
             let value_changed = self.update_value(new_entry, row_index, false);
 
             if value_changed {
-                let mut entries = self
-                    .nodes
-                    .iter()
-                    .flat_map(|row| row.entries.clone())
-                    .collect::<Vec<FlatJsonValue<String>>>();
-                let mut parent_pointer = PointerKey {
-                    pointer: String::new(),
-                    value_type: ValueType::Array(self.nodes.len()),
-                    depth: 0,
-                    position: 0,
-                    column_id: 0,
-                };
-                entries.push(FlatJsonValue {
-                    pointer: parent_pointer.clone(),
-                    value: None,
-                });
-                // entries.iter().for_each(|e| println!("{} -> {:?}", e.pointer.pointer, e.value));
-                let updated_array = serialize_to_json_with_option::<String>(
-                    &mut entries,
-                    self.parent_pointer.depth + 1,
-                )
-                .to_json();
-                parent_pointer.pointer = self.parent_pointer.pointer.clone();
-                array_response.edited_value.push(FlatJsonValue {
-                    pointer: parent_pointer,
-                    value: Some(updated_array),
-                });
+                array_response
+                    .edited_value
+                    .push(self.serialized_sub_table());
             }
         } else {
             let value_changed = self.update_value(new_entry.clone(), row_index, true);
@@ -1163,50 +2004,433 @@ impl<'array> ArrayTable<'array> {
         }
     }
 
-    fn insert_new_row(&mut self, table_row_index: usize, above_or_below: u8) {
-        let row_index = self.filtered_nodes[table_row_index];
-        let depth = self.nodes[row_index].entries.last().unwrap().pointer.depth;
-        let new_table_row_index = table_row_index + above_or_below as usize;
-        let new_index = row_index + above_or_below as usize;
-        for i in new_table_row_index..self.filtered_nodes.len() {
-            self.filtered_nodes[i] += 1;
-        }
-        // Performance are not good on large json but hopefully the feature is used rarely
-        // We need to update all json pointer coming after the new row
-        // For that we substring the pointer to remove the "prefix" containing the index in the json array
-        let substring_len = self.parent_pointer.pointer.len() + 1;
-        for i in new_index..self.nodes.len() {
-            self.nodes[i].index = i + 1;
-            let substring_len = substring_len + (i.checked_ilog10().unwrap_or(0) + 1) as usize;
-            let new_prefix = concat_string!(self.parent_pointer.pointer, "/", (i + 1).to_string());
-            self.nodes[i].entries.iter_mut().for_each(|e| {
-                e.pointer.pointer = concat_string!(new_prefix, e.pointer.pointer[substring_len..]);
+    /// Whole array of this sub table serialized, as new value of the parent table cell.
+    fn serialized_sub_table(&self) -> FlatJsonValue<String> {
+        // Rows are serialized at their index, not at the pointer they were parsed at
+        let mut entries = self
+            .nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(row_index, row)| {
+                row.entries.iter().map(move |entry| FlatJsonValue {
+                    pointer: PointerKey {
+                        pointer: self.absolute_pointer(row_index, &entry.pointer.pointer),
+                        ..entry.pointer.clone()
+                    },
+                    value: entry.value.clone(),
+                })
             })
+            .collect::<Vec<FlatJsonValue<String>>>();
+        let mut parent_pointer = PointerKey {
+            pointer: String::new(),
+            value_type: ValueType::Array(self.nodes.len()),
+            depth: 0,
+            position: 0,
+            column_id: 0,
+        };
+        entries.push(FlatJsonValue {
+            pointer: parent_pointer.clone(),
+            value: None,
+        });
+        // entries.iter().for_each(|e| println!("{} -> {:?}", e.pointer.pointer, e.value));
+        let updated_array =
+            serialize_to_json_with_option::<String>(&mut entries, self.parent_pointer.depth + 1)
+                .to_json();
+        parent_pointer.pointer = self.parent_pointer.pointer.clone();
+        FlatJsonValue {
+            pointer: parent_pointer,
+            value: Some(updated_array),
+        }
+    }
+
+    /// Apply edits made in the sub table of a row, `row_pointer` is the pointer of the row when the
+    /// sub table was opened.
+    fn update_from_sub_table(
+        &mut self,
+        row_index: usize,
+        row_pointer: &str,
+        edited_value: Vec<FlatJsonValue<String>>,
+        array_response: &mut ArrayResponse,
+    ) {
+        for mut entry in edited_value {
+            // Sub tables see pointers of the document
+            entry.pointer.pointer =
+                self.stored_pointer(row_index, row_pointer, &entry.pointer.pointer);
+            if self.update_value(entry.clone(), row_index, false) {
+                array_response.edited_value.push(entry)
+            }
+        }
+    }
+
+    /// Keep sub tables of moved rows opened, `new_index` gives `None` for deleted rows.
+    fn shift_windows(&mut self, new_index: impl Fn(usize) -> Option<usize>) {
+        let parent_pointer = self.parent_pointer.pointer.as_str();
+        self.windows.retain_mut(|window| {
+            if window.detached {
+                return true;
+            }
+            let Some(row_index) = new_index(window.id()) else {
+                return false;
+            };
+            if row_index != window.id() {
+                let row_pointer = concat_string!(parent_pointer, "/", window.id().to_string());
+                let name = match window.name().strip_prefix(row_pointer.as_str()) {
+                    Some(key) => concat_string!(parent_pointer, "/", row_index.to_string(), key),
+                    None => window.name().clone(),
+                };
+                window.set_row_index(row_index, name);
+            }
+            true
+        });
+    }
+
+    fn insert_new_row(
+        &mut self,
+        table_row_index: usize,
+        above_or_below: u8,
+        new_row: NewRow,
+        array_response: &mut ArrayResponse,
+    ) {
+        let row_index = self.filtered_nodes[table_row_index];
+        let depth = self.nodes[row_index].entries.last().unwrap().pointer.depth;
+        let new_table_row_index = table_row_index + above_or_below as usize;
+        let new_index = row_index + above_or_below as usize;
+        // Following rows keep their pointer, new row gets one no other row has
+        let new_entry_pointer = concat_string!(
+            self.parent_pointer.pointer,
+            "/",
+            self.next_row_pointer_index.to_string()
+        );
+        self.next_row_pointer_index += 1;
+        let entries = match new_row {
+            NewRow::Empty => vec![
+                row_number_entry(new_index, 0, new_entry_pointer.as_str()),
+                FlatJsonValue {
+                    pointer: PointerKey {
+                        pointer: new_entry_pointer.clone(),
+                        value_type: ValueType::Object(true, 0),
+                        depth,
+                        position: 0,
+                        column_id: 0,
+                    },
+                    value: Some("{}".to_string()),
+                },
+            ],
+            NewRow::Duplicate | NewRow::Template => {
+                let row_pointer = row_pointer(&self.nodes[row_index].entries);
+                self.nodes[row_index]
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        let column = &entry.pointer.pointer[row_pointer.len()..];
+                        if column == "/#" {
+                            return row_number_entry(new_index, 0, new_entry_pointer.as_str());
+                        }
+                        let mut entry = entry.clone();
+                        entry.pointer.pointer = concat_string!(new_entry_pointer, column);
+                        if matches!(new_row, NewRow::Template) {
+                            entry.value =
+                                template_value(entry.pointer.value_type, entry.value.as_deref());
+                        }
+                        entry
+                    })
+                    .collect()
+            }
+        };
+        // Rows may be sorted, so shift by row index rather than by position in the table
+        self.filtered_nodes
+            .iter_mut()
+            .filter(|i| **i >= new_index)
+            .for_each(|i| *i += 1);
+        for row in self.nodes[new_index..].iter_mut() {
+            row.index += 1;
         }
-        let new_entry_pointer =
-            concat_string!(self.parent_pointer.pointer, "/", new_index.to_string());
         self.nodes.insert(
             new_index,
             JsonArrayEntries {
-                entries: vec![
-                    row_number_entry(new_index, 0, new_entry_pointer.as_str()),
-                    FlatJsonValue {
-                        pointer: PointerKey {
-                            pointer: new_entry_pointer,
-                            value_type: ValueType::Object(true, 0),
-                            depth,
-                            position: 0,
-                            column_id: 0,
-                        },
-                        value: Some("{}".to_string()),
-                    },
-                ],
+                entries,
                 index: new_index,
             },
         );
+        self.filtered_nodes.insert(new_table_row_index, new_index);
+        self.selected_rows = mem::take(&mut self.selected_rows)
+            .into_iter()
+            .map(|i| if i >= new_index { i + 1 } else { i })
+            .collect();
+        self.selection_anchor = None;
+        self.footer_aggregates = None;
+        if let Some(ref mut query_rows) = self.query_rows {
+            // Keep new row visible while query is applied
+            let position = query_rows.partition_point(|row_index| *row_index < new_index);
+            query_rows[position..]
+                .iter_mut()
+                .for_each(|row_index| *row_index += 1);
+            query_rows.insert(position, new_index);
+        }
+        self.schema_errors = mem::take(&mut self.schema_errors)
+            .into_iter()
+            .map(|(i, errors)| (if i >= new_index { i + 1 } else { i }, errors))
+            .collect();
+        self.shift_windows(|i| Some(if i >= new_index { i + 1 } else { i }));
+        self.validate_row(new_index);
+        for computed_column in self.computed_columns.iter_mut() {
+            let value = computed_column.compute_row(&self.nodes[new_index]);
+            computed_column.values.insert(new_index, value);
+        }
+        self.formatting.clear_ranges();
+        self.cache.borrow_mut().evict();
+        if self.is_sub_table {
+            array_response
+                .edited_value
+                .push(self.serialized_sub_table());
+        } else if let Some(root_entry) = self.nodes[new_index].entries.last() {
+            array_response.edited_value.push(root_entry.clone());
+        }
+        if let Some(id_column) = self.id_column.clone() {
+            self.set_next_id(&id_column, new_index, array_response);
+        }
+        if self.is_grouped() {
+            self.do_filter_column();
+        }
+    }
+
+    /// Following rows keep their pointers, only their index is shifted.
+    fn delete_row(&mut self, table_row_index: usize, array_response: &mut ArrayResponse) {
+        let row_index = self.filtered_nodes[table_row_index];
+        let deleted_row = self.nodes.remove(row_index);
+        for row in self.nodes[row_index..].iter_mut() {
+            row.index -= 1;
+        }
+        let shift = |i: usize| if i > row_index { i - 1 } else { i };
+        self.filtered_nodes.retain(|i| *i != row_index);
+        self.filtered_nodes.iter_mut().for_each(|i| *i = shift(*i));
+        self.selected_rows = mem::take(&mut self.selected_rows)
+            .into_iter()
+            .filter(|i| *i != row_index)
+            .map(shift)
+            .collect();
+        self.selection_anchor = None;
+        self.footer_aggregates = None;
+        if let Some(ref mut query_rows) = self.query_rows {
+            query_rows.retain(|i| *i != row_index);
+            query_rows.iter_mut().for_each(|i| *i = shift(*i));
+        }
+        self.schema_errors = mem::take(&mut self.schema_errors)
+            .into_iter()
+            .filter(|(i, _)| *i != row_index)
+            .map(|(i, errors)| (shift(i), errors))
+            .collect();
+        for computed_column in self.computed_columns.iter_mut() {
+            computed_column.values.remove(row_index);
+        }
+        self.shift_windows(|i| (i != row_index).then(|| shift(i)));
+        let rows_count = self.filtered_nodes.len();
+        self.focused_cell = self.focused_cell.filter(|cell| cell.row_index < rows_count);
+        self.formatting.clear_ranges();
+        self.cache.borrow_mut().evict();
+        if self.is_sub_table {
+            array_response
+                .edited_value
+                .push(self.serialized_sub_table());
+        } else if let Some(mut root_entry) = deleted_row.entries.into_iter().last() {
+            // Row root without value stands for the deleted row
+            root_entry.value = None;
+            array_response.edited_value.push(root_entry);
+        }
+        if self.is_grouped() {
+            self.do_filter_column();
+        }
+    }
+
+    /// Rows are moved in array order, which is the table order unless rows are sorted or grouped.
+    fn can_move_rows(&self) -> bool {
+        self.sort.is_none() && !self.is_grouped()
+    }
+
+    /// Selected rows when this row is selected, otherwise this row only.
+    fn rows_to_move(&self, row_index: usize) -> Vec<usize> {
+        if self.selected_rows.contains(&row_index) {
+            self.selected_rows.iter().copied().collect()
+        } else {
+            vec![row_index]
+        }
+    }
+
+    /// Move rows past the previous, or next, visible row.
+    fn move_rows_by_one(&mut self, rows: Vec<usize>, up: bool, array_response: &mut ArrayResponse) {
+        let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+            return;
+        };
+        if !self.can_move_rows() {
+            return;
+        }
+        let is_moved = |row_index: &usize| rows.binary_search(row_index).is_ok();
+        let before = if up {
+            self.filtered_nodes
+                .iter()
+                .rev()
+                .find(|row_index| **row_index < *first && !is_moved(row_index))
+                .copied()
+        } else {
+            self.filtered_nodes
+                .iter()
+                .find(|row_index| **row_index > *last && !is_moved(row_index))
+                .map(|row_index| row_index + 1)
+        };
+        if let Some(before) = before {
+            self.move_rows(&rows, before, array_response);
+        }
+    }
+
+    /// Move dragged rows where pointer is released, nowhere when it is released outside of rows.
+    fn drop_dragged_rows(&mut self, ui: &Ui, array_response: &mut ArrayResponse) {
+        if self.row_drag.is_none() {
+            return;
+        }
+        ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+        if !ui.input(|i| i.pointer.any_released()) {
+            return;
+        }
+        let row_drag = self.row_drag.take().unwrap();
+        if let Some(table_row_index) = row_drag.drop_at {
+            let before = match self.filtered_nodes.get(table_row_index) {
+                Some(row_index) => *row_index,
+                None => self
+                    .filtered_nodes
+                    .last()
+                    .map_or(0, |row_index| row_index + 1),
+            };
+            self.move_rows(&row_drag.rows, before, array_response);
+        }
+    }
+
+    /// Move rows, sorted by index, before row at `before` index, or at the end when it is the
+    /// number of rows. Rows keep their pointers, only their index changes.
+    fn move_rows(&mut self, rows: &[usize], before: usize, array_response: &mut ArrayResponse) {
+        // Old index of rows, by new index
+        let mut order = (0..self.nodes.len())
+            .filter(|row_index| rows.binary_search(row_index).is_err())
+            .collect::<Vec<usize>>();
+        let position = order.partition_point(|row_index| *row_index < before);
+        order.splice(position..position, rows.iter().copied());
+        if order
+            .iter()
+            .enumerate()
+            .all(|(new_index, old_index)| new_index == *old_index)
+        {
+            return;
+        }
+        let mut new_indexes = vec![0; order.len()];
+        for (new_index, old_index) in order.iter().enumerate() {
+            new_indexes[*old_index] = new_index;
+        }
+        let focused_row = self
+            .focused_cell
+            .filter(|cell| !self.is_group_header(cell.row_index))
+            .map(|cell| new_indexes[self.filtered_nodes[cell.row_index]]);
+
+        let mut nodes = mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<Option<JsonArrayEntries<String>>>>();
+        self.nodes = order
+            .iter()
+            .enumerate()
+            .map(|(new_index, old_index)| {
+                let mut row = nodes[*old_index].take().unwrap();
+                row.index = new_index;
+                row
+            })
+            .collect();
+        for computed_column in self.computed_columns.iter_mut() {
+            let mut values = mem::take(&mut computed_column.values);
+            computed_column.values = order.iter().map(|i| values[*i].take()).collect();
+        }
+        // Rows are not sorted, table order is array order
         self.filtered_nodes
-            .insert(table_row_index + above_or_below as usize, new_index);
+            .iter_mut()
+            .for_each(|i| *i = new_indexes[*i]);
+        self.filtered_nodes.sort_unstable();
+        if let Some(ref mut query_rows) = self.query_rows {
+            query_rows.iter_mut().for_each(|i| *i = new_indexes[*i]);
+            query_rows.sort_unstable();
+        }
+        self.selected_rows = mem::take(&mut self.selected_rows)
+            .into_iter()
+            .map(|i| new_indexes[i])
+            .collect();
+        self.selection_anchor = self.selection_anchor.map(|i| new_indexes[i]);
+        self.schema_errors = mem::take(&mut self.schema_errors)
+            .into_iter()
+            .map(|(i, errors)| (new_indexes[i], errors))
+            .collect();
+        self.shift_windows(|i| new_indexes.get(i).copied());
+        if let (Some(focused_row), Some(focused_cell)) = (focused_row, self.focused_cell.as_mut()) {
+            if let Ok(table_row_index) = self.filtered_nodes.binary_search(&focused_row) {
+                focused_cell.row_index = table_row_index;
+                self.scroll_to_row_number = table_row_index;
+                self.changed_arrow_vertical_scroll = true;
+            }
+        }
         self.cache.borrow_mut().evict();
+        if self.is_sub_table {
+            array_response
+                .edited_value
+                .push(self.serialized_sub_table());
+        } else {
+            array_response.edited_value.extend(
+                rows.iter()
+                    .filter_map(|row_index| self.nodes[new_indexes[*row_index]].entries.last())
+                    .cloned(),
+            );
+        }
+    }
+
+    /// Set id column of this row to max id + 1.
+    fn set_next_id(
+        &mut self,
+        id_column: &str,
+        row_index: usize,
+        array_response: &mut ArrayResponse,
+    ) {
+        let next_id = self
+            .nodes
+            .iter()
+            .filter_map(|row| {
+                let pointer = Self::pointer_key(&row.entries, id_column);
+                let entry = row.entries.iter().find(|e| e.pointer.pointer == pointer)?;
+                let id = entry.value.as_ref()?.parse::<f64>().ok()?;
+                id.is_finite().then_some(id)
+            })
+            .reduce(f64::max)
+            .map_or(1, |max| (max.floor() as i64).saturating_add(1));
+        let pointer = Self::pointer_key(&self.nodes[row_index].entries, id_column);
+        let pointer = match self.nodes[row_index]
+            .entries
+            .iter()
+            .find(|e| e.pointer.pointer == pointer)
+        {
+            Some(entry) => PointerKey {
+                value_type: ValueType::Number,
+                ..entry.pointer.clone()
+            },
+            None => PointerKey {
+                pointer,
+                value_type: ValueType::Number,
+                depth: self
+                    .all_columns
+                    .iter()
+                    .find(|c| c.name == id_column)
+                    .map_or(self.parent_pointer.depth + 1, |c| c.depth),
+                position: 0,
+                column_id: column_id(id_column),
+            },
+        };
+        let id_entry = FlatJsonValue {
+            pointer,
+            value: Some(next_id.to_string()),
+        };
+        self.edit_cell(array_response, id_entry, row_index);
     }
 
     #[inline]
@@ -1246,15 +2470,20 @@ impl<'array> ArrayTable<'array> {
     }
 
     fn open_subtable(
+        &self,
         row_index: usize,
         entry: &FlatJsonValue<String>,
         content: String,
     ) -> Option<SubTable<'array>> {
         Some(SubTable::new(
-            entry.pointer.clone(),
+            PointerKey {
+                pointer: self.absolute_pointer(row_index, &entry.pointer.pointer),
+                ..entry.pointer.clone()
+            },
             content,
             entry.pointer.value_type,
             row_index,
+            concat_string!(self.parent_pointer.pointer, "/", row_index.to_string()),
             entry.pointer.depth,
         ))
     }
@@ -1262,13 +2491,41 @@ impl<'array> ArrayTable<'array> {
     #[inline]
     fn update_value(
         &mut self,
-        mut updated_entry: FlatJsonValue<String>,
+        updated_entry: FlatJsonValue<String>,
         row_index: usize,
         should_update_subtable: bool,
     ) -> bool {
+        // Computed column values can not be edited, only materialized
+        let row_pointer = row_pointer(&self.nodes[row_index].entries).to_string();
+        if updated_entry
+            .pointer
+            .pointer
+            .strip_prefix(row_pointer.as_str())
+            .is_some_and(|name| {
+                self.computed_column(name).is_some()
+                    && self.materialized_column.as_deref() != Some(name)
+            })
+        {
+            return false;
+        }
         if should_update_subtable {
-            self.update_sub_tables_value(&mut updated_entry, row_index);
+            self.update_sub_tables_value(&updated_entry, row_index);
         }
+        // Numeric values before edit of edited column and computed columns, for footer aggregates
+        let footer_columns = if self.footer_aggregates.is_some() {
+            let mut footer_columns = self
+                .computed_columns
+                .iter()
+                .map(|c| c.name.clone())
+                .collect::<Vec<String>>();
+            if let Some(column) = updated_entry.pointer.pointer.strip_prefix(&row_pointer) {
+                footer_columns.push(column.to_string());
+            }
+            let values = self.numeric_values(row_index, &footer_columns);
+            footer_columns.into_iter().zip(values).collect()
+        } else {
+            vec![]
+        };
 
         let value_changed = Self::update_row(
             &mut self.nodes[row_index].entries,
@@ -1278,15 +2535,48 @@ impl<'array> ArrayTable<'array> {
         );
         if value_changed {
             self.cache.borrow_mut().evict();
+            self.formatting.clear_ranges();
+            self.validate_row(row_index);
+            for computed_column in self.computed_columns.iter_mut() {
+                computed_column.values[row_index] =
+                    computed_column.compute_row(&self.nodes[row_index]);
+            }
+            let (columns, old_values): (Vec<String>, Vec<Option<f64>>) =
+                footer_columns.into_iter().unzip();
+            let new_values = self.numeric_values(row_index, &columns);
+            for ((column, old_value), new_value) in columns.iter().zip(old_values).zip(new_values) {
+                self.update_footer_value(row_index, column, old_value, new_value);
+            }
         }
         value_changed
     }
 
+    fn numeric_values(&self, row_index: usize, columns: &[String]) -> Vec<Option<f64>> {
+        columns
+            .iter()
+            .map(|column| {
+                Self::cell_value(&self.nodes, self.computed_column(column), row_index, column)
+                    .and_then(|value| value.parse::<f64>().ok())
+            })
+            .collect()
+    }
+
     #[inline]
     fn update_sub_tables_value(&mut self, updated_entry: &FlatJsonValue<String>, row_index: usize) {
+        let key = updated_entry
+            .pointer
+            .pointer
+            .strip_prefix(row_pointer(&self.nodes[row_index].entries));
         for subtable in self.windows.iter_mut() {
-            if subtable.id() == row_index {
-                subtable.update_nodes(updated_entry.pointer.clone(), updated_entry.value.clone());
+            if !subtable.detached && subtable.id() == row_index {
+                // Sub table entries keep the pointer their row had when it was opened
+                let pointer = PointerKey {
+                    pointer: key.map_or(updated_entry.pointer.pointer.clone(), |key| {
+                        concat_string!(subtable.row_pointer(), key)
+                    }),
+                    ..updated_entry.pointer.clone()
+                };
+                subtable.update_nodes(pointer, updated_entry.value.clone());
                 break;
             }
         }
@@ -1304,10 +2594,13 @@ impl<'array> ArrayTable<'array> {
             .iter_mut()
             .find(|entry| entry.pointer.pointer.eq(&updated_entry.pointer.pointer))
         {
-            if !entry.value.eq(&updated_entry.value) {
+            // Typed updates, e.g. from a transform, can change a string into a number or a boolean
+            let value_type_changed = entry.pointer.value_type != updated_entry.pointer.value_type
+                && Self::fits_value_type(&updated_entry);
+            if !entry.value.eq(&updated_entry.value) || value_type_changed {
                 value_changed = true;
                 entry.value = updated_entry.value;
-                if matches!(entry.pointer.value_type, ValueType::Null) {
+                if value_type_changed || matches!(entry.pointer.value_type, ValueType::Null) {
                     entry.pointer.value_type = updated_entry.pointer.value_type;
                 }
             }
@@ -1345,10 +2638,12 @@ impl<'array> ArrayTable<'array> {
                     newly_updated_value.pointer.value_type,
                     ValueType::Object(..)
                 ) {
-                    row_entries
+                    if let Some(entry_to_update) = row_entries
                         .iter_mut()
                         .find(|e| e.pointer.pointer.eq(&newly_updated_value.pointer.pointer))
-                        .map(|entry_to_update| entry_to_update.value = newly_updated_value.value);
+                    {
+                        entry_to_update.value = newly_updated_value.value;
+                    }
                 }
             }
             // let line_number_entry = mem::take(&mut self.nodes[row_index].entries[0]);
@@ -1363,55 +2658,80 @@ impl<'array> ArrayTable<'array> {
         value_changed
     }
 
+    /// Whether value can be serialized as its value type, null never changes type of a value.
+    fn fits_value_type(entry: &FlatJsonValue<String>) -> bool {
+        match (entry.pointer.value_type, entry.value.as_deref()) {
+            (ValueType::Number, Some(value)) => value.parse::<f64>().is_ok(),
+            (ValueType::Bool, Some(value)) => value == "true" || value == "false",
+            (ValueType::String, Some(_)) => true,
+            _ => false,
+        }
+    }
+
     #[inline]
     fn get_pointer_index(
-        parent_pointer: &PointerKey,
         columns: &Vec<Column>,
         data: &&Vec<FlatJsonValue<String>>,
         index: usize,
-        row_index: usize,
     ) -> Option<usize> {
         if let Some(column) = columns.get(index) {
             let key = column.name.as_str();
-            let key = Self::pointer_key(&parent_pointer.pointer, row_index, key);
+            let key = Self::pointer_key(data, key);
             return data.iter().position(|entry| entry.pointer.pointer.eq(&key));
         }
         None
     }
-    #[inline]
-    fn get_pointer<'a>(
-        &self,
-        columns: &Vec<Column>,
-        data: &&'a Vec<FlatJsonValue<String>>,
-        index: usize,
-        row_index: usize,
-    ) -> Option<&'a FlatJsonValue<String>> {
-        if let Some(column) = columns.get(index) {
-            return Self::get_pointer_for_column(
-                &self.parent_pointer.pointer,
-                data,
-                row_index,
-                column,
-            );
-        }
-        None
-    }
-
     #[inline]
     fn get_pointer_for_column<'a>(
-        parent_pointer: &String,
         data: &&'a Vec<FlatJsonValue<String>>,
-        row_index: usize,
         column: &Column,
     ) -> Option<&'a FlatJsonValue<String>> {
         let key = column.name.as_str();
-        let key = Self::pointer_key(parent_pointer, row_index, key);
+        let key = Self::pointer_key(data, key);
         data.iter().find(|entry| entry.pointer.pointer.eq(&key))
     }
 
+    /// Pointer of `key` entry of a row, prefixed by pointer of the row root entry.
     #[inline]
-    fn pointer_key(parent_pointer: &String, row_index: usize, key: &str) -> String {
-        concat_string!(parent_pointer, "/", row_index.to_string(), key)
+    fn pointer_key(row_entries: &[FlatJsonValue<String>], key: &str) -> String {
+        concat_string!(row_pointer(row_entries), key)
+    }
+
+    /// Pointer of a row entry in the document, rows keep the pointer they were parsed or inserted
+    /// at so their current index has to replace it, e.g. for sub tables or to be copied.
+    fn absolute_pointer(&self, row_index: usize, pointer: &str) -> String {
+        match pointer.strip_prefix(row_pointer(&self.nodes[row_index].entries)) {
+            Some(key) => {
+                concat_string!(self.parent_pointer.pointer, "/", row_index.to_string(), key)
+            }
+            None => pointer.to_string(),
+        }
+    }
+
+    /// Pointer of a row entry as stored in the row, from its pointer in the document when the row
+    /// was at `absolute_row_pointer`.
+    fn stored_pointer(
+        &self,
+        row_index: usize,
+        absolute_row_pointer: &str,
+        pointer: &str,
+    ) -> String {
+        match pointer.strip_prefix(absolute_row_pointer) {
+            Some(key) if key.is_empty() || key.starts_with('/') => {
+                Self::pointer_key(&self.nodes[row_index].entries, key)
+            }
+            _ => pointer.to_string(),
+        }
+    }
+
+    /// Replace filter of a column by these values.
+    fn set_column_filter(&mut self, column: String, values: Vec<String>) {
+        if values.is_empty() {
+            self.columns_filter.remove(&column);
+        } else {
+            self.columns_filter.insert(column, values);
+        }
+        self.do_filter_column();
     }
 
     fn on_filter_column_value(&mut self, (column, value): (String, String)) {
@@ -1423,25 +2743,934 @@ impl<'array> ArrayTable<'array> {
                     self.columns_filter.remove(column.as_str());
                 }
             } else {
-                filter.push(value);
+                filter.push(value);
+            }
+        } else {
+            self.columns_filter.insert(column, vec![value]);
+        }
+        self.do_filter_column();
+    }
+
+    fn do_filter_column(&mut self) {
+        // Computed columns have no entry in rows, they are filtered on their computed values
+        let (computed_columns_filter, columns_filter): (HashMap<_, _>, HashMap<_, _>) = self
+            .columns_filter
+            .iter()
+            .map(|(name, filters)| (name.clone(), filters.clone()))
+            .partition(|(name, _)| self.computed_column(name).is_some());
+        if columns_filter.is_empty() {
+            self.filtered_nodes = (0..self.nodes.len()).collect::<Vec<usize>>();
+        } else {
+            self.filtered_nodes = crate::parser::filter_columns(&self.nodes, &columns_filter);
+        }
+        for (name, filters) in computed_columns_filter {
+            let computed_column = self
+                .computed_columns
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap();
+            let should_filter_by_non_null = filters.iter().any(|f| f == NON_NULL_FILTER_VALUE);
+            self.filtered_nodes.retain(|row_index| {
+                let value = computed_column.value(*row_index);
+                if should_filter_by_non_null && value.is_none() {
+                    return false;
+                }
+                filters.len() == should_filter_by_non_null as usize
+                    || value.is_some_and(|value| filters.contains(value))
+            });
+        }
+        if self.only_invalid_rows {
+            self.filtered_nodes
+                .retain(|row_index| self.schema_errors.contains_key(row_index));
+        }
+        if let Some(ref query_rows) = self.query_rows {
+            self.filtered_nodes
+                .retain(|row_index| query_rows.binary_search(row_index).is_ok());
+        }
+        if let Some((column, descending)) = self.sort.clone() {
+            self.sort_rows(&column, descending);
+        }
+        self.group_rows();
+        if self.search_regex.is_some() {
+            self.find_matching_cells();
+        }
+        if !self.selected_rows.is_empty() {
+            // Hidden rows can not stay selected
+            let filtered_rows = self.filtered_rows().collect::<HashSet<usize>>();
+            self.selected_rows
+                .retain(|row_index| filtered_rows.contains(row_index));
+        }
+        self.footer_aggregates = None;
+        if self.opened_windows.contains(PANEL_CHART) {
+            self.refresh_chart();
+        }
+        self.next_frame_reset_scroll = true;
+    }
+
+    fn sort_rows(&mut self, column: &str, descending: bool) {
+        let computed_column = self.computed_column(column);
+        let mut rows = self
+            .filtered_nodes
+            .par_iter()
+            .map(|row_index| {
+                let value = Self::cell_value(&self.nodes, computed_column, *row_index, column);
+                (
+                    *row_index,
+                    value.map(|value| (value.parse::<f64>().ok(), value)),
+                )
+            })
+            .collect::<Vec<(usize, Option<(Option<f64>, &String)>)>>();
+        // Numbers before other values, rows without value always last
+        rows.par_sort_by(|(_, a), (_, b)| match (a, b) {
+            (Some(a), Some(b)) => {
+                let ordering = match (a, b) {
+                    ((Some(a), _), (Some(b), _)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                    ((Some(_), _), (None, _)) => Ordering::Less,
+                    ((None, _), (Some(_), _)) => Ordering::Greater,
+                    ((None, a), (None, b)) => a.cmp(b),
+                };
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+        self.filtered_nodes = rows.into_iter().map(|(row_index, _)| row_index).collect();
+    }
+
+    #[inline]
+    fn cell_value<'a>(
+        nodes: &'a [JsonArrayEntries<String>],
+        computed_column: Option<&'a ComputedColumn>,
+        row_index: usize,
+        column: &str,
+    ) -> Option<&'a String> {
+        match computed_column {
+            Some(computed_column) => computed_column.value(row_index),
+            None => {
+                let pointer = Self::pointer_key(&nodes[row_index].entries, column);
+                nodes[row_index]
+                    .entries
+                    .iter()
+                    .find(|entry| entry.pointer.pointer == pointer)
+                    .and_then(|entry| entry.value.as_ref())
+            }
+        }
+    }
+
+    /// Partition filtered rows by value of group by columns, groups are ordered by their key.
+    fn group_rows(&mut self) {
+        if self.group_by.is_empty() {
+            self.groups.clear();
+            self.group_headers.clear();
+            return;
+        }
+        let collapsed_keys = self
+            .groups
+            .iter()
+            .filter(|group| group.collapsed)
+            .map(|group| group.key.clone())
+            .collect::<HashSet<Vec<Option<String>>>>();
+        let computed_columns = self
+            .group_by
+            .iter()
+            .map(|column| self.computed_columns.iter().find(|c| &c.name == column))
+            .collect::<Vec<Option<&ComputedColumn>>>();
+        let mut groups: IndexMap<Vec<Option<String>>, Vec<usize>> = IndexMap::new();
+        for row_index in self.filtered_nodes.iter() {
+            let key = self
+                .group_by
+                .iter()
+                .zip(computed_columns.iter())
+                .map(|(column, computed_column)| {
+                    Self::cell_value(&self.nodes, *computed_column, *row_index, column).cloned()
+                })
+                .collect::<Vec<Option<String>>>();
+            groups.entry(key).or_default().push(*row_index);
+        }
+        let sort = self.sort.as_ref().and_then(|(column, descending)| {
+            let position = self.group_by.iter().position(|c| c == column)?;
+            Some((position, *descending))
+        });
+        groups.sort_by(|a, _, b, _| {
+            for (i, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                let ordering = Self::compare_cell_values(a.as_ref(), b.as_ref());
+                let ordering = match sort {
+                    Some((position, true)) if position == i => ordering.reverse(),
+                    _ => ordering,
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        self.groups = groups
+            .into_iter()
+            .map(|(key, rows)| RowGroup {
+                collapsed: collapsed_keys.contains(&key),
+                key,
+                rows,
+                aggregates: HashMap::new(),
+            })
+            .collect();
+        self.aggregate_groups();
+        self.layout_groups();
+    }
+
+    /// Aggregates of numeric columns for each group.
+    fn aggregate_groups(&mut self) {
+        let numeric_columns = self
+            .all_columns
+            .iter()
+            .filter(|c| matches!(c.value_type, ValueType::Number))
+            .map(|c| {
+                let computed_column = self.computed_columns.iter().find(|cc| cc.name == c.name);
+                (c.name.to_string(), computed_column)
+            })
+            .collect::<Vec<(String, Option<&ComputedColumn>)>>();
+        let nodes = &self.nodes;
+        self.groups.par_iter_mut().for_each(|group| {
+            group.aggregates = numeric_columns
+                .iter()
+                .map(|(column, computed_column)| {
+                    let aggregates =
+                        Self::column_aggregates(nodes, *computed_column, &group.rows, column);
+                    (column.clone(), aggregates)
+                })
+                .collect();
+        });
+    }
+
+    fn column_aggregates(
+        nodes: &[JsonArrayEntries<String>],
+        computed_column: Option<&ComputedColumn>,
+        rows: &[usize],
+        column: &str,
+    ) -> Aggregates {
+        let mut aggregates = Aggregates::default();
+        rows.iter()
+            .filter_map(|row_index| Self::cell_value(nodes, computed_column, *row_index, column))
+            .filter_map(|value| value.parse::<f64>().ok())
+            .for_each(|value| aggregates.add(value));
+        aggregates
+    }
+
+    /// Table rows: a header row per group followed by its rows when expanded. Header row points to
+    /// the first row of its group so indexing filtered rows is always valid.
+    fn layout_groups(&mut self) {
+        let mut filtered_nodes = Vec::with_capacity(self.filtered_nodes.len() + self.groups.len());
+        self.group_headers.clear();
+        for (group_index, group) in self.groups.iter().enumerate() {
+            self.group_headers.insert(filtered_nodes.len(), group_index);
+            filtered_nodes.push(group.rows[0]);
+            if !group.collapsed {
+                filtered_nodes.extend_from_slice(&group.rows);
+            }
+        }
+        self.filtered_nodes = filtered_nodes;
+    }
+
+    /// Rows matching filters, without group header rows and with rows of collapsed groups.
+    fn filtered_rows(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        if self.is_grouped() {
+            Box::new(
+                self.groups
+                    .iter()
+                    .flat_map(|group| group.rows.iter().copied()),
+            )
+        } else {
+            Box::new(self.filtered_nodes.iter().copied())
+        }
+    }
+
+    #[inline]
+    fn is_group_header(&self, table_row_index: usize) -> bool {
+        self.group_headers.contains_key(&table_row_index)
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        !self.group_by.is_empty()
+    }
+
+    /// Add or remove column from group by columns.
+    pub fn toggle_group_by(&mut self, column: String) {
+        if let Some(position) = self.group_by.iter().position(|c| *c == column) {
+            self.group_by.remove(position);
+        } else {
+            self.group_by.push(column);
+        }
+        self.focused_cell = None;
+        self.do_filter_column();
+    }
+
+    pub fn clear_group_by(&mut self) {
+        self.group_by.clear();
+        self.focused_cell = None;
+        self.do_filter_column();
+    }
+
+    fn toggle_group(&mut self, group_index: usize) {
+        self.groups[group_index].collapsed = !self.groups[group_index].collapsed;
+        self.focused_cell = None;
+        self.layout_groups();
+    }
+
+    pub fn collapse_all_groups(&mut self, collapsed: bool) {
+        self.groups
+            .iter_mut()
+            .for_each(|group| group.collapsed = collapsed);
+        self.focused_cell = None;
+        self.layout_groups();
+        self.next_frame_reset_scroll = true;
+    }
+
+    pub fn set_wrap_rows(&mut self, wrap_rows: bool) {
+        self.wrap_rows = wrap_rows;
+        self.row_heights.clear();
+        self.row_offsets.clear();
+    }
+
+    /// Measure rows about to be drawn, offsets are updated from the first row whose height changed.
+    fn update_row_heights(&mut self, ui: &Ui, visible_height: f32) {
+        if !self.wrap_rows {
+            return;
+        }
+        let text_height = Self::row_height(ui.style(), ui.spacing());
+        let rows_count = self.filtered_nodes.len();
+        if self.row_heights.len() != rows_count || self.row_offsets.len() != rows_count + 1 {
+            self.row_heights = vec![text_height; rows_count];
+            self.update_row_offsets(ui, 0);
+        }
+        let first_row = self
+            .row_offsets
+            .partition_point(|offset| *offset <= self.scroll_y)
+            .saturating_sub(1);
+        let mut changed_from = None;
+        let mut y = self.row_offsets[first_row];
+        let mut table_row_index = first_row;
+        while table_row_index < rows_count && y <= self.scroll_y + visible_height {
+            let height = self.wrapped_row_height(ui, table_row_index, text_height);
+            if height != self.row_heights[table_row_index] {
+                self.row_heights[table_row_index] = height;
+                changed_from.get_or_insert(table_row_index);
+            }
+            y += height + ui.spacing().item_spacing.y;
+            table_row_index += 1;
+        }
+        if let Some(changed_from) = changed_from {
+            self.update_row_offsets(ui, changed_from);
+        }
+    }
+
+    /// Prefix sum of row heights, from given row to the end.
+    fn update_row_offsets(&mut self, ui: &Ui, from: usize) {
+        let spacing_y = ui.spacing().item_spacing.y;
+        self.row_offsets.truncate(from + 1);
+        if self.row_offsets.is_empty() {
+            self.row_offsets.push(0.0);
+        }
+        let mut offset = self.row_offsets[from];
+        for height in self.row_heights[from..].iter() {
+            offset += height + spacing_y;
+            self.row_offsets.push(offset);
+        }
+    }
+
+    /// Start of a value drawn in a cell, cut at a char boundary when longer than MAX_DRAWN_VALUE_LEN.
+    fn drawn_text(value: &str) -> &str {
+        let mut end = value.len().min(MAX_DRAWN_VALUE_LEN);
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        &value[..end]
+    }
+
+    /// Height of the highest cell of both tables, at least one line and at most MAX_WRAPPED_LINES.
+    fn wrapped_row_height(&self, ui: &Ui, table_row_index: usize, text_height: f32) -> f32 {
+        if self.group_headers.contains_key(&table_row_index) {
+            return text_height;
+        }
+        let row_index = self.filtered_nodes[table_row_index];
+        // Row number column is not wrapped
+        let pinned_columns = self.column_pinned.iter().skip(1);
+        pinned_columns
+            .chain(self.column_selected.iter())
+            .filter_map(|column| {
+                let name = column.name.as_ref();
+                let width = *self.drawn_column_widths.get(name)?;
+                let value =
+                    Self::cell_value(&self.nodes, self.computed_column(name), row_index, name)?;
+                let text = Self::drawn_text(value);
+                let height = CellText::wrapped_height(ui, text, width, MAX_WRAPPED_LINES);
+                Some(height)
+            })
+            .fold(text_height, f32::max)
+            .min(text_height * MAX_WRAPPED_LINES as f32)
+    }
+
+    pub fn set_show_aggregates_footer(&mut self, show_aggregates_footer: bool) {
+        self.show_aggregates_footer = show_aggregates_footer;
+        self.footer_aggregates = None;
+        self.stale_footer_columns.clear();
+    }
+
+    /// Rows aggregated in footer: selected rows, or filtered rows when there is no selection.
+    fn footer_rows(&self) -> Vec<usize> {
+        if self.selected_rows.is_empty() {
+            self.filtered_rows().collect()
+        } else {
+            self.selected_rows.iter().copied().collect()
+        }
+    }
+
+    fn is_footer_row(&self, row_index: usize) -> bool {
+        if self.selected_rows.is_empty() {
+            self.filtered_rows().any(|i| i == row_index)
+        } else {
+            self.selected_rows.contains(&row_index)
+        }
+    }
+
+    /// Compute footer aggregates when they are missing, otherwise only those of stale columns.
+    fn update_footer_aggregates(&mut self) {
+        if !self.show_aggregates_footer
+            || (self.footer_aggregates.is_some() && self.stale_footer_columns.is_empty())
+        {
+            return;
+        }
+        let stale_columns = mem::take(&mut self.stale_footer_columns);
+        let compute_all = self.footer_aggregates.is_none();
+        let numeric_columns = self
+            .all_columns
+            .iter()
+            .filter(|c| matches!(c.value_type, ValueType::Number))
+            .filter(|c| compute_all || stale_columns.contains(c.name.as_ref()))
+            .map(|c| (c.name.to_string(), self.computed_column(&c.name)))
+            .collect::<Vec<(String, Option<&ComputedColumn>)>>();
+        let rows = self.footer_rows();
+        let nodes = &self.nodes;
+        let aggregates = numeric_columns
+            .par_iter()
+            .map(|(column, computed_column)| {
+                let aggregates = Self::column_aggregates(nodes, *computed_column, &rows, column);
+                (column.clone(), aggregates)
+            })
+            .collect::<Vec<(String, Aggregates)>>();
+        self.footer_aggregates
+            .get_or_insert_with(HashMap::new)
+            .extend(aggregates);
+    }
+
+    /// Update footer aggregates with the new value of an edited cell, without going through all
+    /// rows again unless it was min or max of its column.
+    fn update_footer_value(
+        &mut self,
+        row_index: usize,
+        column: &str,
+        old_value: Option<f64>,
+        new_value: Option<f64>,
+    ) {
+        if old_value == new_value || !self.is_footer_row(row_index) {
+            return;
+        }
+        let Some(aggregates) = self
+            .footer_aggregates
+            .as_mut()
+            .and_then(|footer_aggregates| footer_aggregates.get_mut(column))
+        else {
+            return;
+        };
+        if let Some(old_value) = old_value {
+            if !aggregates.remove(old_value) {
+                self.stale_footer_columns.insert(column.to_string());
+                return;
+            }
+        }
+        if let Some(new_value) = new_value {
+            aggregates.add(new_value);
+        }
+    }
+
+    /// Footer cell text and its tooltip, for each column of the table.
+    fn footer_cells(&self, pinned_column_table: bool) -> Vec<(String, String)> {
+        let footer_aggregates = self.footer_aggregates.as_ref();
+        self.columns(pinned_column_table)
+            .iter()
+            .enumerate()
+            .map(|(col_index, column)| {
+                if pinned_column_table && col_index == 0 {
+                    let tooltip = if self.selected_rows.is_empty() {
+                        "Aggregates of filtered rows".to_string()
+                    } else {
+                        format!("Aggregates of {} selected rows", self.selected_rows.len())
+                    };
+                    return ("Σ".to_string(), tooltip);
+                }
+                footer_aggregates
+                    .and_then(|footer_aggregates| footer_aggregates.get(column.name.as_ref()))
+                    .map_or((String::new(), String::new()), |aggregates| {
+                        (aggregates.summary(), column.name.to_string())
+                    })
+            })
+            .collect()
+    }
+
+    /// Ctrl click toggles row selection, shift click selects rows from the last clicked one and a
+    /// plain click clears selection.
+    fn select_row(&mut self, row_index: usize, modifiers: Modifiers) {
+        if modifiers.command {
+            if !self.selected_rows.remove(&row_index) {
+                self.selected_rows.insert(row_index);
+            }
+        } else if modifiers.shift {
+            let rows = self.filtered_rows().collect::<Vec<usize>>();
+            let anchor = self.selection_anchor.unwrap_or(row_index);
+            let start = rows.iter().position(|i| *i == anchor);
+            let end = rows.iter().position(|i| *i == row_index);
+            if let (Some(start), Some(end)) = (start, end) {
+                self.selected_rows
+                    .extend(&rows[start.min(end)..=start.max(end)]);
+            }
+            self.footer_aggregates = None;
+            return;
+        } else if self.selected_rows.is_empty() {
+            self.selection_anchor = Some(row_index);
+            return;
+        } else {
+            self.selected_rows.clear();
+        }
+        self.selection_anchor = Some(row_index);
+        self.footer_aggregates = None;
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected_rows.clear();
+        self.selection_anchor = None;
+        self.footer_aggregates = None;
+    }
+
+    pub(crate) fn compare_cell_values(a: Option<&String>, b: Option<&String>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    /// Cycle column sort between ascending, descending and no sort.
+    fn toggle_sort(&mut self, column: String) {
+        self.sort = match self.sort.take() {
+            Some((sorted_column, false)) if sorted_column == column => Some((column, true)),
+            Some((sorted_column, true)) if sorted_column == column => None,
+            _ => Some((column, false)),
+        };
+        self.do_filter_column();
+    }
+
+    /// Filter rows with the query, or show its result in a sub table when it selects values out
+    /// of rows or when `open_in_sub_table` is set.
+    pub fn apply_query(&mut self, open_in_sub_table: bool) {
+        let query = match Query::parse(&self.query) {
+            Ok(query) => query,
+            Err(e) => {
+                self.query_error = Some(e);
+                return;
+            }
+        };
+        self.query_error = None;
+        let content = match query.run(&self.nodes) {
+            QueryResult::Rows(rows) if !open_in_sub_table => {
+                self.query_rows = Some(rows);
+                self.do_filter_column();
+                return;
+            }
+            QueryResult::Rows(rows) => {
+                let rows = rows
+                    .iter()
+                    .filter_map(|row_index| self.nodes[*row_index].entries.last()?.value.as_ref())
+                    .map(|row| row.as_str())
+                    .collect::<Vec<&str>>();
+                concat_string!("[", rows.join(","), "]")
+            }
+            QueryResult::Values { name, values } => {
+                // Table rows have to be objects
+                let values = if values.iter().all(|value| value.is_object()) {
+                    values
+                } else {
+                    values
+                        .into_iter()
+                        .map(|value| serde_json::json!({ name.as_str(): value }))
+                        .collect()
+                };
+                serde_json::to_string(&values).unwrap()
+            }
+        };
+        let name = format!("Query result: {}", self.query.trim());
+        match SubTable::new_detached(name, content) {
+            Ok(subtable) => {
+                self.windows.retain(|w| w.name() != subtable.name());
+                self.windows.push(subtable);
+            }
+            Err(e) => self.query_error = Some(e),
+        }
+    }
+
+    pub fn clear_query(&mut self) {
+        self.query_error = None;
+        if self.query_rows.take().is_some() {
+            self.do_filter_column();
+        }
+    }
+
+    #[inline]
+    pub fn is_query_applied(&self) -> bool {
+        self.query_rows.is_some()
+    }
+
+    #[inline]
+    fn computed_column(&self, name: &str) -> Option<&ComputedColumn> {
+        self.computed_columns.iter().find(|c| c.name == name)
+    }
+
+    fn computed_column_header(&self, computed_column: &ComputedColumn) -> Column<'array> {
+        Column {
+            name: Cow::from(computed_column.name.clone()),
+            // Visible at any depth
+            depth: 0,
+            value_type: computed_column.value_type,
+            seen_count: 0,
+            order: self.all_columns.len(),
+            id: self.all_columns.iter().map(|c| c.id).max().unwrap_or(0) + 1,
+        }
+    }
+
+    pub fn add_computed_column(&mut self, name: &str, expression: &str) -> Result<(), String> {
+        let mut computed_column = ComputedColumn::new(name, expression)?;
+        if self
+            .all_columns
+            .iter()
+            .any(|c| c.name == computed_column.name)
+        {
+            return Err(format!("Column {} already exists", computed_column.name));
+        }
+        computed_column.compute(&self.nodes);
+        let column = self.computed_column_header(&computed_column);
+        self.all_columns.push(column.clone());
+        self.column_selected.retain(|c| !c.name.is_empty());
+        self.column_selected.push(column);
+        self.computed_columns.push(computed_column);
+        self.computed_columns_panel
+            .set_computed_columns(&self.computed_columns);
+        self.cache.borrow_mut().evict();
+        self.footer_aggregates = None;
+        Ok(())
+    }
+
+    pub fn remove_computed_column(&mut self, name: &str) {
+        self.computed_columns.retain(|c| c.name != name);
+        self.all_columns.retain(|c| c.name != name);
+        self.column_selected.retain(|c| c.name != name);
+        self.column_pinned.retain(|c| c.name != name);
+        self.columns_filter.remove(name);
+        if self.sort.as_ref().is_some_and(|(column, _)| column == name) {
+            self.sort = None;
+        }
+        self.computed_columns_panel
+            .set_computed_columns(&self.computed_columns);
+        self.cache.borrow_mut().evict();
+        self.do_filter_column();
+    }
+
+    /// Write computed value as a real key in every row, the column then becomes a regular column.
+    pub fn materialize_computed_column(&mut self, name: &str, array_response: &mut ArrayResponse) {
+        let Some(index) = self.computed_columns.iter().position(|c| c.name == name) else {
+            return;
+        };
+        let Some(column) = self.all_columns.iter().find(|c| c.name == name).cloned() else {
+            return;
+        };
+        let computed_column = &self.computed_columns[index];
+        let key_depth = name.matches('/').count() as u8;
+        let updates = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(row_index, row)| {
+                let value = computed_column.json_value(row);
+                if value.is_null() {
+                    return None;
+                }
+                let depth = row.entries.last()?.pointer.depth + key_depth;
+                let value_type = match &value {
+                    Value::Bool(_) => ValueType::Bool,
+                    Value::Number(_) => ValueType::Number,
+                    Value::Array(elements) => ValueType::Array(elements.len()),
+                    Value::Object(map) => ValueType::Object(true, map.len()),
+                    _ => ValueType::String,
+                };
+                let pointer = PointerKey {
+                    pointer: Self::pointer_key(&row.entries, name),
+                    value_type,
+                    depth,
+                    position: 0,
+                    column_id: column.id,
+                };
+                let value = crate::computed_column::to_cell_value(value);
+                Some((FlatJsonValue { pointer, value }, row_index))
+            })
+            .collect::<Vec<(FlatJsonValue<String>, usize)>>();
+        let depth = updates
+            .first()
+            .map_or(column.depth, |(entry, _)| entry.pointer.depth);
+        for c in self
+            .all_columns
+            .iter_mut()
+            .chain(self.column_selected.iter_mut())
+            .chain(self.column_pinned.iter_mut())
+            .filter(|c| c.name == name)
+        {
+            c.depth = depth;
+        }
+        self.materialized_column = Some(name.to_string());
+        self.update_values(updates, array_response);
+        self.materialized_column = None;
+        self.computed_columns.remove(index);
+        self.computed_columns_panel
+            .set_computed_columns(&self.computed_columns);
+        self.cache.borrow_mut().evict();
+        self.do_filter_column();
+    }
+
+    fn compute_columns(&mut self) {
+        for computed_column in self.computed_columns.iter_mut() {
+            computed_column.compute(&self.nodes);
+        }
+    }
+
+    pub fn open_computed_columns_panel(&mut self) {
+        set_open(&mut self.opened_windows, PANEL_COMPUTED_COLUMNS, true);
+    }
+
+    pub fn open_pivot_panel(&mut self) {
+        self.pivot_panel.set_columns(&self.all_columns);
+        set_open(&mut self.opened_windows, PANEL_PIVOT, true);
+    }
+
+    pub fn open_formatting_panel(&mut self) {
+        self.formatting_panel.set_columns(&self.all_columns);
+        self.formatting_panel.set_rules(&self.formatting.rules);
+        set_open(&mut self.opened_windows, PANEL_FORMATTING, true);
+    }
+
+    /// Compute min and max of color scale columns, over all rows.
+    fn update_formatting_ranges(&mut self) {
+        let columns = self.formatting.missing_ranges();
+        if columns.is_empty() {
+            return;
+        }
+        let rows = (0..self.nodes.len()).collect::<Vec<usize>>();
+        for column in columns {
+            let aggregates =
+                Self::column_aggregates(&self.nodes, self.computed_column(&column), &rows, &column);
+            self.formatting
+                .set_range(column, (aggregates.min, aggregates.max));
+        }
+    }
+
+    pub fn open_chart_panel(&mut self, column: Option<String>) {
+        self.chart_panel.set_columns(&self.all_columns);
+        if let Some(column) = column {
+            self.chart_panel.set_column(column);
+            if let Some(ChartResponse::Plot {
+                kind,
+                column,
+                y_column,
+            }) = self.chart_panel.plot_request()
+            {
+                self.chart_panel.chart = Some(self.chart(kind, column, y_column));
+            }
+        }
+        set_open(&mut self.opened_windows, PANEL_CHART, true);
+    }
+
+    /// Chart of rows matching filters.
+    fn chart(&self, kind: ChartKind, column: String, y_column: Option<String>) -> Chart {
+        let value_of = |row_index: usize, column: &str| {
+            Self::cell_value(&self.nodes, self.computed_column(column), row_index, column).cloned()
+        };
+        let values = self
+            .filtered_rows()
+            .map(|row_index| {
+                (
+                    value_of(row_index, &column),
+                    y_column
+                        .as_ref()
+                        .and_then(|y_column| value_of(row_index, y_column)),
+                )
+            })
+            .collect();
+        Chart::new(kind, column, y_column, values)
+    }
+
+    /// Plot current chart again, e.g. after filters changed.
+    fn refresh_chart(&mut self) {
+        if let Some(chart) = self.chart_panel.chart.take() {
+            self.chart_panel.chart = Some(self.chart(chart.kind, chart.column, chart.y_column));
+        }
+    }
+
+    /// Pivot of rows matching filters, including rows of collapsed groups.
+    fn pivot(&self, pivot_response: PivotResponse) -> Pivot {
+        let PivotResponse {
+            row_column,
+            column_column,
+            value_column,
+            aggregate,
+        } = pivot_response;
+        let value_of = |row_index: usize, column: &str| {
+            let computed_column = self.computed_columns.iter().find(|c| c.name == column);
+            Self::cell_value(&self.nodes, computed_column, row_index, column).cloned()
+        };
+        let values = self
+            .filtered_rows()
+            .map(|row_index| {
+                (
+                    value_of(row_index, &row_column),
+                    value_of(row_index, &column_column),
+                    value_of(row_index, &value_column),
+                )
+            })
+            .collect();
+        Pivot::new(row_column, column_column, value_column, aggregate, values)
+    }
+
+    pub fn set_schema_validator(&mut self, schema_validator: Option<RowValidator>) {
+        self.schema_validator = schema_validator;
+        self.validate_rows();
+        if self.schema_validator.is_none() && self.only_invalid_rows {
+            self.set_only_invalid_rows(false);
+        }
+    }
+
+    pub fn schema_source(&self) -> Option<&str> {
+        self.schema_validator.as_ref().map(|v| v.source.as_str())
+    }
+
+    pub fn set_only_invalid_rows(&mut self, only_invalid_rows: bool) {
+        self.only_invalid_rows = only_invalid_rows;
+        self.do_filter_column();
+    }
+
+    fn validate_rows(&mut self) {
+        self.schema_errors = match self.schema_validator {
+            Some(ref validator) => validator.validate_rows(&self.nodes),
+            None => BTreeMap::new(),
+        };
+    }
+
+    fn validate_row(&mut self, row_index: usize) {
+        if let Some(ref validator) = self.schema_validator {
+            let errors = validator.validate_row(&self.nodes[row_index]);
+            if errors.is_empty() {
+                self.schema_errors.remove(&row_index);
+            } else {
+                self.schema_errors.insert(row_index, errors);
             }
-        } else {
-            self.columns_filter.insert(column, vec![value]);
         }
-        self.do_filter_column();
     }
 
-    fn do_filter_column(&mut self) {
-        if self.columns_filter.is_empty() {
-            self.filtered_nodes = (0..self.nodes.len()).collect::<Vec<usize>>();
-        } else {
-            self.filtered_nodes = crate::parser::filter_columns(
-                &self.nodes,
-                &self.parent_pointer.pointer,
-                &self.columns_filter,
-            );
+    /// Focus first invalid cell of the next invalid row after focused one.
+    pub fn goto_next_schema_error(&mut self) {
+        if self.schema_errors.is_empty() {
+            return;
         }
-        self.next_frame_reset_scroll = true;
+        let start = self
+            .focused_cell
+            .map_or(0, |focused_cell| focused_cell.row_index + 1);
+        let rows_count = self.filtered_nodes.len();
+        let next_invalid_row = (0..rows_count)
+            .map(|i| (start + i) % rows_count)
+            .find(|table_row_index| {
+                !self.is_group_header(*table_row_index)
+                    && self
+                        .schema_errors
+                        .contains_key(&self.filtered_nodes[*table_row_index])
+            });
+        let Some(table_row_index) = next_invalid_row else {
+            return;
+        };
+        let errors = &self.schema_errors[&self.filtered_nodes[table_row_index]];
+        let is_invalid = |column: &Column| errors.iter().any(|e| e.concerns_column(&column.name));
+        // Pinned columns are drawn first, the row number one is focused when no column is invalid
+        let pinned_column_index = self
+            .column_pinned
+            .iter()
+            .skip(1)
+            .position(is_invalid)
+            .map(|column_index| column_index + 1);
+        let column_index = pinned_column_index
+            .is_none()
+            .then(|| self.column_selected.iter().position(is_invalid))
+            .flatten();
+        self.focused_cell = Some(CellLocation {
+            row_index: table_row_index,
+            column_index: column_index.or(pinned_column_index).unwrap_or(0),
+            is_pinned_column_table: column_index.is_none(),
+        });
+        self.scroll_to_row_number = table_row_index;
+        self.changed_arrow_vertical_scroll = true;
+        if let Some(column_index) = column_index {
+            self.scroll_to_column_number = column_index;
+            self.changed_arrow_horizontal_scroll = true;
+        }
+    }
+
+    /// Outline cell having schema errors and show them on hover, `column` is none for row number cell
+    /// which shows all errors of the row.
+    fn highlight_schema_errors(
+        &self,
+        ui: &Ui,
+        response: Response,
+        row_index: usize,
+        column: Option<&str>,
+    ) -> Response {
+        let Some(errors) = self.schema_errors.get(&row_index) else {
+            return response;
+        };
+        let messages = errors
+            .iter()
+            .filter(|error| match column {
+                Some(column) => error.concerns_column(column),
+                None => true,
+            })
+            .map(|error| {
+                if column.is_none() && !error.pointer.is_empty() {
+                    concat_string!(error.pointer, ": ", error.message)
+                } else {
+                    error.message.clone()
+                }
+            })
+            .collect::<Vec<String>>();
+        if messages.is_empty() {
+            return response;
+        }
+        ui.painter().rect_stroke(
+            response.rect.shrink(1.0),
+            0.0,
+            Stroke::new(1.0, Color32::from_rgb(220, 50, 50)),
+        );
+        response.on_hover_text(messages.join("\n"))
     }
 
     #[inline]
@@ -1451,14 +3680,126 @@ impl<'array> ArrayTable<'array> {
 
     pub fn reset_search(&mut self) {
         self.scroll_to_row.clear();
-        self.matching_rows.clear();
+        self.matching_cells.clear();
         self.changed_scroll_to_row_value =
             Some(crate::compatibility::now().sub(Duration::from_millis(1000)));
-        self.matching_row_selected = 0;
+        self.matching_cell_selected = 0;
+        self.search_regex = None;
+        self.search_error = None;
+    }
+
+    /// Search cells of filtered rows matching searched term, then go to the first one.
+    pub fn search(&mut self) {
+        self.find_matching_cells();
+        if !self.matching_cells.is_empty() {
+            self.select_matching_cell(0);
+        }
+    }
+
+    fn find_matching_cells(&mut self) {
+        self.matching_cells.clear();
+        self.matching_cell_selected = 0;
+        self.search_regex = None;
+        self.search_error = None;
+        if self.scroll_to_row.is_empty() {
+            return;
+        }
+        match search_regex(&self.scroll_to_row, &self.search_mode) {
+            Ok(regex) => {
+                // Matching keys come first, as headers are above cells
+                let key_matches = search_keys(&self.all_columns, &regex)
+                    .into_iter()
+                    .map(|column| SearchMatch {
+                        table_row_index: None,
+                        column,
+                    });
+                let value_matches = search_occurrences(&self.nodes, &self.filtered_nodes, &regex)
+                    .into_iter()
+                    // Group header rows point to the first row of their group
+                    .filter(|(table_row_index, _)| {
+                        !self.group_headers.contains_key(table_row_index)
+                    })
+                    .map(|(table_row_index, column)| SearchMatch {
+                        table_row_index: Some(table_row_index),
+                        column,
+                    });
+                self.matching_cells = key_matches.chain(value_matches).collect();
+                self.search_regex = Some(regex);
+            }
+            Err(error) => self.search_error = Some(error),
+        }
+    }
+
+    /// Returns false when there is no matching cell.
+    pub fn next_matching_cell(&mut self) -> bool {
+        if self.matching_cells.is_empty() {
+            return false;
+        }
+        self.select_matching_cell((self.matching_cell_selected + 1) % self.matching_cells.len());
+        true
+    }
+
+    pub fn previous_matching_cell(&mut self) {
+        if self.matching_cells.is_empty() {
+            return;
+        }
+        let index = self
+            .matching_cell_selected
+            .checked_sub(1)
+            .unwrap_or(self.matching_cells.len() - 1);
+        self.select_matching_cell(index);
+    }
+
+    /// Scroll to matching cell and focus it, when its column is displayed. Only scroll to the column
+    /// of a matching key.
+    fn select_matching_cell(&mut self, index: usize) {
+        self.matching_cell_selected = index;
+        self.changed_matching_cell_selected = true;
+        let search_match = &self.matching_cells[index];
+        let pinned_column_index = self
+            .column_pinned
+            .iter()
+            .position(|c| c.name == search_match.column);
+        let column_index = self
+            .column_selected
+            .iter()
+            .position(|c| c.name == search_match.column);
+        if let (Some(column_index), Some(row_index)) = (
+            pinned_column_index.or(column_index),
+            search_match.table_row_index,
+        ) {
+            self.focused_cell = Some(CellLocation {
+                column_index,
+                row_index,
+                is_pinned_column_table: pinned_column_index.is_some(),
+            });
+        }
+        if let (None, Some(column_index)) = (pinned_column_index, column_index) {
+            self.scroll_to_column_number = column_index;
+            self.changed_arrow_horizontal_scroll = true;
+        }
+    }
+
+    fn is_matching_key(&self, column: &str) -> bool {
+        self.matching_cells
+            .iter()
+            .take_while(|search_match| search_match.table_row_index.is_none())
+            .any(|search_match| search_match.column == column)
+    }
+
+    /// Ranges of searched term in a cell value, to highlight them.
+    fn search_highlights(&self, value: &str) -> Vec<Range<usize>> {
+        match self.search_regex {
+            Some(ref regex) if !self.matching_cells.is_empty() => {
+                regex.find_iter(value).map(|m| m.range()).collect()
+            }
+            _ => vec![],
+        }
     }
 
     fn handle_shortcut(&mut self, ui: &mut Ui, array_response: &mut ArrayResponse) {
         let mut copied_value = None;
+        let mut moved_rows_up = None;
         let maybe_focused_id = ui.ctx().memory(|m| m.focused());
         ui.input_mut(|i| {
             if i.key_pressed(Key::Escape) {
@@ -1472,6 +3813,12 @@ impl<'array> ArrayTable<'array> {
                 }
             }
             if is_table_focused {
+                // Before arrow keys, which match with any alt modifier
+                if i.consume_shortcut(&SHORTCUT_MOVE_ROWS_UP) {
+                    moved_rows_up = Some(true);
+                } else if i.consume_shortcut(&SHORTCUT_MOVE_ROWS_DOWN) {
+                    moved_rows_up = Some(false);
+                }
                 if let Some(focused_cell) = self.focused_cell.as_mut() {
                     if i.consume_key(Modifiers::NONE, Key::Tab) {
                         if !focused_cell.is_pinned_column_table
@@ -1535,6 +3882,7 @@ impl<'array> ArrayTable<'array> {
                     let typed_alphanum = Self::get_typed_alphanum_from_events(i);
                     if (typed_alphanum.is_some() || i.consume_key(Modifiers::NONE, Key::Enter))
                         && !self.was_editing
+                        && !self.group_headers.contains_key(&focused_cell.row_index)
                     {
                         let row_index = self.filtered_nodes[focused_cell.row_index];
                         *self.editing_index.borrow_mut() = Some((
@@ -1557,10 +3905,9 @@ impl<'array> ArrayTable<'array> {
                                         col_index,
                                     );
                                     if let Some(index) = index {
-                                        row_data.entries()[index]
-                                            .value
-                                            .clone()
-                                            .map(|v| editing_value = v);
+                                        if let Some(v) = row_data.entries()[index].value.clone() {
+                                            editing_value = v;
+                                        }
                                     }
                                 }
                             }
@@ -1582,7 +3929,10 @@ impl<'array> ArrayTable<'array> {
                     self.open_replace_panel(None);
                 }
             }
-            let hovered_cell = array_response.hover_data.hovered_cell;
+            let hovered_cell = array_response
+                .hover_data
+                .hovered_cell
+                .filter(|cell| !self.group_headers.contains_key(&cell.row_index));
             for event in i.events.iter().filter(|e| match e {
                 egui::Event::Copy => hovered_cell.is_some(),
                 egui::Event::Paste(_) => hovered_cell.is_some(),
@@ -1605,8 +3955,7 @@ impl<'array> ArrayTable<'array> {
                     } => {
                         let columns = self.columns(cell_location.is_pinned_column_table);
                         let pointer = Self::pointer_key(
-                            &self.parent_pointer.pointer,
-                            row_index,
+                            &self.nodes[row_index].entries,
                             columns
                                 .get(cell_location.column_index)
                                 .as_ref()
@@ -1629,8 +3978,7 @@ impl<'array> ArrayTable<'array> {
                     egui::Event::Paste(v) => {
                         let columns = self.columns(cell_location.is_pinned_column_table);
                         let pointer = Self::pointer_key(
-                            &self.parent_pointer.pointer,
-                            row_index,
+                            &self.nodes[row_index].entries,
                             &columns
                                 .get(cell_location.column_index)
                                 .as_ref()
@@ -1647,12 +3995,9 @@ impl<'array> ArrayTable<'array> {
                             },
                             value: Some(v.clone()),
                         };
-                        match flat_json_value.pointer.value_type {
-                            // When we paste an object it should not be considered as parsed
-                            ValueType::Object(..) => {
-                                flat_json_value.pointer.value_type = ValueType::Object(false, 0)
-                            }
-                            _ => {}
+                        // When we paste an object it should not be considered as parsed
+                        if let ValueType::Object(..) = flat_json_value.pointer.value_type {
+                            flat_json_value.pointer.value_type = ValueType::Object(false, 0)
                         }
                         self.edit_cell(array_response, flat_json_value, row_index);
                     }
@@ -1670,6 +4015,18 @@ impl<'array> ArrayTable<'array> {
         if let Some(value) = copied_value {
             ui.ctx().copy_text(value.clone());
         }
+        if let Some(up) = moved_rows_up {
+            // Selected rows, or the focused row when there is no selection
+            let rows = if self.selected_rows.is_empty() {
+                self.focused_cell
+                    .filter(|cell| !self.is_group_header(cell.row_index))
+                    .map(|cell| vec![self.filtered_nodes[cell.row_index]])
+                    .unwrap_or_default()
+            } else {
+                self.selected_rows.iter().copied().collect()
+            };
+            self.move_rows_by_one(rows, up, array_response);
+        }
     }
 
     pub fn get_typed_alphanum_from_events(i: &mut InputState) -> Option<String> {
@@ -1733,26 +4090,329 @@ impl<'array> ArrayTable<'array> {
         typed_alphanum
     }
 
-    pub fn replace_columns(
+    /// Values that would change by replacing, with their current value.
+    fn replace_preview(
+        &self,
+        search_replace_response: SearchReplaceResponse,
+    ) -> Result<ReplacePreview, String> {
+        let nodes = &self.nodes;
+        let scope = search_replace_response.scope;
+        let rows = self.scope_rows(scope);
+        let occurrences = replace_occurrences(nodes, &rows, search_replace_response)?
+            .into_par_iter()
+            .filter_map(|(new_value, row_index)| {
+                let old_value = nodes[row_index]
+                    .entries
+                    .iter()
+                    .find(|entry| entry.pointer.pointer == new_value.pointer.pointer)
+                    .and_then(|entry| entry.value.clone());
+                if old_value == new_value.value {
+                    return None;
+                }
+                let column = new_value
+                    .pointer
+                    .pointer
+                    .strip_prefix(row_pointer(&nodes[row_index].entries))
+                    .unwrap_or(&new_value.pointer.pointer)
+                    .to_string();
+                Some(ReplaceOccurrence {
+                    row_index,
+                    column,
+                    old_value,
+                    new_value,
+                    confirmed: true,
+                })
+            })
+            .collect();
+        Ok(ReplacePreview::new(occurrences, scope))
+    }
+
+    /// Number of values of replaced columns matching search criteria, `None` for invalid pattern.
+    fn count_matching_values(
+        &self,
+        search_replace_response: SearchReplaceResponse,
+    ) -> Option<usize> {
+        let regex = search_regex(
+            &search_replace_response.search_criteria,
+            &search_replace_response.replace_mode,
+        )
+        .ok()?;
+        let column_ids = search_replace_response
+            .selected_column
+            .unwrap_or_default()
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<usize>>();
+        let rows = self.scope_rows(search_replace_response.scope);
+        Some(count_matching_values(
+            &self.nodes,
+            &rows,
+            &column_ids,
+            &regex,
+        ))
+    }
+
+    /// Values that would change by transform, with their current value.
+    fn transform_preview(
+        &self,
+        columns: &[Column],
+        transform: &Transform,
+        scope: ReplaceScope,
+    ) -> ReplacePreview {
+        let nodes = &self.nodes;
+        let rows = self.scope_rows(scope);
+        let occurrences = rows
+            .par_iter()
+            .flat_map_iter(|row_index| {
+                columns.iter().filter_map(move |column| {
+                    let pointer = Self::pointer_key(&nodes[*row_index].entries, &column.name);
+                    let entry = nodes[*row_index]
+                        .entries
+                        .iter()
+                        .find(|entry| entry.pointer.pointer == pointer);
+                    let old_value = entry.and_then(|entry| entry.value.clone());
+                    let new_value = transform.apply(old_value.as_deref())?;
+                    let value_type = value_type(&new_value);
+                    let new_value = to_cell_value(new_value);
+                    let same_type = match entry {
+                        Some(entry) => entry.pointer.value_type == value_type,
+                        None => true,
+                    };
+                    if new_value == old_value && same_type {
+                        return None;
+                    }
+                    let pointer = match entry {
+                        Some(entry) => PointerKey {
+                            value_type,
+                            ..entry.pointer.clone()
+                        },
+                        // Key is missing from this row, it is added
+                        None => PointerKey {
+                            pointer,
+                            value_type,
+                            depth: column.depth,
+                            position: 0,
+                            column_id: column.id,
+                        },
+                    };
+                    Some(ReplaceOccurrence {
+                        row_index: *row_index,
+                        column: column.name.to_string(),
+                        old_value,
+                        new_value: FlatJsonValue {
+                            pointer,
+                            value: new_value,
+                        },
+                        confirmed: true,
+                    })
+                })
+            })
+            .collect();
+        ReplacePreview::new(occurrences, scope)
+    }
+
+    /// Rename keys of rows in scope at every depth, then columns and view settings referring to
+    /// them when keys are renamed in all rows. Returns number of rows having renamed keys.
+    fn rename_keys(
         &mut self,
         search_replace_response: SearchReplaceResponse,
         array_response: &mut ArrayResponse,
-    ) {
-        // let start = std::time::Instant::now();
-        if let Some(ref columns) = search_replace_response.selected_column {
+    ) -> Result<usize, String> {
+        let regex = search_regex(
+            &search_replace_response.search_criteria,
+            &search_replace_response.replace_mode,
+        )?;
+        let replace_value = search_replace_response.replace_value.unwrap_or_default();
+        let mode = search_replace_response.replace_mode;
+        let rename = |key: &str| rename_key(key, &regex, &replace_value, &mode);
+        let scope = search_replace_response.scope;
+        let mut in_scope = vec![false; self.nodes.len()];
+        for row_index in self.scope_rows(scope) {
+            in_scope[row_index] = true;
+        }
+        let renamed_rows = self
+            .nodes
+            .par_iter_mut()
+            .filter(|row| in_scope[row.index])
+            .filter_map(|row| {
+                let row_pointer = row_pointer(&row.entries).to_string();
+                let mut renamed = false;
+                for entry in row.entries.iter_mut() {
+                    let Some(column) = entry.pointer.pointer.strip_prefix(row_pointer.as_str())
+                    else {
+                        continue;
+                    };
+                    if let Some(new_column) = rename_pointer_keys(column, &rename) {
+                        entry.pointer.column_id = column_id(&new_column);
+                        entry.pointer.pointer = concat_string!(row_pointer, new_column);
+                        renamed = true;
+                    }
+                    // Nested values, and row root, are serialized with their keys
+                    if matches!(
+                        entry.pointer.value_type,
+                        ValueType::Object(..) | ValueType::Array(_)
+                    ) {
+                        if let Some(new_value) = entry
+                            .value
+                            .as_deref()
+                            .and_then(|value| rename_serialized_keys(value, &rename))
+                        {
+                            entry.value = Some(new_value);
+                            renamed = true;
+                        }
+                    }
+                }
+                renamed.then_some(row.index)
+            })
+            .collect::<Vec<usize>>();
+        if renamed_rows.is_empty() {
+            return Ok(0);
+        }
+
+        // Computed columns are not keys of rows
+        let computed_columns = self
+            .computed_columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<HashSet<String>>();
+        let rename_column = |name: &str| {
+            if computed_columns.contains(name) {
+                None
+            } else {
+                rename_pointer_keys(name, &rename)
+            }
+        };
+        // Other rows may still have the old keys, in which case old columns are kept
+        let rename_all = matches!(scope, ReplaceScope::All);
+        let mut all_columns: Vec<Column<'array>> = Vec::with_capacity(self.all_columns.len());
+        for column in mem::take(&mut self.all_columns) {
+            let new_column = rename_column(&column.name).map(|new_name| Column {
+                id: column_id(&new_name),
+                name: Cow::Owned(new_name),
+                ..column.clone()
+            });
+            let columns = match new_column {
+                Some(new_column) if rename_all => vec![new_column],
+                Some(new_column) => vec![column, new_column],
+                None => vec![column],
+            };
             for column in columns {
-                self.columns_filter.remove(column.name.as_str());
+                match all_columns.iter_mut().find(|c| c.name == column.name) {
+                    Some(existing_column) => existing_column.seen_count += column.seen_count,
+                    None => all_columns.push(column),
+                }
+            }
+        }
+        self.all_columns = all_columns;
+        if rename_all {
+            for column in self.column_pinned.iter_mut() {
+                if let Some(new_name) = rename_column(&column.name) {
+                    column.id = column_id(&new_name);
+                    column.name = Cow::Owned(new_name);
+                }
+            }
+            let renamed = |name: &String| rename_column(name).unwrap_or_else(|| name.clone());
+            self.columns_filter = mem::take(&mut self.columns_filter)
+                .into_iter()
+                .map(|(column, values)| (renamed(&column), values))
+                .collect();
+            if let Some((ref mut column, _)) = self.sort {
+                *column = renamed(column);
+            }
+            for column in self.group_by.iter_mut() {
+                *column = renamed(column);
+            }
+            for rule in self.formatting.rules.iter_mut() {
+                rule.column = renamed(&rule.column);
+            }
+            for column in self.column_order.iter_mut() {
+                *column = renamed(column);
+            }
+            self.hidden_columns = mem::take(&mut self.hidden_columns)
+                .iter()
+                .map(renamed)
+                .collect();
+            self.column_widths = mem::take(&mut self.column_widths)
+                .into_iter()
+                .map(|(column, width)| (renamed(&column), width))
+                .collect();
+        }
+        self.select_columns(self.max_depth);
+
+        if self.is_sub_table {
+            array_response
+                .edited_value
+                .push(self.serialized_sub_table());
+        } else {
+            array_response.edited_value.extend(
+                renamed_rows
+                    .iter()
+                    .filter_map(|row_index| self.nodes[*row_index].entries.last().cloned()),
+            );
+        }
+        // Sub tables display values with their old keys
+        self.windows.clear();
+        self.cache.borrow_mut().evict();
+        self.footer_aggregates = None;
+        self.formatting.clear_ranges();
+        self.validate_rows();
+        self.compute_columns();
+        self.do_filter_column();
+        self.open_replace_panel(None);
+        Ok(renamed_rows.len())
+    }
+
+    /// Row index of rows in which to replace.
+    fn scope_rows(&self, scope: ReplaceScope) -> Vec<usize> {
+        match scope {
+            ReplaceScope::All => (0..self.nodes.len()).collect(),
+            ReplaceScope::Filtered => self.filtered_rows().collect(),
+            ReplaceScope::Selected => self.selected_rows.iter().copied().collect(),
+        }
+    }
+
+    /// Replace ticked occurrences of preview.
+    fn replace_columns(&mut self, preview: ReplacePreview, array_response: &mut ArrayResponse) {
+        // let start = std::time::Instant::now();
+        // Replacing in filtered or selected rows keeps filters, so the same subset can be edited
+        // again
+        if matches!(preview.scope, ReplaceScope::All) {
+            for (column, _) in preview.column_counts.iter() {
+                self.columns_filter.remove(column.as_str());
             }
         }
-        let mut occurrences = replace_occurrences(&mut self.nodes, search_replace_response);
-        if self.is_sub_table || occurrences.len() < 100 {
-            for (flat_json_value, row_index) in occurrences {
+        self.update_values(preview.into_confirmed(), array_response);
+        // println!("took {}ms to update columns", start.elapsed().as_millis());
+        self.do_filter_column();
+    }
+
+    /// Apply new values to rows, in parallel when there are many of them.
+    fn update_values(
+        &mut self,
+        mut updates: Vec<(FlatJsonValue<String>, usize)>,
+        array_response: &mut ArrayResponse,
+    ) {
+        if self.is_sub_table || updates.len() < 100 {
+            for (flat_json_value, row_index) in updates {
                 self.edit_cell(array_response, flat_json_value, row_index);
             }
         } else {
-            for (flat_json_value, row_index) in occurrences.iter() {
+            for (flat_json_value, row_index) in updates.iter() {
                 self.update_sub_tables_value(flat_json_value, *row_index);
             }
+            array_response
+                .edited_value
+                .extend(updates.iter().map(|(entry, _)| entry.clone()));
+            let edited_columns = updates
+                .iter()
+                .filter_map(|(entry, row_index)| {
+                    entry
+                        .pointer
+                        .pointer
+                        .strip_prefix(row_pointer(&self.nodes[*row_index].entries))
+                        .map(str::to_string)
+                })
+                .collect::<HashSet<String>>();
             let json_array = mem::take(&mut self.nodes);
             let mut len = json_array.len();
             let new_json_array = Arc::new(Mutex::new(json_array));
@@ -1760,7 +4420,7 @@ impl<'array> ArrayTable<'array> {
             if len < 8 {
                 len = 8;
             }
-            let chunks = occurrences.par_chunks_mut(len / 8);
+            let chunks = updates.par_chunks_mut(len / 8);
             chunks.into_par_iter().for_each(|chunk| {
                 for (updated_entry, row_index) in chunk {
                     let mut json_array_entry = {
@@ -1780,9 +4440,15 @@ impl<'array> ArrayTable<'array> {
             let mut new_json_array_guard = new_json_array.lock().unwrap();
             self.nodes = mem::take(&mut new_json_array_guard);
             self.cache.borrow_mut().evict();
+            self.validate_rows();
+            self.compute_columns();
+            self.formatting.clear_ranges();
+            if self.footer_aggregates.is_some() {
+                self.stale_footer_columns.extend(edited_columns);
+                self.stale_footer_columns
+                    .extend(self.computed_columns.iter().map(|c| c.name.clone()));
+            }
         }
-        // println!("took {}ms to update columns", start.elapsed().as_millis());
-        self.do_filter_column();
     }
 
     pub fn open_replace_panel(&mut self, selected_column: Option<Column<'array>>) {
@@ -1794,7 +4460,529 @@ impl<'array> ArrayTable<'array> {
             self.search_replace_panel
                 .set_title(format!("Replace in {}", self.parent_pointer.pointer));
         }
-        self.search_replace_panel
-            .set_columns(self.all_columns().clone());
+        let columns = self
+            .all_columns
+            .iter()
+            .filter(|c| self.computed_column(&c.name).is_none())
+            .cloned()
+            .collect();
+        self.search_replace_panel.set_columns(columns);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregates::Aggregate;
+    use crate::array_table::{ArrayTable, NewRow};
+    use crate::panels::{ReplaceMode, ReplaceScope};
+    use crate::parser::{as_array, save_to_buffer, SaveFormat, SaveOptions};
+    use crate::replace_panel::{Transform, TransformKind};
+    use crate::schema::SchemaError;
+    use crate::ArrayResponse;
+    use json_flat_parser::{FlatJsonValue, JSONParser, ParseOptions, PointerKey, ValueType};
+
+    fn table(json: &str) -> ArrayTable<'static> {
+        let options = ParseOptions::default()
+            .parse_array(false)
+            .max_depth(u8::MAX);
+        let result = JSONParser::parse(json, options).unwrap().to_owned();
+        let parse_result = result.clone_except_json();
+        let (nodes, columns) = as_array(result).unwrap();
+        let depth = (parse_result.depth_after_start_at + 1).max(
+            parse_result
+                .parsing_max_depth
+                .min(parse_result.max_json_depth as u8),
+        );
+        let len = nodes.len();
+        ArrayTable::new(
+            Some(parse_result),
+            nodes,
+            columns,
+            depth,
+            PointerKey::from_pointer(String::new(), ValueType::Array(len), 1, 0),
+        )
+    }
+
+    fn edit(table: &mut ArrayTable, row_index: usize, column: &str, value: &str) {
+        let column = table
+            .all_columns
+            .iter()
+            .find(|c| c.name == column)
+            .unwrap()
+            .clone();
+        let entry = FlatJsonValue {
+            pointer: PointerKey {
+                pointer: ArrayTable::pointer_key(&table.nodes[row_index].entries, &column.name),
+                value_type: column.value_type,
+                depth: column.depth,
+                position: 0,
+                column_id: column.id,
+            },
+            value: Some(value.to_string()),
+        };
+        table.update_value(entry, row_index, true);
+    }
+
+    #[test]
+    fn test_footer_aggregates_after_edit() {
+        let mut table = table(r#"[{"id": 1, "hp": 10}, {"id": 2, "hp": 30}, {"id": 3, "hp": 20}]"#);
+        table.set_show_aggregates_footer(true);
+        table.update_footer_aggregates();
+        let hp = |table: &ArrayTable, aggregate| {
+            table.footer_aggregates.as_ref().unwrap()["/hp"].get(aggregate)
+        };
+        assert_eq!(hp(&table, Aggregate::Sum), Some(60.0));
+
+        // Neither min nor max: aggregates are updated in place
+        edit(&mut table, 2, "/hp", "25");
+        assert!(table.stale_footer_columns.is_empty());
+        assert_eq!(hp(&table, Aggregate::Sum), Some(65.0));
+        assert_eq!(hp(&table, Aggregate::Max), Some(30.0));
+
+        // Former max: column is computed again from all rows
+        edit(&mut table, 1, "/hp", "5");
+        assert!(table.stale_footer_columns.contains("/hp"));
+        table.update_footer_aggregates();
+        assert!(table.stale_footer_columns.is_empty());
+        assert_eq!(hp(&table, Aggregate::Sum), Some(40.0));
+        assert_eq!(hp(&table, Aggregate::Min), Some(5.0));
+        assert_eq!(hp(&table, Aggregate::Max), Some(25.0));
+
+        // Only selected rows are aggregated once there is a selection
+        table.selected_rows.insert(0);
+        table.footer_aggregates = None;
+        table.update_footer_aggregates();
+        assert_eq!(hp(&table, Aggregate::Count), Some(1.0));
+        edit(&mut table, 2, "/hp", "100");
+        assert_eq!(hp(&table, Aggregate::Max), Some(10.0));
+    }
+
+    #[test]
+    fn test_replace_scope_rows() {
+        let mut table = table(
+            r#"[{"type": "boss", "hp": 10}, {"type": "minion", "hp": 1}, {"type": "boss", "hp": 20}, {"type": "minion", "hp": 2}]"#,
+        );
+        table.set_column_filter("/type".to_string(), vec!["boss".to_string()]);
+        table.selected_rows.insert(1);
+        table.selected_rows.insert(2);
+        let rows = |scope| {
+            let mut rows = table.scope_rows(scope);
+            rows.sort();
+            rows
+        };
+        assert_eq!(rows(ReplaceScope::All), vec![0, 1, 2, 3]);
+        assert_eq!(rows(ReplaceScope::Filtered), vec![0, 2]);
+        assert_eq!(rows(ReplaceScope::Selected), vec![1, 2]);
+
+        // Preview only has occurrences of rows in scope
+        let columns = vec![table
+            .all_columns
+            .iter()
+            .find(|c| c.name == "/hp")
+            .unwrap()
+            .clone()];
+        let transform = Transform::new(TransformKind::Add, "1", "").unwrap();
+        let preview_rows = |scope| {
+            let mut rows = table
+                .transform_preview(&columns, &transform, scope)
+                .occurrences
+                .iter()
+                .map(|occurrence| occurrence.row_index)
+                .collect::<Vec<usize>>();
+            rows.sort();
+            rows
+        };
+        assert_eq!(preview_rows(ReplaceScope::All), vec![0, 1, 2, 3]);
+        assert_eq!(preview_rows(ReplaceScope::Filtered), vec![0, 2]);
+        assert_eq!(preview_rows(ReplaceScope::Selected), vec![1, 2]);
+    }
+
+    fn value(table: &ArrayTable, row_index: usize, column: &str) -> Option<String> {
+        let pointer = ArrayTable::pointer_key(&table.nodes[row_index].entries, column);
+        table.nodes[row_index]
+            .entries
+            .iter()
+            .find(|entry| entry.pointer.pointer == pointer)
+            .and_then(|entry| entry.value.clone())
+    }
+
+    #[test]
+    fn test_set_next_id() {
+        let next_id = |json: &str| {
+            let mut table = table(json);
+            table.set_next_id("/id", 0, &mut ArrayResponse::default());
+            value(&table, 0, "/id")
+        };
+        assert_eq!(
+            next_id(r#"[{"id": 3}, {"id": 7}, {"id": 5}]"#),
+            Some("8".to_string())
+        );
+        // Max id is not an integer
+        assert_eq!(
+            next_id(r#"[{"id": 1}, {"id": 2.5}]"#),
+            Some("3".to_string())
+        );
+        // Ids which are not numbers are ignored
+        assert_eq!(
+            next_id(r#"[{"id": "abc"}, {"id": 2}]"#),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            next_id(r#"[{"id": "inf"}, {"id": 2}]"#),
+            Some("3".to_string())
+        );
+        // No id at all, the key is added
+        assert_eq!(
+            next_id(r#"[{"name": "orc"}, {"id": null}]"#),
+            Some("1".to_string())
+        );
+        assert_eq!(next_id(r#"[{"id": "abc"}]"#), Some("1".to_string()));
+    }
+
+    fn saved(table: &ArrayTable) -> String {
+        let mut buffer = vec![];
+        let save_options = SaveOptions {
+            format: SaveFormat::Compact,
+            ..Default::default()
+        };
+        save_to_buffer("", table.nodes(), &mut buffer, &save_options).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    /// Open sub table of a cell, then edit its array as the sub table does.
+    fn open_sub_table(table: &mut ArrayTable<'static>, row_index: usize, column: &str) {
+        let pointer = ArrayTable::pointer_key(&table.nodes[row_index].entries, column);
+        let entry = table.nodes[row_index]
+            .entries
+            .iter()
+            .find(|entry| entry.pointer.pointer == pointer)
+            .unwrap()
+            .clone();
+        let content = entry.value.clone().unwrap();
+        let subtable = table.open_subtable(row_index, &entry, content).unwrap();
+        table.windows.push(subtable);
+    }
+
+    fn edit_sub_table(table: &mut ArrayTable, window: usize, column: &str, value: &str) {
+        let (row_index, row_pointer) = (
+            table.windows[window].id(),
+            table.windows[window].row_pointer().to_string(),
+        );
+        let mut entry = table.nodes[row_index]
+            .entries
+            .iter()
+            .find(|entry| entry.pointer.pointer.ends_with(column))
+            .unwrap()
+            .clone();
+        entry.pointer.pointer = format!("{}{}", row_pointer, column);
+        entry.value = Some(value.to_string());
+        table.update_from_sub_table(
+            row_index,
+            &row_pointer,
+            vec![entry],
+            &mut ArrayResponse::default(),
+        );
+    }
+
+    #[test]
+    fn test_sub_tables_after_insert_and_delete() {
+        let mut table =
+            table(r#"[{"id": 1, "items": [1]}, {"id": 2, "items": [2]}, {"id": 3, "items": [3]}]"#);
+        open_sub_table(&mut table, 0, "/items");
+        open_sub_table(&mut table, 2, "/items");
+        let windows = |table: &ArrayTable| {
+            table
+                .windows
+                .iter()
+                .map(|window| (window.id(), window.name().clone()))
+                .collect::<Vec<(usize, String)>>()
+        };
+
+        // Sub table of following row follows it
+        table.insert_new_row(1, 0, NewRow::Empty, &mut ArrayResponse::default());
+        assert_eq!(
+            windows(&table),
+            vec![(0, "/0/items".to_string()), (3, "/3/items".to_string())]
+        );
+        edit_sub_table(&mut table, 1, "/items", "[30]");
+        assert_eq!(
+            saved(&table),
+            r#"[{"id":1,"items":[1]},{},{"id":2,"items":[2]},{"id":3,"items":[30]}]"#
+        );
+
+        // Sub table of deleted row is closed
+        table.delete_row(0, &mut ArrayResponse::default());
+        assert_eq!(windows(&table), vec![(2, "/2/items".to_string())]);
+        edit_sub_table(&mut table, 0, "/items", "[300]");
+        assert_eq!(
+            saved(&table),
+            r#"[{},{"id":2,"items":[2]},{"id":3,"items":[300]}]"#
+        );
+    }
+
+    #[test]
+    fn test_move_rows() {
+        let mut table = table(r#"[{"id": 0}, {"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]"#);
+        let ids = |table: &ArrayTable| {
+            (0..table.nodes.len())
+                .map(|row_index| value(table, row_index, "/id").unwrap())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        table.selected_rows.extend([1, 3]);
+
+        // Rows move together, past the next row which is not moved
+        table.move_rows_by_one(vec![1, 3], false, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "0,2,4,1,3");
+        assert_eq!(
+            table.selected_rows.iter().copied().collect::<Vec<usize>>(),
+            vec![3, 4]
+        );
+        // Last rows can't move down
+        table.move_rows_by_one(vec![3, 4], false, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "0,2,4,1,3");
+
+        table.move_rows_by_one(vec![3, 4], true, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "0,2,1,3,4");
+        table.move_rows(&[2, 3], 0, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "1,3,0,2,4");
+        // First rows can't move up
+        table.move_rows_by_one(vec![0, 1], true, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "1,3,0,2,4");
+
+        // Moved at the end
+        table.move_rows(&[0], 5, &mut ArrayResponse::default());
+        assert_eq!(ids(&table), "3,0,2,4,1");
+        assert_eq!(
+            saved(&table),
+            r#"[{"id":3},{"id":0},{"id":2},{"id":4},{"id":1}]"#
+        );
+    }
+
+    #[test]
+    fn test_sub_tables_after_move() {
+        let mut table =
+            table(r#"[{"id": 1, "items": [1]}, {"id": 2, "items": [2]}, {"id": 3, "items": [3]}]"#);
+        open_sub_table(&mut table, 0, "/items");
+        open_sub_table(&mut table, 2, "/items");
+        table.move_rows(&[0], 3, &mut ArrayResponse::default());
+        let windows = table
+            .windows
+            .iter()
+            .map(|window| (window.id(), window.name().as_str()))
+            .collect::<Vec<(usize, &str)>>();
+        assert_eq!(windows, vec![(2, "/2/items"), (1, "/1/items")]);
+        edit_sub_table(&mut table, 0, "/items", "[10]");
+        edit_sub_table(&mut table, 1, "/items", "[30]");
+        assert_eq!(
+            saved(&table),
+            r#"[{"id":2,"items":[2]},{"id":3,"items":[30]},{"id":1,"items":[10]}]"#
+        );
+    }
+
+    fn column_names(columns: &[crate::array_table::Column]) -> Vec<String> {
+        columns.iter().map(|c| c.name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_column_layout() {
+        let json = r#"[{"id": 1, "name": "orc", "hp": 10, "race": "orc"}]"#;
+        let mut table = table(json);
+        let layout = |columns: &[(&str, bool)]| {
+            columns
+                .iter()
+                .map(|(name, visible)| (name.to_string(), *visible))
+                .collect::<Vec<(String, bool)>>()
+        };
+        assert_eq!(
+            table.column_layout(),
+            layout(&[
+                ("/id", true),
+                ("/name", true),
+                ("/hp", true),
+                ("/race", true)
+            ])
+        );
+
+        table.set_column_layout(layout(&[
+            ("/race", true),
+            ("/id", false),
+            ("/hp", true),
+            ("/name", true),
+        ]));
+        assert_eq!(
+            column_names(&table.column_selected),
+            vec!["/race", "/hp", "/name"]
+        );
+        // Hidden columns stay listed at their place
+        assert_eq!(
+            table.column_layout(),
+            layout(&[
+                ("/race", true),
+                ("/id", false),
+                ("/hp", true),
+                ("/name", true)
+            ])
+        );
+
+        // Layout is restored from workspace, pinned columns are not part of it
+        let mut view_state = table.view_state();
+        view_state.pinned_columns.push("/hp".to_string());
+        let mut restored = self::table(json);
+        restored.restore_view_state(view_state);
+        assert_eq!(column_names(&restored.column_pinned), vec!["/#", "/hp"]);
+        assert_eq!(
+            column_names(&restored.column_selected),
+            vec!["/race", "/name"]
+        );
+        assert_eq!(
+            restored.column_layout(),
+            layout(&[("/race", true), ("/id", false), ("/name", true)])
+        );
+
+        // Columns missing from layout follow listed ones, in their former order
+        table.set_column_layout(layout(&[("/id", true), ("/hp", true)]));
+        assert_eq!(
+            column_names(&table.column_selected),
+            vec!["/id", "/hp", "/race", "/name"]
+        );
+    }
+
+    fn with_ui(run: impl FnOnce(&egui::Ui)) {
+        let ctx = egui::Context::default();
+        let mut run = Some(run);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| run.take().unwrap()(ui));
+        });
+    }
+
+    #[test]
+    fn test_fit_columns() {
+        let json = r#"[{"id": 1, "name": "a much longer name than its column one"}, {"id": 2}]"#;
+        let mut table = table(json);
+        table.visible_rows = 0..2;
+        with_ui(|ui| {
+            table.fit_columns(ui);
+            let id_width = table.column_widths["/id"];
+            let name_width = table.column_widths["/name"];
+            // Fitted to column name when values are narrower, row number column is left as is
+            let name_column = |name: &str| {
+                crate::components::cell_text::CellText::text_width(ui, name)
+                    + ui.spacing().item_spacing.x * 2.0
+            };
+            assert_eq!(id_width, name_column("/id"));
+            assert!(name_width > name_column("/name"));
+            assert!(!table.column_widths.contains_key("/#"));
+        });
+
+        // Widths are restored from workspace
+        let view_state = table.view_state();
+        let mut restored = self::table(json);
+        restored.restore_view_state(view_state);
+        assert_eq!(restored.column_widths, table.column_widths);
+    }
+
+    #[test]
+    fn test_drawn_text() {
+        assert_eq!(ArrayTable::drawn_text("orc"), "orc");
+        let long_value = "a".repeat(1500);
+        assert_eq!(ArrayTable::drawn_text(&long_value).len(), 1000);
+        // 1000th byte is in the middle of a char
+        let long_value = format!("{}{}", "a".repeat(999), "é".repeat(10));
+        assert_eq!(ArrayTable::drawn_text(&long_value), "a".repeat(999));
+    }
+
+    #[test]
+    fn test_wrapped_row_height_by_column_name() {
+        let mut table = table(r#"[{"id": 1, "text": "a few words which have to be wrapped"}]"#);
+        table.wrap_rows = true;
+        with_ui(|ui| {
+            let text_height = 10.0;
+            // Widths are found by column name, whatever table and position columns are drawn at
+            table.drawn_column_widths.insert("/id".to_string(), 10.0);
+            table
+                .drawn_column_widths
+                .insert("/text".to_string(), 1000.0);
+            let one_line = table.wrapped_row_height(ui, 0, text_height);
+            table.drawn_column_widths.insert("/text".to_string(), 40.0);
+            let wrapped = table.wrapped_row_height(ui, 0, text_height);
+            assert!(wrapped > one_line);
+            assert!(wrapped <= text_height * crate::array_table::MAX_WRAPPED_LINES as f32);
+        });
+    }
+
+    #[test]
+    fn test_materialize_computed_column() {
+        let mut table = table(r#"[{"id": 1, "hp": 10}, {"id": 2, "hp": 20}]"#);
+        table.add_computed_column("double", "/hp * 2").unwrap();
+        table.materialize_computed_column("/double", &mut ArrayResponse::default());
+        assert!(table.computed_column("/double").is_none());
+        assert_eq!(value(&table, 1, "/double"), Some("40".to_string()));
+        assert_eq!(
+            saved(&table),
+            r#"[{"id":1,"hp":10,"double":20},{"id":2,"hp":20,"double":40}]"#
+        );
+    }
+
+    #[test]
+    fn test_goto_next_schema_error_in_pinned_column() {
+        let mut table = table(r#"[{"id": 1, "name": "a"}, {"id": "2", "name": 3}]"#);
+        let id_index = table
+            .column_selected
+            .iter()
+            .position(|c| c.name == "/id")
+            .unwrap();
+        let id = table.column_selected.remove(id_index);
+        table.column_pinned.push(id);
+        table.schema_errors.insert(
+            1,
+            vec![SchemaError {
+                pointer: "/id".to_string(),
+                message: "\"2\" is not of type \"integer\"".to_string(),
+            }],
+        );
+        table.goto_next_schema_error();
+        let focused_cell = table.focused_cell.unwrap();
+        assert_eq!(focused_cell.row_index, 1);
+        assert!(focused_cell.is_pinned_column_table);
+        assert_eq!(table.column_pinned[focused_cell.column_index].name, "/id");
+
+        table.schema_errors.insert(
+            1,
+            vec![SchemaError {
+                pointer: "/name".to_string(),
+                message: "3 is not of type \"string\"".to_string(),
+            }],
+        );
+        table.goto_next_schema_error();
+        let focused_cell = table.focused_cell.unwrap();
+        assert!(!focused_cell.is_pinned_column_table);
+        assert_eq!(
+            table.column_selected[focused_cell.column_index].name,
+            "/name"
+        );
+    }
+
+    #[test]
+    fn test_search_reports_matching_key_once() {
+        let mut table = table(r#"[{"id": 1, "level": 2}, {"id": 2, "level": 3}, {"id": 3}]"#);
+        table.scroll_to_row = "level|^3$".to_string();
+        table.search_mode = ReplaceMode::Regex;
+        table.search();
+        let matches = table
+            .matching_cells
+            .iter()
+            .map(|m| (m.table_row_index, m.column.as_str()))
+            .collect::<Vec<(Option<usize>, &str)>>();
+        assert_eq!(
+            matches,
+            vec![(None, "/level"), (Some(1), "/level"), (Some(2), "/id")]
+        );
+        assert!(table.is_matching_key("/level"));
+        assert!(!table.is_matching_key("/id"));
+        // Key match scrolls to its column without focusing a cell
+        assert!(table.focused_cell.is_none());
+        table.next_matching_cell();
+        assert_eq!(table.focused_cell.unwrap().row_index, 1);
     }
 }
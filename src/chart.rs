@@ -0,0 +1,507 @@
+//! Charts of column values: histogram of a numeric column, bar chart of value counts and scatter
+//! plot of two numeric columns.
+
+use crate::aggregates::format_number;
+use eframe::egui::{
+    pos2, vec2, Align2, Color32, FontId, Rect, Response, Rounding, Sense, Stroke, Ui,
+};
+use indexmap::IndexSet;
+use std::collections::HashMap;
+
+const MAX_BINS: usize = 50;
+const MAX_BARS: usize = 50;
+// Scatter plot points are sampled above this count
+const MAX_POINTS: usize = 20_000;
+const CHART_HEIGHT: f32 = 260.0;
+const AXIS_MARGIN: f32 = 48.0;
+// Room for value labels left of value count bars
+const LABEL_MARGIN: f32 = 120.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum ChartKind {
+    #[default]
+    Histogram,
+    ValueCounts,
+    Scatter,
+}
+
+impl ChartKind {
+    pub const ALL: [ChartKind; 3] = [
+        ChartKind::Histogram,
+        ChartKind::ValueCounts,
+        ChartKind::Scatter,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChartKind::Histogram => "Histogram",
+            ChartKind::ValueCounts => "Value counts",
+            ChartKind::Scatter => "Scatter",
+        }
+    }
+
+    /// Whether plotted columns have to be numeric.
+    pub fn is_numeric(&self) -> bool {
+        !matches!(self, ChartKind::ValueCounts)
+    }
+}
+
+struct Bin {
+    start: f64,
+    end: f64,
+    // Distinct values falling in the bin, used to filter rows on click
+    values: IndexSet<String>,
+    count: usize,
+}
+
+enum ChartData {
+    Histogram(Vec<Bin>),
+    // Most frequent values first
+    ValueCounts {
+        bars: Vec<(String, usize)>,
+        other_values: usize,
+    },
+    Scatter(Vec<(f64, f64)>),
+}
+
+pub struct Chart {
+    pub kind: ChartKind,
+    pub column: String,
+    pub y_column: Option<String>,
+    data: ChartData,
+}
+
+impl Chart {
+    /// `values` are (column value, y column value) of each plotted row.
+    pub fn new(
+        kind: ChartKind,
+        column: String,
+        y_column: Option<String>,
+        values: Vec<(Option<String>, Option<String>)>,
+    ) -> Self {
+        let data = match kind {
+            ChartKind::Histogram => ChartData::Histogram(Self::histogram(
+                values.into_iter().filter_map(|(value, _)| value).collect(),
+            )),
+            ChartKind::ValueCounts => {
+                let mut counts: HashMap<String, usize> = HashMap::new();
+                for value in values.into_iter().filter_map(|(value, _)| value) {
+                    *counts.entry(value).or_default() += 1;
+                }
+                let mut bars = counts.into_iter().collect::<Vec<(String, usize)>>();
+                bars.sort_by(|(a_value, a), (b_value, b)| b.cmp(a).then(a_value.cmp(b_value)));
+                let other_values = bars.iter().skip(MAX_BARS).map(|(_, count)| count).sum();
+                bars.truncate(MAX_BARS);
+                ChartData::ValueCounts { bars, other_values }
+            }
+            ChartKind::Scatter => {
+                let points = values
+                    .into_iter()
+                    .filter_map(|(x, y)| Some((x?.parse::<f64>().ok()?, y?.parse::<f64>().ok()?)))
+                    .filter(|(x, y)| x.is_finite() && y.is_finite())
+                    .collect::<Vec<(f64, f64)>>();
+                let step = points.len().div_ceil(MAX_POINTS).max(1);
+                ChartData::Scatter(points.into_iter().step_by(step).collect())
+            }
+        };
+        Self {
+            kind,
+            column,
+            y_column,
+            data,
+        }
+    }
+
+    fn histogram(values: Vec<String>) -> Vec<Bin> {
+        let values = values
+            .into_iter()
+            .filter_map(|value| Some((value.parse::<f64>().ok()?, value)))
+            .filter(|(number, _)| number.is_finite())
+            .collect::<Vec<(f64, String)>>();
+        if values.is_empty() {
+            return vec![];
+        }
+        let (min, max) = values
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), (n, _)| {
+                (min.min(*n), max.max(*n))
+            });
+        let bins_count = if min == max {
+            1
+        } else {
+            ((values.len() as f64).sqrt().ceil() as usize).clamp(1, MAX_BINS)
+        };
+        let bin_width = (max - min) / bins_count as f64;
+        let mut bins = (0..bins_count)
+            .map(|i| Bin {
+                start: min + i as f64 * bin_width,
+                end: if i + 1 == bins_count {
+                    max
+                } else {
+                    min + (i + 1) as f64 * bin_width
+                },
+                values: IndexSet::new(),
+                count: 0,
+            })
+            .collect::<Vec<Bin>>();
+        for (number, value) in values {
+            let index = if bin_width == 0.0 {
+                0
+            } else {
+                (((number - min) / bin_width) as usize).min(bins_count - 1)
+            };
+            bins[index].count += 1;
+            bins[index].values.insert(value);
+        }
+        bins
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match &self.data {
+            ChartData::Histogram(bins) => bins.is_empty(),
+            ChartData::ValueCounts { bars, .. } => bars.is_empty(),
+            ChartData::Scatter(points) => points.is_empty(),
+        }
+    }
+
+    /// Draw chart, returns values of the clicked bar or bin.
+    pub fn ui(&self, ui: &mut Ui) -> Option<Vec<String>> {
+        match &self.data {
+            ChartData::Histogram(bins) => Self::histogram_ui(ui, bins),
+            ChartData::ValueCounts { bars, other_values } => {
+                let clicked = Self::value_counts_ui(ui, bars);
+                if *other_values > 0 {
+                    ui.weak(format!("{} rows with other values", other_values));
+                }
+                clicked
+            }
+            ChartData::Scatter(points) => {
+                Self::scatter_ui(ui, points);
+                None
+            }
+        }
+    }
+
+    fn allocate(ui: &mut Ui, height: f32, left_margin: f32) -> (Response, Rect) {
+        let width = ui.available_width().max(200.0);
+        let (response, painter) = ui.allocate_painter(vec2(width, height), Sense::click());
+        let rect = response.rect;
+        painter.rect_stroke(
+            rect,
+            Rounding::ZERO,
+            ui.visuals().widgets.noninteractive.bg_stroke,
+        );
+        let plot_rect = Rect::from_min_max(
+            pos2(rect.left() + left_margin, rect.top() + 8.0),
+            pos2(rect.right() - 8.0, rect.bottom() - 20.0),
+        );
+        (response, plot_rect)
+    }
+
+    fn histogram_ui(ui: &mut Ui, bins: &[Bin]) -> Option<Vec<String>> {
+        let (response, plot_rect) = Self::allocate(ui, CHART_HEIGHT, AXIS_MARGIN);
+        let max_count = bins.iter().map(|bin| bin.count).max().unwrap_or(0).max(1);
+        let bar_width = plot_rect.width() / bins.len().max(1) as f32;
+        let painter = ui.painter();
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(11.0);
+        let hover_position = response.hover_pos();
+        let mut hovered_bin = None;
+        for (i, bin) in bins.iter().enumerate() {
+            let height = plot_rect.height() * bin.count as f32 / max_count as f32;
+            let bar_rect = Rect::from_min_max(
+                pos2(
+                    plot_rect.left() + i as f32 * bar_width,
+                    plot_rect.bottom() - height,
+                ),
+                pos2(
+                    plot_rect.left() + (i + 1) as f32 * bar_width - 1.0,
+                    plot_rect.bottom(),
+                ),
+            );
+            let column_rect = Rect::from_x_y_ranges(bar_rect.x_range(), plot_rect.y_range());
+            let hovered = hover_position.is_some_and(|position| column_rect.contains(position));
+            if hovered {
+                hovered_bin = Some(bin);
+            }
+            painter.rect_filled(bar_rect, Rounding::ZERO, Self::bar_color(ui, hovered));
+        }
+        Self::y_axis(ui, plot_rect, max_count as f64, 0.0);
+        if let (Some(first), Some(last)) = (bins.first(), bins.last()) {
+            painter.text(
+                plot_rect.left_bottom() + vec2(0.0, 4.0),
+                Align2::LEFT_TOP,
+                format_number(first.start),
+                font.clone(),
+                text_color,
+            );
+            painter.text(
+                plot_rect.right_bottom() + vec2(0.0, 4.0),
+                Align2::RIGHT_TOP,
+                format_number(last.end),
+                font,
+                text_color,
+            );
+        }
+        let clicked = response.clicked();
+        if let Some(bin) = hovered_bin {
+            response.on_hover_text_at_pointer(format!(
+                "[{}, {}]: {} rows\nClick to filter rows in this range",
+                format_number(bin.start),
+                format_number(bin.end),
+                bin.count
+            ));
+            if clicked {
+                return Some(bin.values.iter().cloned().collect());
+            }
+        }
+        None
+    }
+
+    fn value_counts_ui(ui: &mut Ui, bars: &[(String, usize)]) -> Option<Vec<String>> {
+        let row_height = 18.0;
+        let (response, plot_rect) =
+            Self::allocate(ui, bars.len() as f32 * row_height + 28.0, LABEL_MARGIN);
+        let max_count = bars
+            .iter()
+            .map(|(_, count)| *count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let painter = ui.painter();
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(11.0);
+        let hover_position = response.hover_pos();
+        let mut hovered_bar = None;
+        for (i, (value, count)) in bars.iter().enumerate() {
+            let top = plot_rect.top() + i as f32 * row_height;
+            let row_rect =
+                Rect::from_x_y_ranges(response.rect.x_range(), top..=top + row_height - 2.0);
+            let hovered = hover_position.is_some_and(|position| row_rect.contains(position));
+            if hovered {
+                hovered_bar = Some((value, count));
+            }
+            let width = plot_rect.width() * 0.85 * *count as f32 / max_count as f32;
+            let bar_rect = Rect::from_min_max(
+                pos2(plot_rect.left(), top),
+                pos2(plot_rect.left() + width, top + row_height - 2.0),
+            );
+            painter.rect_filled(bar_rect, Rounding::ZERO, Self::bar_color(ui, hovered));
+            painter.text(
+                pos2(plot_rect.left() - 4.0, top + row_height / 2.0),
+                Align2::RIGHT_CENTER,
+                Self::truncate(value, 18),
+                font.clone(),
+                text_color,
+            );
+            painter.text(
+                pos2(bar_rect.right() + 4.0, top + row_height / 2.0),
+                Align2::LEFT_CENTER,
+                count.to_string(),
+                font.clone(),
+                text_color,
+            );
+        }
+        let clicked = response.clicked();
+        if let Some((value, count)) = hovered_bar {
+            response.on_hover_text_at_pointer(format!(
+                "{}: {} rows\nClick to filter rows with this value",
+                value, count
+            ));
+            if clicked {
+                return Some(vec![value.clone()]);
+            }
+        }
+        None
+    }
+
+    fn scatter_ui(ui: &mut Ui, points: &[(f64, f64)]) {
+        let (_, plot_rect) = Self::allocate(ui, CHART_HEIGHT, AXIS_MARGIN);
+        let (min_x, max_x, min_y, max_y) = points.iter().fold(
+            (
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(min_x, max_x, min_y, max_y), (x, y)| {
+                (min_x.min(*x), max_x.max(*x), min_y.min(*y), max_y.max(*y))
+            },
+        );
+        let scale = |value: f64, min: f64, max: f64| {
+            if max == min {
+                0.5
+            } else {
+                ((value - min) / (max - min)) as f32
+            }
+        };
+        let painter = ui.painter();
+        let color = ui.visuals().selection.bg_fill;
+        for (x, y) in points {
+            let position = pos2(
+                plot_rect.left() + plot_rect.width() * scale(*x, min_x, max_x),
+                plot_rect.bottom() - plot_rect.height() * scale(*y, min_y, max_y),
+            );
+            painter.circle_filled(position, 2.0, color);
+        }
+        Self::y_axis(ui, plot_rect, max_y, min_y);
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(11.0);
+        painter.text(
+            plot_rect.left_bottom() + vec2(0.0, 4.0),
+            Align2::LEFT_TOP,
+            format_number(min_x),
+            font.clone(),
+            text_color,
+        );
+        painter.text(
+            plot_rect.right_bottom() + vec2(0.0, 4.0),
+            Align2::RIGHT_TOP,
+            format_number(max_x),
+            font,
+            text_color,
+        );
+    }
+
+    fn y_axis(ui: &Ui, plot_rect: Rect, max: f64, min: f64) {
+        let painter = ui.painter();
+        let text_color = ui.visuals().text_color();
+        let font = FontId::proportional(11.0);
+        let stroke = Stroke::new(1.0, ui.visuals().weak_text_color());
+        painter.line_segment([plot_rect.left_top(), plot_rect.left_bottom()], stroke);
+        painter.line_segment([plot_rect.left_bottom(), plot_rect.right_bottom()], stroke);
+        painter.text(
+            plot_rect.left_top() - vec2(4.0, 0.0),
+            Align2::RIGHT_TOP,
+            format_number(max),
+            font.clone(),
+            text_color,
+        );
+        painter.text(
+            plot_rect.left_bottom() - vec2(4.0, 0.0),
+            Align2::RIGHT_BOTTOM,
+            format_number(min),
+            font,
+            text_color,
+        );
+    }
+
+    fn bar_color(ui: &Ui, hovered: bool) -> Color32 {
+        if hovered {
+            ui.visuals().widgets.hovered.fg_stroke.color
+        } else {
+            ui.visuals().selection.bg_fill
+        }
+    }
+
+    fn truncate(value: &str, max_chars: usize) -> String {
+        if value.chars().count() > max_chars {
+            let truncated = value.chars().take(max_chars - 1).collect::<String>();
+            format!("{}…", truncated)
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chart::{Chart, ChartData, ChartKind};
+
+    fn values(values: &[&str]) -> Vec<(Option<String>, Option<String>)> {
+        values
+            .iter()
+            .map(|value| (Some(value.to_string()), None))
+            .collect()
+    }
+
+    #[test]
+    fn test_histogram() {
+        let bins = Chart::histogram(
+            [
+                "1", "2", "2", "3", "4", "5", "6", "7", "10", "abc", "NaN", "inf",
+            ]
+            .map(str::to_string)
+            .to_vec(),
+        );
+        // sqrt(9) bins between 1 and 10
+        assert_eq!(bins.len(), 3);
+        assert_eq!((bins[0].start, bins[0].end), (1.0, 4.0));
+        assert_eq!((bins[2].start, bins[2].end), (7.0, 10.0));
+        assert_eq!(
+            bins.iter().map(|bin| bin.count).collect::<Vec<usize>>(),
+            vec![4, 3, 2]
+        );
+        // Max goes in the last bin, values are distinct
+        assert_eq!(
+            bins[0].values.iter().collect::<Vec<&String>>(),
+            vec!["1", "2", "3"]
+        );
+        assert!(bins[2].values.contains("10"));
+
+        let bins = Chart::histogram(["4", "4.0"].map(str::to_string).to_vec());
+        assert_eq!(bins.len(), 1);
+        assert_eq!(bins[0].count, 2);
+        assert!(Chart::histogram(vec!["a".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_value_counts() {
+        let chart = Chart::new(
+            ChartKind::ValueCounts,
+            "/type".to_string(),
+            None,
+            values(&["b", "a", "b", "c", "a", "b"]),
+        );
+        let ChartData::ValueCounts { bars, other_values } = chart.data else {
+            panic!("value counts expected");
+        };
+        assert_eq!(
+            bars,
+            vec![
+                ("b".to_string(), 3),
+                ("a".to_string(), 2),
+                ("c".to_string(), 1)
+            ]
+        );
+        assert_eq!(other_values, 0);
+
+        let many_values = (0..60).map(|i| i.to_string()).collect::<Vec<String>>();
+        let chart = Chart::new(
+            ChartKind::ValueCounts,
+            "/id".to_string(),
+            None,
+            values(
+                &many_values
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<&str>>(),
+            ),
+        );
+        let ChartData::ValueCounts { bars, other_values } = chart.data else {
+            panic!("value counts expected");
+        };
+        assert_eq!(bars.len(), 50);
+        assert_eq!(other_values, 10);
+    }
+
+    #[test]
+    fn test_scatter() {
+        let chart = Chart::new(
+            ChartKind::Scatter,
+            "/x".to_string(),
+            Some("/y".to_string()),
+            vec![
+                (Some("1".to_string()), Some("2".to_string())),
+                (Some("a".to_string()), Some("2".to_string())),
+                (Some("3".to_string()), None),
+                (Some("inf".to_string()), Some("1".to_string())),
+                (Some("-4.5".to_string()), Some("0".to_string())),
+            ],
+        );
+        let ChartData::Scatter(points) = chart.data else {
+            panic!("scatter expected");
+        };
+        assert_eq!(points, vec![(1.0, 2.0), (-4.5, 0.0)]);
+    }
+}
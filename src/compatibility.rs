@@ -1,4 +1,7 @@
-use std::time::{Duration,Instant};
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 #[macro_export]
 macro_rules! log {
     () => {
@@ -3,8 +3,6 @@ pub trait CacheTrait {
 
     fn clear(&mut self);
 
-    fn len(&self) -> usize;
-
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
 pub struct FrameCache<Value, Computer> {
@@ -31,10 +29,6 @@ impl<Value: 'static, Computer: 'static> CacheTrait for FrameCache<Value, Compute
         self.generation = self.generation.wrapping_add(1);
     }
 
-    fn len(&self) -> usize {
-        self.cache.len()
-    }
-
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
         self
     }
@@ -103,11 +97,6 @@ impl CacheStorage {
             .unwrap()
     }
 
-    /// Total number of cached values
-    fn num_values(&self) -> usize {
-        self.caches.values().map(|cache| cache.len()).sum()
-    }
-
     /// Call once per frame to evict cache.
     pub fn update(&mut self) {
         for cache in self.caches.values_mut() {
@@ -1,30 +1,77 @@
 use eframe::emath::Align;
 use eframe::epaint;
-use egui::{FontSelection, Id, Response, RichText, Sense, Ui, WidgetText};
+use eframe::epaint::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontSelection, Id, Response, Sense, Ui, WidgetText};
+use std::ops::Range;
+
+pub const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 165, 0);
 
 pub struct CellText {
     text: WidgetText,
+    color: Option<Color32>,
+    background: Option<Color32>,
+    // Byte ranges of text to highlight, e.g. matches of a search
+    highlights: Vec<Range<usize>>,
+    // Wrap text at cell width up to this number of lines
+    wrap: Option<usize>,
 }
 
 impl CellText {
     pub fn new(text: impl Into<WidgetText>) -> CellText {
-        CellText { text: text.into() }
+        CellText {
+            text: text.into(),
+            color: None,
+            background: None,
+            highlights: vec![],
+            wrap: None,
+        }
+    }
+
+    /// Text color, default to visuals text color.
+    pub fn color(mut self, color: Option<Color32>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Fill the whole cell behind the text.
+    pub fn background(mut self, background: Option<Color32>) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn highlights(mut self, highlights: Vec<Range<usize>>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    /// Wrap text at cell width, lines after `max_rows` are elided.
+    pub fn wrap(mut self, max_rows: Option<usize>) -> Self {
+        self.wrap = max_rows;
+        self
     }
 
     pub fn ui(self, ui: &mut Ui, cell_id: usize) -> Response {
         let rect = ui.available_rect_before_wrap();
         let cell_zone = ui.interact(rect, Id::new(cell_id), Sense::click());
 
+        if let Some(background) = self.background {
+            ui.painter()
+                .rect_filled(rect, egui::Rounding::ZERO, background);
+        }
+
         let valign = ui.text_valign();
 
         let widget_text = self.text;
-        let mut layout_job =
-            widget_text.into_layout_job(ui.style(), FontSelection::Default, valign);
+        let layout_job = if self.highlights.is_empty() {
+            widget_text.into_layout_job(ui.style(), FontSelection::Default, valign)
+        } else {
+            Self::highlighted_layout_job(ui, widget_text.text(), &self.highlights, valign)
+        };
 
-        layout_job.break_on_newline = false;
-        layout_job.wrap.max_width = f32::INFINITY;
-        layout_job.halign = Align::LEFT;
-        layout_job.justify = false;
+        let layout_job = match self.wrap {
+            Some(max_rows) => Self::wrapped(layout_job, rect.width(), max_rows),
+            None => Self::single_line(layout_job),
+        };
         let galley = ui.fonts(|fonts| fonts.layout_job(layout_job));
         let galley_pos = match galley.job.halign {
             Align::LEFT => rect.left_top(),
@@ -35,9 +82,84 @@ impl CellText {
         ui.painter().add(epaint::TextShape::new(
             galley_pos,
             galley,
-            ui.style().visuals.text_color(),
+            self.color.unwrap_or(ui.style().visuals.text_color()),
         ));
 
         cell_zone
     }
+
+    /// Width taken by text drawn in a cell, without highlights which don't change it.
+    pub fn text_width(ui: &Ui, text: &str) -> f32 {
+        let layout_job = WidgetText::from(text).into_layout_job(
+            ui.style(),
+            FontSelection::Default,
+            ui.text_valign(),
+        );
+        ui.fonts(|fonts| fonts.layout_job(Self::single_line(layout_job)))
+            .size()
+            .x
+    }
+
+    /// Height taken by text wrapped in a cell of given width.
+    pub fn wrapped_height(ui: &Ui, text: &str, width: f32, max_rows: usize) -> f32 {
+        let layout_job = WidgetText::from(text).into_layout_job(
+            ui.style(),
+            FontSelection::Default,
+            ui.text_valign(),
+        );
+        ui.fonts(|fonts| fonts.layout_job(Self::wrapped(layout_job, width, max_rows)))
+            .size()
+            .y
+    }
+
+    fn wrapped(mut layout_job: LayoutJob, width: f32, max_rows: usize) -> LayoutJob {
+        layout_job.break_on_newline = true;
+        layout_job.wrap.max_width = width;
+        layout_job.wrap.max_rows = max_rows;
+        layout_job.halign = Align::LEFT;
+        layout_job.justify = false;
+        layout_job
+    }
+
+    fn single_line(mut layout_job: LayoutJob) -> LayoutJob {
+        layout_job.break_on_newline = false;
+        layout_job.wrap.max_width = f32::INFINITY;
+        layout_job.halign = Align::LEFT;
+        layout_job.justify = false;
+        layout_job
+    }
+
+    /// Text with highlighted ranges, ranges out of text (e.g. truncated) are ignored.
+    fn highlighted_layout_job(
+        ui: &Ui,
+        text: &str,
+        highlights: &[Range<usize>],
+        valign: Align,
+    ) -> LayoutJob {
+        let font_id = FontSelection::Default.resolve(ui.style());
+        let format = TextFormat {
+            font_id: font_id.clone(),
+            // Replaced by text color when painting
+            color: Color32::PLACEHOLDER,
+            valign,
+            ..Default::default()
+        };
+        let highlight_format = TextFormat {
+            color: Color32::BLACK,
+            background: HIGHLIGHT_COLOR,
+            ..format.clone()
+        };
+        let mut layout_job = LayoutJob::default();
+        let mut start = 0;
+        for range in highlights.iter() {
+            if range.start < start || range.end > text.len() || range.is_empty() {
+                continue;
+            }
+            layout_job.append(&text[start..range.start], 0.0, format.clone());
+            layout_job.append(&text[range.clone()], 0.0, highlight_format.clone());
+            start = range.end;
+        }
+        layout_job.append(&text[start..], 0.0, format);
+        layout_job
+    }
 }
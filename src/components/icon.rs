@@ -23,8 +23,8 @@ pub fn button(
     color: Option<Color32>,
 ) -> Response {
     let mut icon = icon::icon(name);
-    if color.is_some() {
-        icon = icon.color(color.unwrap());
+    if let Some(color) = color {
+        icon = icon.color(color);
     }
     let button = Button::new(icon);
     let mut response = ui.add(button);
@@ -71,74 +71,12 @@ impl ButtonWithIcon {
             selected: false,
         }
     }
-    #[inline]
-    pub fn wrap(mut self, wrap: bool) -> Self {
-        if wrap {
-            self.wrap = Some(TextWrapMode::Wrap);
-        } else {
-            self.wrap = Some(TextWrapMode::Extend);
-        }
-        self
-    }
-
-    #[inline]
-    pub fn fill(mut self, fill: impl Into<Color32>) -> Self {
-        self.fill = Some(fill.into());
-        self.frame = Some(true);
-        self
-    }
-
-    #[inline]
-    pub fn stroke(mut self, stroke: impl Into<Stroke>) -> Self {
-        self.stroke = Some(stroke.into());
-        self.frame = Some(true);
-        self
-    }
-
-    #[inline]
-    pub fn small(mut self) -> Self {
-        if let Some(text) = self.text {
-            self.text = Some(text.text_style(TextStyle::Body));
-        }
-        self.small = true;
-        self
-    }
-
-    #[inline]
-    pub fn frame(mut self, frame: bool) -> Self {
-        self.frame = Some(frame);
-        self
-    }
-
-    #[inline]
-    pub fn sense(mut self, sense: Sense) -> Self {
-        self.sense = sense;
-        self
-    }
-
-    #[inline]
-    pub fn min_size(mut self, min_size: Vec2) -> Self {
-        self.min_size = min_size;
-        self
-    }
-
-    #[inline]
-    pub fn rounding(mut self, rounding: impl Into<Rounding>) -> Self {
-        self.rounding = Some(rounding.into());
-        self
-    }
 
     #[inline]
     pub fn shortcut_text(mut self, shortcut_text: impl Into<WidgetText>) -> Self {
         self.shortcut_text = shortcut_text.into();
         self
     }
-
-    #[inline]
-    pub fn selected(mut self, selected: bool) -> Self {
-        self.selected = selected;
-        self
-    }
 }
 
 impl Widget for ButtonWithIcon {
@@ -9,7 +9,6 @@ use eframe::egui::*;
 
 pub struct PopupMenu {
     id_source: Id,
-    width: Option<f32>,
     height: Option<f32>,
 }
 
@@ -17,23 +16,10 @@ impl PopupMenu {
     pub fn new(id_source: impl std::hash::Hash) -> Self {
         Self {
             id_source: Id::new(id_source),
-            width: None,
             height: None,
         }
     }
 
-    #[inline]
-    pub fn width(mut self, width: f32) -> Self {
-        self.width = Some(width);
-        self
-    }
-
-    #[inline]
-    pub fn height(mut self, height: f32) -> Self {
-        self.height = Some(height);
-        self
-    }
-
     pub fn show_ui<R>(
         self,
         ui: &mut Ui,
@@ -49,11 +35,7 @@ impl PopupMenu {
         button: impl FnOnce(&mut Ui) -> Response,
         menu_contents: Box<dyn FnOnce(&mut Ui) -> R + 'c>,
     ) -> InnerResponse<Option<R>> {
-        let Self {
-            id_source,
-            width: _,
-            height,
-        } = self;
+        let Self { id_source, height } = self;
 
         let button_id = ui.make_persistent_id(id_source);
 
@@ -11,9 +11,6 @@ pub enum Size {
     /// Absolute size in points, with a given range of allowed sizes to resize within.
     Absolute { initial: f32, range: Rangef },
 
-    /// Relative size relative to all available space.
-    Relative { fraction: f32, range: Rangef },
-
     /// Multiple remainders each get the same space.
     Remainder { range: Rangef },
 }
@@ -46,9 +43,7 @@ impl Size {
     #[inline]
     pub fn at_least(mut self, minimum: f32) -> Self {
         match &mut self {
-            Self::Absolute { range, .. }
-            | Self::Relative { range, .. }
-            | Self::Remainder { range, .. } => {
+            Self::Absolute { range, .. } | Self::Remainder { range, .. } => {
                 range.min = minimum;
             }
         }
@@ -59,9 +54,7 @@ impl Size {
     #[inline]
     pub fn at_most(mut self, maximum: f32) -> Self {
         match &mut self {
-            Self::Absolute { range, .. }
-            | Self::Relative { range, .. }
-            | Self::Remainder { range, .. } => {
+            Self::Absolute { range, .. } | Self::Remainder { range, .. } => {
                 range.max = maximum;
             }
         }
@@ -90,10 +83,6 @@ impl Sizing {
             .iter()
             .map(|&size| match size {
                 Size::Absolute { initial, .. } => initial,
-                Size::Relative { fraction, range } => {
-                    assert!((0.0..=1.0).contains(&fraction));
-                    range.clamp(length * fraction)
-                }
                 Size::Remainder { .. } => {
                     remainders += 1;
                     0.0
@@ -126,7 +115,6 @@ impl Sizing {
             .iter()
             .map(|&size| match size {
                 Size::Absolute { initial, .. } => initial,
-                Size::Relative { fraction, range } => range.clamp(length * fraction),
                 Size::Remainder { range } => range.clamp(avg_remainder_length),
             })
             .collect()
@@ -170,9 +158,6 @@ pub(crate) enum CellSize {
 pub(crate) enum CellDirection {
     /// Cells go from left to right.
     Horizontal,
-
-    /// Cells go from top to bottom.
-    Vertical,
 }
 
 /// Flags used by [`StripLayout::add`].
@@ -184,6 +169,7 @@ pub(crate) struct StripLayoutFlags {
     pub(crate) selected: bool,
     pub(crate) selected_cell: bool,
     pub(crate) highlighted: bool,
+    pub(crate) background: Option<Color32>,
 }
 
 /// Positions cells in [`CellDirection`] and starts a new line on [`StripLayout::end_line`]
@@ -244,9 +230,6 @@ impl<'l> StripLayout<'l> {
             CellDirection::Horizontal => {
                 self.cursor.x = rect.right() + self.ui.spacing().item_spacing.x;
             }
-            CellDirection::Vertical => {
-                self.cursor.y = rect.bottom() + self.ui.spacing().item_spacing.y;
-            }
         }
     }
 
@@ -276,6 +259,12 @@ impl<'l> StripLayout<'l> {
             );
         }
 
+        if let Some(background) = flags.background {
+            self.ui
+                .painter()
+                .rect_filled(gapless_rect, egui::Rounding::ZERO, background);
+        }
+
         if flags.selected {
             self.ui.painter().rect_filled(
                 gapless_rect,
@@ -365,10 +354,6 @@ impl<'l> StripLayout<'l> {
                 self.cursor.y = self.max.y + self.ui.spacing().item_spacing.y;
                 self.cursor.x = self.rect.left();
             }
-            CellDirection::Vertical => {
-                self.cursor.x = self.max.x + self.ui.spacing().item_spacing.x;
-                self.cursor.y = self.rect.top();
-            }
         }
     }
 
@@ -450,6 +435,9 @@ pub struct Column {
     clip: bool,
 
     resizable: Option<bool>,
+
+    /// Width kept by the caller, used instead of the width stored in table state.
+    width: Option<f32>,
 }
 
 impl Column {
@@ -472,9 +460,19 @@ impl Column {
             width_range: Rangef::new(0.0, f32::INFINITY),
             resizable: None,
             clip: false,
+            width: None,
         }
     }
 
+    /// Use this width, e.g. restored from a saved layout, instead of the one stored in table state.
+    ///
+    /// Resizing is reported in [`TableResponse::resized_columns`], the caller keeps the new width.
+    #[inline]
+    pub fn width(mut self, width: Option<f32>) -> Self {
+        self.width = width;
+        self
+    }
+
     /// Can this column be resized by dragging the column separator?
     ///
     /// If you don't call this, the fallback value of
@@ -746,7 +744,6 @@ impl<'a> TableBuilder<'a> {
         let table_top = ui.cursor().top();
         let clip_rect = ui.clip_rect();
 
-        let mut x_offset = 0.0;
         let mut ui_builder = egui::UiBuilder::new();
         if is_sizing_pass {
             ui_builder = ui_builder.sizing_pass();
@@ -755,29 +752,13 @@ impl<'a> TableBuilder<'a> {
             let mut layout = StripLayout::new(ui, CellDirection::Horizontal, cell_layout, sense);
             let mut response: Option<Response> = None;
 
-            let end_x = clip_rect.right();
             let start_x = clip_rect.left();
-            let scroll_offset_x = start_x - layout.rect.left();
-            let mut visible_index = Vec::with_capacity(state.column_widths.len());
-            let mut first_col_visible_offset = -layout.ui.spacing().item_spacing[0];
-            let mut first_visible_seen = false;
-            let mut last_visible_seen = false;
-            let mut remainder_with = 0.0;
-            for (index, width) in state.column_widths.iter().enumerate() {
-                if x_offset + width >= scroll_offset_x && x_offset <= end_x + scroll_offset_x {
-                    first_visible_seen = true;
-                    visible_index.push(index);
-                } else if first_visible_seen && !last_visible_seen {
-                    last_visible_seen = true;
-                }
-                if !first_visible_seen {
-                    first_col_visible_offset += width + layout.ui.spacing().item_spacing[0];
-                }
-                x_offset += width + layout.ui.spacing().item_spacing[0];
-                if last_visible_seen {
-                    remainder_with += width + layout.ui.spacing().item_spacing[0];
-                }
-            }
+            let (visible_index, first_col_visible_offset, remainder_with) = visible_columns(
+                &state.column_widths,
+                clip_rect,
+                start_x - layout.rect.left(),
+                layout.ui.spacing().item_spacing[0],
+            );
             let mut hover_data = Default::default();
             add_header_row(TableRow {
                 layout: &mut layout,
@@ -787,17 +768,16 @@ impl<'a> TableBuilder<'a> {
                 max_used_widths: &mut max_used_widths,
                 row_index: 0,
                 col_index: 0,
-                start_x,
                 first_col_visible_offset,
                 remainder_with,
                 height,
                 striped: false,
                 hovered: false,
                 selected: false,
+                background: None,
                 response: &mut response,
                 hover_data: &mut hover_data,
                 highlighted: false,
-                highlighted_cell: None,
                 selected_cell: None,
                 hovered_cell_index_id: None,
                 is_pinned_column_table,
@@ -820,8 +800,42 @@ impl<'a> TableBuilder<'a> {
             scroll_options,
             sense,
             is_pinned_column_table,
+            footer: None,
+        }
+    }
+}
+
+/// Columns intersecting the clip rect, followed by the width of columns before the first visible
+/// one and the width of columns after the last visible one.
+fn visible_columns(
+    column_widths: &[f32],
+    clip_rect: Rect,
+    scroll_offset_x: f32,
+    spacing_x: f32,
+) -> (Vec<usize>, f32, f32) {
+    let end_x = clip_rect.right();
+    let mut x_offset = 0.0;
+    let mut visible_index = Vec::with_capacity(column_widths.len());
+    let mut first_col_visible_offset = -spacing_x;
+    let mut first_visible_seen = false;
+    let mut last_visible_seen = false;
+    let mut remainder_with = 0.0;
+    for (index, width) in column_widths.iter().enumerate() {
+        if x_offset + width >= scroll_offset_x && x_offset <= end_x + scroll_offset_x {
+            first_visible_seen = true;
+            visible_index.push(index);
+        } else if first_visible_seen && !last_visible_seen {
+            last_visible_seen = true;
+        }
+        if !first_visible_seen {
+            first_col_visible_offset += width + spacing_x;
+        }
+        x_offset += width + spacing_x;
+        if last_visible_seen {
+            remainder_with += width + spacing_x;
         }
     }
+    (visible_index, first_col_visible_offset, remainder_with)
 }
 
 // ----------------------------------------------------------------------------
@@ -893,10 +907,18 @@ pub struct Table<'a> {
 
     sense: egui::Sense,
     is_pinned_column_table: bool,
+    footer: Option<(f32, FooterRow<'a>)>,
 }
 
+type FooterRow<'a> = Box<dyn FnOnce(TableRow<'_, '_>) + 'a>;
+
 pub struct TableResponse {
     pub scroll_area_output: ScrollAreaOutput<()>,
+    /// Columns resized by dragging their border, with their new width.
+    pub resized_columns: Vec<(usize, f32)>,
+    /// Column whose border was double-clicked, to fit it to its content.
+    pub fit_column: Option<usize>,
+    pub column_widths: Vec<f32>,
     pub columns_offset: Vec<f32>,
     pub first_visible_index: usize,
     pub last_visible_index: usize,
@@ -904,7 +926,17 @@ pub struct TableResponse {
     pub last_visible_offset: f32,
 }
 
-impl Table<'_> {
+impl<'a> Table<'a> {
+    /// Add a row which always stays visible under the body
+    pub fn footer(
+        mut self,
+        height: f32,
+        add_footer_row: impl FnOnce(TableRow<'_, '_>) + 'a,
+    ) -> Self {
+        self.footer = Some((height, Box::new(add_footer_row)));
+        self
+    }
+
     /// Create table body after adding a header row
     pub fn body<F>(
         self,
@@ -931,6 +963,7 @@ impl Table<'_> {
             scroll_options,
             sense,
             is_pinned_column_table,
+            footer,
         } = self;
 
         let TableScrollOptions {
@@ -941,7 +974,7 @@ impl Table<'_> {
             scroll_offset_y,
             min_scrolled_height,
             max_scroll_height,
-            auto_shrink: _,
+            auto_shrink,
             scroll_bar_visibility,
         } = scroll_options;
 
@@ -949,11 +982,16 @@ impl Table<'_> {
 
         let mut scroll_area = ScrollArea::new([false, vscroll])
             .id_salt(self.state_id.with("__scroll_area"))
-            .auto_shrink(true)
+            .auto_shrink(auto_shrink)
             .drag_to_scroll(drag_to_scroll)
             .stick_to_bottom(stick_to_bottom)
             .min_scrolled_height(min_scrolled_height)
-            .max_height(max_scroll_height)
+            .max_height(
+                max_scroll_height
+                    - footer
+                        .as_ref()
+                        .map_or(0.0, |(height, _)| height + ui.spacing().item_spacing.y),
+            )
             .scroll_bar_visibility(scroll_bar_visibility)
             .animated(false);
 
@@ -1028,12 +1066,9 @@ impl Table<'_> {
                     striped,
                     row_index: 0,
                     start_y: clip_rect.top(),
-                    start_x: clip_rect.left(),
                     end_y: clip_rect.bottom(),
-                    end_x: clip_rect.right(),
                     scroll_to_row: scroll_to_row.map(|(r, _)| r),
                     scroll_to_y_range: &mut scroll_to_y_range,
-                    scroll_offset_x,
                     first_col_visible_width: first_col_visible_offset,
                     hovered_row_index,
                     hovered_cell_index,
@@ -1057,14 +1092,63 @@ impl Table<'_> {
             }
         });
 
+        if let Some((footer_height, add_footer_row)) = footer {
+            let clip_rect = ui.clip_rect();
+            let mut ui_builder = egui::UiBuilder::new();
+            if is_sizing_pass {
+                ui_builder = ui_builder.sizing_pass();
+            }
+            ui.scope_builder(ui_builder, |ui| {
+                let mut layout =
+                    StripLayout::new(ui, CellDirection::Horizontal, cell_layout, sense);
+                let start_x = clip_rect.left();
+                let (visible_index, first_col_visible_offset, remainder_with) = visible_columns(
+                    &state.column_widths,
+                    clip_rect,
+                    start_x - layout.rect.left(),
+                    layout.ui.spacing().item_spacing[0],
+                );
+                let mut response: Option<Response> = None;
+                let mut hover_data = Default::default();
+                add_footer_row(TableRow {
+                    layout: &mut layout,
+                    columns: &columns,
+                    widths: &state.column_widths,
+                    visible_columns: visible_index.as_slice(),
+                    max_used_widths: &mut max_used_widths,
+                    row_index: 0,
+                    col_index: 0,
+                    first_col_visible_offset,
+                    remainder_with,
+                    height: footer_height,
+                    striped: false,
+                    hovered: false,
+                    selected: false,
+                    background: None,
+                    response: &mut response,
+                    hover_data: &mut hover_data,
+                    highlighted: false,
+                    selected_cell: None,
+                    hovered_cell_index_id: None,
+                    is_pinned_column_table,
+                });
+                layout.allocate_rect();
+            });
+        }
+
         let bottom = ui.min_rect().bottom();
 
         let spacing_x = ui.spacing().item_spacing.x;
         let mut x = cursor_position.x - spacing_x * 0.5;
+        let mut resized_columns = vec![];
+        let mut fit_column = None;
         for (i, column_width) in state.column_widths.iter_mut().enumerate() {
             let column = &columns[i];
             let column_is_resizable = column.resizable.unwrap_or(resizable);
             let width_range = column.width_range;
+            if let Some(width) = column.width {
+                *column_width = width;
+            }
 
             if !column.clip {
                 // Unless we clip we don't want to shrink below the
@@ -1104,6 +1188,7 @@ impl Table<'_> {
                 if resize_response.double_clicked() {
                     // Resize to the minimum of what is needed.
                     *column_width = width_range.clamp(max_used_widths[i]);
+                    fit_column = Some(i);
                 } else if resize_response.dragged() {
                     if let Some(pointer) = ui.ctx().pointer_latest_pos() {
                         let mut new_width = *column_width + pointer.x - x;
@@ -1124,6 +1209,7 @@ impl Table<'_> {
                         (p0.x, p1.x) = (x, x);
 
                         *column_width = new_width;
+                        resized_columns.push((i, new_width));
                     }
                 }
 
@@ -1153,9 +1239,13 @@ impl Table<'_> {
 
             available_width -= *column_width + spacing_x;
         }
+        let column_widths = state.column_widths.clone();
         state.store(ui, state_id);
         TableResponse {
             scroll_area_output,
+            resized_columns,
+            fit_column,
+            column_widths,
             columns_offset,
             first_visible_index: first_col_visible_index,
             last_visible_index: last_col_visible_index,
@@ -1180,13 +1270,6 @@ pub struct HoverData {
 }
 
 impl HoverData {
-    pub fn from_cell(hovered_cell: Option<CellLocation>) -> Self {
-        Self {
-            hovered_row: None,
-            hovered_cell,
-            response_rows: None,
-        }
-    }
     pub fn union(&mut self, other: HoverData) -> Self {
         let mut new_data = mem::take(self);
         if new_data.hovered_row.is_none() && other.hovered_row.is_some() {
@@ -1217,9 +1300,7 @@ pub struct TableBody<'a> {
     striped: bool,
     row_index: usize,
     start_y: f32,
-    start_x: f32,
     end_y: f32,
-    end_x: f32,
 
     /// Look for this row to scroll to.
     scroll_to_row: Option<usize>,
@@ -1234,7 +1315,6 @@ pub struct TableBody<'a> {
 
     /// Used to store the hovered row index between frames.
     hovered_row_index_id: egui::Id,
-    pub scroll_offset_x: f32,
     pub first_col_visible_width: f32,
     pub search_matching_row_index: Option<usize>,
     is_pinned_column_table: bool,
@@ -1245,41 +1325,69 @@ impl TableBody<'_> {
         self.start_y - self.layout.rect.top()
     }
 
-    pub fn scroll_offset_x(&self) -> f32 {
-        self.start_x - self.layout.rect.left()
+    pub fn rows(
+        self,
+        row_height_sans_spacing: f32,
+        total_rows: usize,
+        add_row_content: impl FnMut(TableRow<'_, '_>),
+    ) -> HoverData {
+        let row_height_with_spacing =
+            row_height_sans_spacing + self.layout.ui.spacing().item_spacing.y;
+        self.add_rows(
+            total_rows,
+            |row_index| row_index as f32 * row_height_with_spacing,
+            |y| (y / row_height_with_spacing).floor() as usize,
+            add_row_content,
+        )
     }
 
-    pub fn rows(
+    /// Rows of different heights. `row_offsets` holds the top of each row, spacing included,
+    /// followed by the bottom of the last row, so visible rows are found by binary search.
+    pub fn heterogeneous_rows(
+        self,
+        row_offsets: &[f32],
+        add_row_content: impl FnMut(TableRow<'_, '_>),
+    ) -> HoverData {
+        self.add_rows(
+            row_offsets.len().saturating_sub(1),
+            |row_index| row_offsets[row_index],
+            |y| {
+                row_offsets
+                    .partition_point(|offset| *offset <= y)
+                    .saturating_sub(1)
+            },
+            add_row_content,
+        )
+    }
+
+    /// Add visible rows only, `row_top` gives the offset of a row and `row_at` the row at an offset.
+    fn add_rows(
         mut self,
-        row_height_sans_spacing: f32,
         total_rows: usize,
+        row_top: impl Fn(usize) -> f32,
+        row_at: impl Fn(f32) -> usize,
         mut add_row_content: impl FnMut(TableRow<'_, '_>),
     ) -> HoverData {
         let spacing = self.layout.ui.spacing().item_spacing;
-        let row_height_with_spacing = row_height_sans_spacing + spacing.y;
 
         if let Some(scroll_to_row) = self.scroll_to_row {
-            let scroll_to_row = scroll_to_row.at_most(total_rows.saturating_sub(1)) as f32;
+            let scroll_to_row = scroll_to_row.at_most(total_rows.saturating_sub(1));
             *self.scroll_to_y_range = Some(Rangef::new(
-                self.layout.cursor.y + scroll_to_row * row_height_with_spacing,
-                self.layout.cursor.y + (scroll_to_row + 1.0) * row_height_with_spacing,
+                self.layout.cursor.y + row_top(scroll_to_row),
+                self.layout.cursor.y + row_top(scroll_to_row + 1),
             ));
         }
 
-        let scroll_offset_y = self
-            .scroll_offset_y()
-            .min(total_rows as f32 * row_height_with_spacing);
+        let scroll_offset_y = self.scroll_offset_y().min(row_top(total_rows));
         let max_height = self.end_y - self.start_y;
         let mut min_row = 0;
 
         if scroll_offset_y > 0.0 {
-            min_row = (scroll_offset_y / row_height_with_spacing).floor() as usize;
-            self.add_buffer(min_row as f32 * row_height_with_spacing);
+            min_row = row_at(scroll_offset_y).min(total_rows);
+            self.add_buffer(row_top(min_row));
         }
 
-        let max_row =
-            ((scroll_offset_y + max_height) / row_height_with_spacing).ceil() as usize + 1;
-        let max_row = max_row.min(total_rows);
+        let max_row = (row_at(scroll_offset_y + max_height) + 2).min(total_rows);
 
         let mut response_union: Option<Response> = None;
         let mut hover_data = Default::default();
@@ -1293,15 +1401,14 @@ impl TableBody<'_> {
                 max_used_widths: self.max_used_widths,
                 row_index,
                 col_index: 0,
-                start_x: self.start_x,
                 first_col_visible_offset: self.first_col_visible_width,
-                height: row_height_sans_spacing,
-                striped: self.striped && (row_index + self.row_index) % 2 == 0,
+                height: row_top(row_index + 1) - row_top(row_index) - spacing.y,
+                striped: self.striped && (row_index + self.row_index) & 1 == 0,
                 hovered: self.hovered_row_index == Some(row_index),
                 highlighted: self.search_matching_row_index == Some(row_index),
-                highlighted_cell: None,
                 selected_cell: self.hovered_cell_index,
                 selected: false,
+                background: None,
                 response: &mut response,
                 hover_data: &mut hover_data,
                 remainder_with: 0.0,
@@ -1317,7 +1424,7 @@ impl TableBody<'_> {
         }
 
         if total_rows - max_row > 0 {
-            let skip_height = (total_rows - max_row) as f32 * row_height_with_spacing;
+            let skip_height = row_top(total_rows) - row_top(max_row);
             self.add_buffer(skip_height - spacing.y);
         }
 
@@ -1362,7 +1469,6 @@ pub struct TableRow<'a, 'b> {
     /// grows during building with the maximum widths
     max_used_widths: &'b mut [f32],
 
-    start_x: f32,
     first_col_visible_offset: f32,
 
     row_index: usize,
@@ -1372,12 +1478,12 @@ pub struct TableRow<'a, 'b> {
     striped: bool,
     hovered: bool,
     selected: bool,
+    background: Option<Color32>,
 
     response: &'b mut Option<Response>,
     hover_data: &'b mut HoverData,
     pub remainder_with: f32,
     pub highlighted: bool,
-    pub highlighted_cell: Option<usize>,
     pub selected_cell: Option<CellLocation>,
     hovered_cell_index_id: Option<egui::Id>,
     is_pinned_column_table: bool,
@@ -1423,8 +1529,7 @@ impl TableRow<'_, '_> {
 
         let width = CellSize::Absolute(width);
         let height = CellSize::Absolute(self.height);
-        let selected_cell = if self.selected_cell.is_some() {
-            let cell = self.selected_cell.unwrap();
+        let selected_cell = if let Some(cell) = self.selected_cell {
             self.row_index == cell.row_index && cell.column_index == col_index
         } else {
             false
@@ -1437,6 +1542,7 @@ impl TableRow<'_, '_> {
             selected: self.selected,
             selected_cell,
             highlighted: self.highlighted,
+            background: self.background,
         };
 
         let (used_rect, response) = self.layout.add(
@@ -1500,8 +1606,7 @@ impl TableRow<'_, '_> {
             let width = CellSize::Absolute(width);
             let height = CellSize::Absolute(self.height);
 
-            let selected_cell = if self.selected_cell.is_some() {
-                let cell = self.selected_cell.unwrap();
+            let selected_cell = if let Some(cell) = self.selected_cell {
                 cell.row_index == self.row_index
                     && cell.column_index == *col_index
                     && self.is_pinned_column_table == cell.is_pinned_column_table
@@ -1515,6 +1620,7 @@ impl TableRow<'_, '_> {
                 selected: self.selected,
                 selected_cell,
                 highlighted: self.highlighted,
+                background: self.background,
             };
 
             let (used_rect, response) = self.layout.add(
@@ -1570,10 +1676,16 @@ impl TableRow<'_, '_> {
         self.row_index
     }
 
-    /// Returns the index of the column. Incremented after a column is added.
+    /// Paint row background as selected.
+    #[inline]
+    pub fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    /// Paint row background with given color, below selection and hover.
     #[inline]
-    pub fn col_index(&self) -> usize {
-        self.col_index
+    pub fn set_background(&mut self, background: Option<Color32>) {
+        self.background = background;
     }
 }
 
@@ -0,0 +1,188 @@
+//! Transparent (de)compression of opened files: gzip, zstd and json member of a zip archive.
+//!
+//! Format is detected from the first bytes of the file, not from its extension, and files are
+//! saved back in the format they were opened with.
+
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use json_flat_parser::JsonArrayEntries;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::parser::{save_to_buffer, write_atomically, BackupOptions, SaveOptions};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const ZIP_MAGIC: &[u8] = &[0x50, 0x4b, 0x03, 0x04];
+const ZIP_EMPTY_MAGIC: &[u8] = &[0x50, 0x4b, 0x05, 0x06];
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Zip,
+}
+
+impl Compression {
+    pub fn detect(path: &Path) -> std::io::Result<Self> {
+        let mut header = [0u8; 4];
+        let mut file = fs::File::open(path)?;
+        let mut read = 0;
+        while read < header.len() {
+            let n = file.read(&mut header[read..])?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        Ok(Self::from_magic(&header[..read]))
+    }
+
+    fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Self::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Self::Zstd
+        } else if header.starts_with(ZIP_MAGIC) || header.starts_with(ZIP_EMPTY_MAGIC) {
+            Self::Zip
+        } else {
+            Self::None
+        }
+    }
+
+    /// Used when saving under a new name, where there is no content to detect format from.
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst") | Some("zstd") => Self::Zstd,
+            Some("zip") => Self::Zip,
+            _ => Self::None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Zip => "zip",
+        }
+    }
+}
+
+/// Decompressed content of `path`, `zip_member` is required for zip archives.
+pub fn read_file(
+    path: &Path,
+    compression: Compression,
+    zip_member: Option<&str>,
+) -> std::io::Result<Vec<u8>> {
+    let file = fs::File::open(path)?;
+    let capacity = file.metadata().map_or(0, |m| m.len() as usize);
+    let mut content = Vec::with_capacity(capacity);
+    match compression {
+        Compression::None => {
+            BufReader::new(file).read_to_end(&mut content)?;
+        }
+        Compression::Gzip => {
+            MultiGzDecoder::new(BufReader::new(file)).read_to_end(&mut content)?;
+        }
+        Compression::Zstd => {
+            zstd::Decoder::new(file)?.read_to_end(&mut content)?;
+        }
+        Compression::Zip => {
+            let member = zip_member.ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "No archive member selected",
+                )
+            })?;
+            let mut archive = ZipArchive::new(BufReader::new(file))?;
+            archive.by_name(member)?.read_to_end(&mut content)?;
+        }
+    }
+    Ok(content)
+}
+
+/// Name of files in the archive, json files first.
+pub fn zip_members(path: &Path) -> std::io::Result<Vec<String>> {
+    let archive = ZipArchive::new(BufReader::new(fs::File::open(path)?))?;
+    let mut members = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+    members.sort_by_key(|name| (!is_json_member(name), name.clone()));
+    Ok(members)
+}
+
+pub fn is_json_member(name: &str) -> bool {
+    name.to_lowercase().ends_with(".json")
+}
+
+pub fn save_to_file(
+    parent_pointer: &str,
+    array: &[JsonArrayEntries<String>],
+    file_path: &Path,
+    compression: Compression,
+    zip_member: Option<&str>,
+    save_options: &SaveOptions,
+    backup_options: &BackupOptions,
+) -> std::io::Result<()> {
+    let backup_path = backup_options.backup_path(file_path);
+    match compression {
+        Compression::None => crate::parser::save_to_file(
+            parent_pointer,
+            array,
+            file_path,
+            save_options,
+            backup_options,
+        ),
+        Compression::Gzip => write_atomically(file_path, backup_path.as_deref(), |file| {
+            let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+            save_to_buffer(parent_pointer, array, &mut encoder, save_options)?;
+            encoder.finish()?;
+            Ok(())
+        }),
+        Compression::Zstd => write_atomically(file_path, backup_path.as_deref(), |file| {
+            let mut encoder = zstd::Encoder::new(file, 0)?;
+            save_to_buffer(parent_pointer, array, &mut encoder, save_options)?;
+            encoder.finish()?;
+            Ok(())
+        }),
+        Compression::Zip => {
+            let default_member = file_path
+                .file_stem()
+                .map(|stem| format!("{}.json", stem.to_string_lossy()))
+                .unwrap_or("data.json".to_string());
+            let member = zip_member.unwrap_or(default_member.as_str());
+            // Other members of the archive are copied as is into the new archive, it is moved in the
+            // closure so it is closed before the archive gets replaced.
+            let previous_archive = fs::File::open(file_path)
+                .ok()
+                .and_then(|file| ZipArchive::new(BufReader::new(file)).ok());
+            write_atomically(file_path, backup_path.as_deref(), move |file| {
+                let mut writer = ZipWriter::new(file);
+                if let Some(mut previous_archive) = previous_archive {
+                    for i in 0..previous_archive.len() {
+                        let entry = previous_archive.by_index_raw(i)?;
+                        if entry.name() != member {
+                            writer.raw_copy_file(entry)?;
+                        }
+                    }
+                }
+                let options =
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+                writer.start_file(member, options)?;
+                save_to_buffer(parent_pointer, array, &mut writer, save_options)?;
+                writer.finish()?;
+                Ok(())
+            })
+        }
+    }
+}
@@ -0,0 +1,605 @@
+//! Virtual columns whose value is an expression over other columns of the same row.
+//!
+//! Columns are referenced by their json pointer relative to the row (`/maxLevel`, `/stats/hp`), which
+//! is also how columns are named in the table. Expressions support arithmetic (`+ - * / %`, `+` on
+//! a string concatenates), comparisons, `&&`, `||`, `!` and functions: `concat`, `len`, `upper`,
+//! `lower`, `trim`, `round`, `floor`, `ceil`, `abs`, `min`, `max`, `if`, `coalesce`, `number`, `string`.
+//! Operators have to be surrounded by spaces when they could be read as part of a key, e.g. `/a - 1`.
+
+use std::cmp::Ordering;
+
+use json_flat_parser::{JsonArrayEntries, ValueType};
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde_json::{Number, Value};
+
+use crate::cursor::Cursor;
+use crate::query::row_value;
+
+#[derive(Debug, Clone, Copy)]
+enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug)]
+enum Expression {
+    Literal(Value),
+    Pointer(String),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
+    Function(Function, Vec<Expression>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Concat,
+    Len,
+    Upper,
+    Lower,
+    Trim,
+    Round,
+    Floor,
+    Ceil,
+    Abs,
+    Min,
+    Max,
+    If,
+    Coalesce,
+    Number,
+    String,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "concat" => Self::Concat,
+            "len" => Self::Len,
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "trim" => Self::Trim,
+            "round" => Self::Round,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "abs" => Self::Abs,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "if" => Self::If,
+            "coalesce" => Self::Coalesce,
+            "number" => Self::Number,
+            "string" => Self::String,
+            _ => return None,
+        })
+    }
+
+    // Accepted number of arguments
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Self::Concat | Self::Min | Self::Max | Self::Coalesce => (1, usize::MAX),
+            Self::Round => (1, 2),
+            Self::If => (3, 3),
+            _ => (1, 1),
+        }
+    }
+}
+
+pub struct ComputedColumn {
+    // Column name, a json pointer like real columns
+    pub name: String,
+    pub expression_source: String,
+    expression: Expression,
+    pub value_type: ValueType,
+    // Computed value by row index, None when expression evaluates to null
+    pub values: Vec<Option<String>>,
+}
+
+impl ComputedColumn {
+    pub fn new(name: &str, expression_source: &str) -> Result<Self, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Column name is required".to_string());
+        }
+        let name = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("/{}", name)
+        };
+        Ok(Self {
+            name,
+            expression_source: expression_source.trim().to_string(),
+            expression: ExpressionParser::parse(expression_source)?,
+            value_type: ValueType::Null,
+            values: vec![],
+        })
+    }
+
+    pub fn compute(&mut self, rows: &[JsonArrayEntries<String>]) {
+        let values = rows
+            .par_iter()
+            .map(|row| self.json_value(row))
+            .collect::<Vec<Value>>();
+        self.value_type = values
+            .iter()
+            .find(|value| !value.is_null())
+            .map_or(ValueType::Null, value_type);
+        self.values = values.into_par_iter().map(to_cell_value).collect();
+    }
+
+    pub fn compute_row(&self, row: &JsonArrayEntries<String>) -> Option<String> {
+        let value = self.expression.eval(&row_value(row)?);
+        to_cell_value(value)
+    }
+
+    #[inline]
+    pub fn value(&self, row_index: usize) -> Option<&String> {
+        self.values.get(row_index).and_then(|value| value.as_ref())
+    }
+
+    /// Value of the row as a json value to write into the row when materializing the column.
+    pub fn json_value(&self, row: &JsonArrayEntries<String>) -> Value {
+        row_value(row).map_or(Value::Null, |row| self.expression.eval(&row))
+    }
+}
+
+pub fn value_type(value: &Value) -> ValueType {
+    match value {
+        Value::Null => ValueType::Null,
+        Value::Bool(_) => ValueType::Bool,
+        Value::Number(_) => ValueType::Number,
+        Value::String(_) => ValueType::String,
+        Value::Array(elements) => ValueType::Array(elements.len()),
+        Value::Object(map) => ValueType::Object(true, map.len()),
+    }
+}
+
+pub fn to_cell_value(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value),
+        value => Some(value.to_string()),
+    }
+}
+
+impl Expression {
+    fn eval(&self, row: &Value) -> Value {
+        match self {
+            Expression::Literal(value) => value.clone(),
+            Expression::Pointer(pointer) => row.pointer(pointer).cloned().unwrap_or(Value::Null),
+            Expression::Negate(expression) => match expression.eval(row).as_f64() {
+                Some(number) => number_value(-number),
+                None => Value::Null,
+            },
+            Expression::Not(expression) => Value::Bool(!is_truthy(&expression.eval(row))),
+            Expression::Binary(left, BinaryOperator::And, right) => {
+                Value::Bool(is_truthy(&left.eval(row)) && is_truthy(&right.eval(row)))
+            }
+            Expression::Binary(left, BinaryOperator::Or, right) => {
+                Value::Bool(is_truthy(&left.eval(row)) || is_truthy(&right.eval(row)))
+            }
+            Expression::Binary(left, operator, right) => {
+                binary(left.eval(row), *operator, right.eval(row))
+            }
+            Expression::Function(function, arguments) => call(*function, arguments, row),
+        }
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+// Keep integers as integers so `/maxLevel * 2` displays "10" and not "10.0"
+pub fn number_value(number: f64) -> Value {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Value::Number(Number::from(number as i64))
+    } else {
+        Number::from_f64(number).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn as_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn binary(left: Value, operator: BinaryOperator, right: Value) -> Value {
+    if let BinaryOperator::Add = operator {
+        if left.is_string() || right.is_string() {
+            if left.is_null() || right.is_null() {
+                return Value::Null;
+            }
+            return Value::String(as_string(&left) + as_string(&right).as_str());
+        }
+    }
+    let ordering = || match (&left, &right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64().partial_cmp(&right.as_f64()),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (left, right) if left == right => Some(Ordering::Equal),
+        _ => None,
+    };
+    match operator {
+        BinaryOperator::Eq => return Value::Bool(ordering() == Some(Ordering::Equal)),
+        BinaryOperator::Ne => return Value::Bool(ordering() != Some(Ordering::Equal)),
+        BinaryOperator::Lt => return Value::Bool(ordering() == Some(Ordering::Less)),
+        BinaryOperator::Le => {
+            return Value::Bool(matches!(ordering(), Some(Ordering::Less | Ordering::Equal)))
+        }
+        BinaryOperator::Gt => return Value::Bool(ordering() == Some(Ordering::Greater)),
+        BinaryOperator::Ge => {
+            return Value::Bool(matches!(
+                ordering(),
+                Some(Ordering::Greater | Ordering::Equal)
+            ))
+        }
+        _ => {}
+    }
+    let (Some(left), Some(right)) = (left.as_f64(), right.as_f64()) else {
+        return Value::Null;
+    };
+    match operator {
+        BinaryOperator::Add => number_value(left + right),
+        BinaryOperator::Subtract => number_value(left - right),
+        BinaryOperator::Multiply => number_value(left * right),
+        BinaryOperator::Divide if right != 0.0 => number_value(left / right),
+        BinaryOperator::Remainder if right != 0.0 => number_value(left % right),
+        _ => Value::Null,
+    }
+}
+
+fn call(function: Function, arguments: &[Expression], row: &Value) -> Value {
+    // "if" only evaluates the selected branch
+    if let Function::If = function {
+        return if is_truthy(&arguments[0].eval(row)) {
+            arguments[1].eval(row)
+        } else {
+            arguments[2].eval(row)
+        };
+    }
+    let mut values = arguments.iter().map(|argument| argument.eval(row));
+    match function {
+        Function::Concat => Value::String(values.map(|value| as_string(&value)).collect()),
+        Function::Coalesce => values.find(|value| !value.is_null()).unwrap_or(Value::Null),
+        Function::Min | Function::Max => {
+            let numbers = values.filter_map(|value| value.as_f64());
+            let result = if let Function::Min = function {
+                numbers.reduce(f64::min)
+            } else {
+                numbers.reduce(f64::max)
+            };
+            result.map_or(Value::Null, number_value)
+        }
+        _ => {
+            let value = values.next().unwrap();
+            match function {
+                Function::Len => match &value {
+                    Value::String(value) => Value::from(value.chars().count()),
+                    Value::Array(elements) => Value::from(elements.len()),
+                    Value::Object(map) => Value::from(map.len()),
+                    _ => Value::Null,
+                },
+                Function::Upper => value
+                    .as_str()
+                    .map_or(Value::Null, |value| Value::String(value.to_uppercase())),
+                Function::Lower => value
+                    .as_str()
+                    .map_or(Value::Null, |value| Value::String(value.to_lowercase())),
+                Function::Trim => value
+                    .as_str()
+                    .map_or(Value::Null, |value| Value::String(value.trim().to_string())),
+                Function::Round => {
+                    let digits = values
+                        .next()
+                        .and_then(|digits| digits.as_i64())
+                        .unwrap_or(0);
+                    let factor = 10f64.powi(digits as i32);
+                    value.as_f64().map_or(Value::Null, |number| {
+                        number_value((number * factor).round() / factor)
+                    })
+                }
+                Function::Floor => value
+                    .as_f64()
+                    .map_or(Value::Null, |number| number_value(number.floor())),
+                Function::Ceil => value
+                    .as_f64()
+                    .map_or(Value::Null, |number| number_value(number.ceil())),
+                Function::Abs => value
+                    .as_f64()
+                    .map_or(Value::Null, |number| number_value(number.abs())),
+                Function::Number => match &value {
+                    Value::Number(_) => value,
+                    Value::String(value) => value
+                        .trim()
+                        .parse::<f64>()
+                        .map_or(Value::Null, number_value),
+                    Value::Bool(value) => Value::from(*value as u8),
+                    _ => Value::Null,
+                },
+                Function::String => match value {
+                    Value::Null => Value::Null,
+                    value => Value::String(as_string(&value)),
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+struct ExpressionParser<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl ExpressionParser<'_> {
+    fn parse(input: &str) -> Result<Expression, String> {
+        let mut parser = ExpressionParser {
+            cursor: Cursor::new(input),
+        };
+        let expression = parser.parse_or()?;
+        if !parser.cursor.is_at_end() {
+            return Err(parser.cursor.error("Unexpected content"));
+        }
+        Ok(expression)
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_and()?;
+        while self.cursor.eat("||") {
+            let right = self.parse_and()?;
+            left = Expression::Binary(Box::new(left), BinaryOperator::Or, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_comparison()?;
+        while self.cursor.eat("&&") {
+            let right = self.parse_comparison()?;
+            left = Expression::Binary(Box::new(left), BinaryOperator::And, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, String> {
+        let left = self.parse_additive()?;
+        let operator = if self.cursor.eat("==") {
+            BinaryOperator::Eq
+        } else if self.cursor.eat("!=") {
+            BinaryOperator::Ne
+        } else if self.cursor.eat("<=") {
+            BinaryOperator::Le
+        } else if self.cursor.eat(">=") {
+            BinaryOperator::Ge
+        } else if self.cursor.eat("<") {
+            BinaryOperator::Lt
+        } else if self.cursor.eat(">") {
+            BinaryOperator::Gt
+        } else {
+            return Ok(left);
+        };
+        let right = self.parse_additive()?;
+        Ok(Expression::Binary(
+            Box::new(left),
+            operator,
+            Box::new(right),
+        ))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let operator = if self.cursor.eat("+") {
+                BinaryOperator::Add
+            } else if self.cursor.eat("-") {
+                BinaryOperator::Subtract
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_multiplicative()?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expression, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let operator = if self.cursor.eat("*") {
+                BinaryOperator::Multiply
+            } else if self.cursor.eat("/") {
+                BinaryOperator::Divide
+            } else if self.cursor.eat("%") {
+                BinaryOperator::Remainder
+            } else {
+                return Ok(left);
+            };
+            let right = self.parse_unary()?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        self.cursor.skip_whitespace();
+        let rest = self.cursor.rest();
+        if rest.starts_with('-') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            self.cursor.advance();
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        if rest.starts_with('!') && !rest.starts_with("!=") {
+            self.cursor.advance();
+            return Ok(Expression::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        self.cursor.skip_whitespace();
+        match self.cursor.peek() {
+            Some('(') => {
+                self.cursor.advance();
+                let expression = self.parse_or()?;
+                self.cursor.expect(")")?;
+                Ok(expression)
+            }
+            // In operand position "/" starts a column pointer, it is a division otherwise
+            Some('/') => {
+                let start = self.cursor.position;
+                self.cursor.advance();
+                self.cursor.take_while(is_pointer_char);
+                Ok(Expression::Pointer(
+                    self.cursor.input[start..self.cursor.position].to_string(),
+                ))
+            }
+            Some('"') | Some('\'') => Ok(Expression::Literal(Value::String(
+                self.cursor.parse_string()?,
+            ))),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                self.cursor.parse_number().map(Expression::Literal)
+            }
+            Some(c) if c.is_alphabetic() => {
+                let start = self.cursor.position;
+                let name = self.cursor.take_while(|c| c.is_alphanumeric() || c == '_');
+                match name {
+                    "true" => return Ok(Expression::Literal(Value::Bool(true))),
+                    "false" => return Ok(Expression::Literal(Value::Bool(false))),
+                    "null" => return Ok(Expression::Literal(Value::Null)),
+                    _ => {}
+                }
+                let Some(function) = Function::from_name(name) else {
+                    self.cursor.position = start;
+                    return Err(self.cursor.error(&format!("Unknown function \"{}\"", name)));
+                };
+                self.cursor.expect("(")?;
+                let mut arguments = vec![];
+                if !self.cursor.eat(")") {
+                    loop {
+                        arguments.push(self.parse_or()?);
+                        if self.cursor.eat(")") {
+                            break;
+                        }
+                        self.cursor.expect(",")?;
+                    }
+                }
+                let (min, max) = function.arity();
+                if arguments.len() < min || arguments.len() > max {
+                    self.cursor.position = start;
+                    return Err(self
+                        .cursor
+                        .error(&format!("Wrong number of arguments for \"{}\"", name)));
+                }
+                Ok(Expression::Function(function, arguments))
+            }
+            _ => Err(self
+                .cursor
+                .error("Expected a column pointer, a literal or a function")),
+        }
+    }
+}
+
+#[inline]
+fn is_pointer_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '$' | '~' | '@' | '#' | '/')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::computed_column::{number_value, ComputedColumn};
+    use json_flat_parser::{FlatJsonValue, JsonArrayEntries, PointerKey, ValueType};
+    use serde_json::{json, Value};
+
+    fn row(row: &str) -> JsonArrayEntries<String> {
+        JsonArrayEntries {
+            entries: vec![FlatJsonValue {
+                pointer: PointerKey {
+                    pointer: "/0".to_string(),
+                    value_type: ValueType::Object(true, 0),
+                    depth: 1,
+                    position: 0,
+                    column_id: 0,
+                },
+                value: Some(row.to_string()),
+            }],
+            index: 0,
+        }
+    }
+
+    fn eval(expression: &str) -> Value {
+        let row = row(
+            r#"{"name": " Poring ", "level": 5, "rate": 0.5, "tags": ["a", "b"], "stats": {"hp": 50}, "boss": false, "zone": null}"#,
+        );
+        ComputedColumn::new("computed", expression)
+            .unwrap()
+            .json_value(&row)
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval("/level * 2 + 1"), json!(11));
+        assert_eq!(eval("/level - 1"), json!(4));
+        assert_eq!(eval("-/level"), json!(-5));
+        assert_eq!(eval("/level / 2"), json!(2.5));
+        assert_eq!(eval("/level % 2"), json!(1));
+        assert_eq!(eval("/level / 0"), Value::Null);
+        assert_eq!(eval("1e2 + 1.5e-1"), json!(100.15));
+        assert_eq!(eval("/stats/hp >= 50 && !/boss"), json!(true));
+        assert_eq!(eval("/level == 5 || /zone"), json!(true));
+        assert_eq!(eval("/level != 5"), json!(false));
+        assert_eq!(eval("'lv ' + /level"), json!("lv 5"));
+        assert_eq!(eval("/zone + 'a'"), Value::Null);
+        assert_eq!(eval("upper(trim(/name))"), json!("PORING"));
+        assert_eq!(eval("concat(lower('AB'), \"-\", /level)"), json!("ab-5"));
+        assert_eq!(eval("len(/tags) + len(/stats) + len('éa')"), json!(5));
+        assert_eq!(eval("round(/rate * 3.14159, 2)"), json!(1.57));
+        assert_eq!(eval("floor(/rate) + ceil(/rate) + abs(-2)"), json!(3));
+        assert_eq!(eval("min(/level, 3, /zone) + max(1, /rate)"), json!(4));
+        assert_eq!(eval("if(/boss, 'boss', 'mob')"), json!("mob"));
+        assert_eq!(eval("coalesce(/zone, /missing, 'none')"), json!("none"));
+        assert_eq!(eval("number('12.5') + number(true)"), json!(13.5));
+        assert_eq!(eval("string(/level) + string(/boss)"), json!("5false"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for expression in [
+            "",
+            "/level +",
+            "(/level",
+            "unknown(1)",
+            "round()",
+            "'unterminated",
+            "/level 1",
+            "é + 1",
+            "/level +\u{a0}é",
+        ] {
+            assert!(
+                ComputedColumn::new("computed", expression).is_err(),
+                "{} should not parse",
+                expression
+            );
+        }
+        assert!(ComputedColumn::new("", "1").is_err());
+        assert_eq!(ComputedColumn::new("total", "1").unwrap().name, "/total");
+        assert_eq!(eval("/level\u{a0}+\u{3000}1"), json!(6));
+    }
+
+    #[test]
+    fn test_number_value() {
+        assert_eq!(number_value(10.0), json!(10));
+        assert_eq!(number_value(-3.0), json!(-3));
+        assert_eq!(number_value(2.5), json!(2.5));
+        assert_eq!(number_value(f64::NAN), Value::Null);
+        assert_eq!(number_value(f64::INFINITY), Value::Null);
+        assert_eq!(number_value(1e300), json!(1e300));
+    }
+}
@@ -0,0 +1,129 @@
+//! Position in the text of a query or a computed column expression, shared by their parsers.
+//!
+//! Position is a byte offset which always stays on a char boundary: it only moves by the length of
+//! the char or of the text that was matched.
+
+use serde_json::Value;
+
+pub struct Cursor<'a> {
+    pub input: &'a str,
+    pub position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, position: 0 }
+    }
+
+    #[inline]
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Move after next char.
+    #[inline]
+    pub fn advance(&mut self) {
+        if let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+        }
+    }
+
+    /// Move after chars matching `predicate` and return them.
+    pub fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.position;
+        while self.peek().is_some_and(&predicate) {
+            self.advance();
+        }
+        &self.input[start..self.position]
+    }
+
+    #[inline]
+    pub fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    pub fn is_at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.position == self.input.len()
+    }
+
+    pub fn eat(&mut self, expected: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(expected) {
+            self.position += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect(&mut self, expected: &str) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected \"{}\"", expected)))
+        }
+    }
+
+    /// String between single or double quotes, `\n` and `\t` are unescaped, any other escaped char
+    /// is kept as is.
+    pub fn parse_string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap();
+        let start = self.position;
+        self.advance();
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            self.advance();
+            if c == quote {
+                return Ok(value);
+            }
+            if c == '\\' {
+                match self.peek() {
+                    Some(escaped) => {
+                        self.advance();
+                        value.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            escaped => escaped,
+                        });
+                    }
+                    None => break,
+                }
+            } else {
+                value.push(c);
+            }
+        }
+        self.position = start;
+        Err(self.error("Unterminated string"))
+    }
+
+    /// Json number, a sign is only read at its start and after its exponent.
+    pub fn parse_number(&mut self) -> Result<Value, String> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.advance();
+        }
+        self.take_while(|c| c.is_ascii_digit() || c == '.');
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.advance();
+            if matches!(self.peek(), Some('-' | '+')) {
+                self.advance();
+            }
+            self.take_while(|c| c.is_ascii_digit());
+        }
+        let input = self.input;
+        serde_json::from_str::<Value>(&input[start..self.position]).map_err(|_| {
+            self.position = start;
+            self.error("Invalid number")
+        })
+    }
+
+    pub fn error(&self, message: &str) -> String {
+        format!("{} at position {}", message, self.position + 1)
+    }
+}
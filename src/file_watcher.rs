@@ -0,0 +1,84 @@
+//! Detect modifications of the opened file made by other programs.
+//!
+//! The file is polled instead of relying on OS notifications: files we edit are often replaced
+//! (renamed over) by the tool regenerating them, which most notification backends report poorly.
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui::Context;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+pub struct FileWatcher {
+    // Stamp of the version loaded in (or last saved from) the editor
+    loaded: Option<FileStamp>,
+    // Stamp of a version user has chosen to not reload
+    ignored: Option<FileStamp>,
+    last_poll: Instant,
+    pub changed_on_disk: bool,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self {
+            loaded: None,
+            ignored: None,
+            last_poll: Instant::now(),
+            changed_on_disk: false,
+        }
+    }
+}
+
+impl FileWatcher {
+    /// To call once the editor content and the file on disk are the same (after loading or saving).
+    pub fn mark_in_sync(&mut self, path: &Path) {
+        self.loaded = FileStamp::read(path);
+        self.ignored = None;
+        self.changed_on_disk = false;
+    }
+
+    pub fn poll(&mut self, path: &Path, ctx: &Context) {
+        if self.loaded.is_none() {
+            return;
+        }
+        if self.last_poll.elapsed() >= POLL_INTERVAL {
+            self.last_poll = Instant::now();
+            let current = FileStamp::read(path);
+            if current.is_some() && current != self.loaded && current != self.ignored {
+                self.changed_on_disk = true;
+            }
+        }
+        ctx.request_repaint_after(POLL_INTERVAL);
+    }
+
+    pub fn ignore_change(&mut self, path: &Path) {
+        self.ignored = FileStamp::read(path);
+        self.changed_on_disk = false;
+    }
+
+    /// Whether saving now would overwrite a version of the file the editor has not loaded.
+    pub fn is_newer_on_disk(&self, path: &Path) -> bool {
+        match (self.loaded, FileStamp::read(path)) {
+            (Some(loaded), Some(current)) => loaded != current,
+            _ => false,
+        }
+    }
+}
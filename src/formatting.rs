@@ -0,0 +1,388 @@
+//! Conditional formatting: color cells or rows whose value of a column matches a condition, or
+//! color cells along a scale between min and max of a numeric column.
+
+use egui::Color32;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FormattingCondition {
+    Equals,
+    NotEquals,
+    GreaterThan,
+    GreaterOrEqual,
+    LessThan,
+    LessOrEqual,
+    Contains,
+    ColorScale,
+}
+
+impl FormattingCondition {
+    pub const ALL: [FormattingCondition; 8] = [
+        FormattingCondition::Equals,
+        FormattingCondition::NotEquals,
+        FormattingCondition::GreaterThan,
+        FormattingCondition::GreaterOrEqual,
+        FormattingCondition::LessThan,
+        FormattingCondition::LessOrEqual,
+        FormattingCondition::Contains,
+        FormattingCondition::ColorScale,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormattingCondition::Equals => "==",
+            FormattingCondition::NotEquals => "!=",
+            FormattingCondition::GreaterThan => ">",
+            FormattingCondition::GreaterOrEqual => ">=",
+            FormattingCondition::LessThan => "<",
+            FormattingCondition::LessOrEqual => "<=",
+            FormattingCondition::Contains => "contains",
+            FormattingCondition::ColorScale => "color scale",
+        }
+    }
+
+    pub fn from_name(condition: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.as_str() == condition)
+    }
+
+    /// Whether condition value is compared as a number.
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            FormattingCondition::GreaterThan
+                | FormattingCondition::GreaterOrEqual
+                | FormattingCondition::LessThan
+                | FormattingCondition::LessOrEqual
+        )
+    }
+
+    pub fn has_value(&self) -> bool {
+        !matches!(self, FormattingCondition::ColorScale)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum FormattingTarget {
+    /// Color text of matching cell
+    #[default]
+    Cell,
+    /// Color background of the whole row
+    Row,
+}
+
+impl FormattingTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FormattingTarget::Cell => "cell",
+            FormattingTarget::Row => "row",
+        }
+    }
+
+    pub fn from_name(target: &str) -> Option<Self> {
+        [FormattingTarget::Cell, FormattingTarget::Row]
+            .into_iter()
+            .find(|t| t.as_str() == target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FormattingRule {
+    pub column: String,
+    pub condition: FormattingCondition,
+    pub value: String,
+    pub target: FormattingTarget,
+    pub color: Color32,
+}
+
+impl FormattingRule {
+    pub fn new(
+        column: String,
+        condition: FormattingCondition,
+        value: String,
+        target: FormattingTarget,
+        color: Color32,
+    ) -> Result<Self, String> {
+        if condition.is_numeric() && value.trim().parse::<f64>().is_err() {
+            return Err(format!(
+                "{} requires a number, got \"{}\"",
+                condition.as_str(),
+                value
+            ));
+        }
+        let target = if matches!(condition, FormattingCondition::ColorScale) {
+            FormattingTarget::Cell
+        } else {
+            target
+        };
+        Ok(Self {
+            column,
+            condition,
+            value,
+            target,
+            color,
+        })
+    }
+
+    /// Null or missing values never match.
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        let numbers = || {
+            value
+                .parse::<f64>()
+                .ok()
+                .zip(self.value.trim().parse::<f64>().ok())
+        };
+        match self.condition {
+            FormattingCondition::Equals => {
+                value == self.value || numbers().is_some_and(|(a, b)| a == b)
+            }
+            FormattingCondition::NotEquals => {
+                value != self.value && !numbers().is_some_and(|(a, b)| a == b)
+            }
+            FormattingCondition::GreaterThan => numbers().is_some_and(|(a, b)| a > b),
+            FormattingCondition::GreaterOrEqual => numbers().is_some_and(|(a, b)| a >= b),
+            FormattingCondition::LessThan => numbers().is_some_and(|(a, b)| a < b),
+            FormattingCondition::LessOrEqual => numbers().is_some_and(|(a, b)| a <= b),
+            FormattingCondition::Contains => value.contains(self.value.as_str()),
+            FormattingCondition::ColorScale => false,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self.condition {
+            FormattingCondition::ColorScale => format!("color scale of {}", self.column),
+            _ => format!(
+                "{} {} {} ({})",
+                self.column,
+                self.condition.as_str(),
+                self.value,
+                self.target.as_str()
+            ),
+        }
+    }
+}
+
+/// Cell text color and background color.
+#[derive(Default, Clone, Copy)]
+pub struct CellStyle {
+    pub color: Option<Color32>,
+    pub background: Option<Color32>,
+}
+
+/// Formatting rules, first matching rule wins.
+#[derive(Default)]
+pub struct Formatting {
+    pub rules: Vec<FormattingRule>,
+    // Min and max values of columns having a color scale rule, missing when they have to be
+    // computed again
+    ranges: HashMap<String, (f64, f64)>,
+}
+
+impl Formatting {
+    pub fn new(rules: Vec<FormattingRule>) -> Self {
+        Self {
+            rules,
+            ranges: HashMap::new(),
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: FormattingRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn remove_rule(&mut self, index: usize) {
+        if index < self.rules.len() {
+            let rule = self.rules.remove(index);
+            self.ranges.remove(&rule.column);
+        }
+    }
+
+    /// Columns of color scale rules.
+    pub fn scale_columns(&self) -> impl Iterator<Item = &String> {
+        self.rules
+            .iter()
+            .filter(|rule| matches!(rule.condition, FormattingCondition::ColorScale))
+            .map(|rule| &rule.column)
+    }
+
+    /// Columns of color scale rules whose min and max have to be computed.
+    pub fn missing_ranges(&self) -> Vec<String> {
+        let mut columns = self
+            .scale_columns()
+            .filter(|column| !self.ranges.contains_key(*column))
+            .cloned()
+            .collect::<Vec<String>>();
+        columns.sort();
+        columns.dedup();
+        columns
+    }
+
+    pub fn set_range(&mut self, column: String, range: (f64, f64)) {
+        self.ranges.insert(column, range);
+    }
+
+    /// Values changed, color scales have to be computed again.
+    pub fn clear_ranges(&mut self) {
+        self.ranges.clear();
+    }
+
+    pub fn has_row_rules(&self) -> bool {
+        self.row_rules().next().is_some()
+    }
+
+    /// Rules coloring the whole row.
+    pub fn row_rules(&self) -> impl Iterator<Item = &FormattingRule> {
+        self.rules
+            .iter()
+            .filter(|rule| matches!(rule.target, FormattingTarget::Row))
+    }
+
+    /// Background of a row, `value_of` gives value of a column for this row.
+    pub fn row_background<'a>(
+        &self,
+        value_of: impl Fn(&str) -> Option<&'a String>,
+    ) -> Option<Color32> {
+        self.row_rules()
+            .find(|rule| rule.matches(value_of(&rule.column).map(String::as_str)))
+            .map(|rule| rule.color.gamma_multiply(0.5))
+    }
+
+    pub fn cell_style(&self, column: &str, value: Option<&str>) -> CellStyle {
+        let mut style = CellStyle::default();
+        for rule in self.rules.iter().filter(|rule| rule.column == column) {
+            match (rule.condition, rule.target) {
+                (FormattingCondition::ColorScale, _) => {
+                    if style.background.is_none() {
+                        style.background = self.scale_color(rule, value);
+                    }
+                }
+                (_, FormattingTarget::Cell) => {
+                    if style.color.is_none() && rule.matches(value) {
+                        style.color = Some(rule.color);
+                    }
+                }
+                (_, FormattingTarget::Row) => {}
+            }
+        }
+        style
+    }
+
+    /// Rule color, more transparent as value gets closer to the column min.
+    fn scale_color(&self, rule: &FormattingRule, value: Option<&str>) -> Option<Color32> {
+        let value = value?.parse::<f64>().ok()?;
+        let (min, max) = self.ranges.get(&rule.column)?;
+        let ratio = if max > min {
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        Some(rule.color.gamma_multiply(0.1 + 0.7 * ratio as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatting::{Formatting, FormattingCondition, FormattingRule, FormattingTarget};
+    use egui::Color32;
+
+    fn rule(column: &str, condition: FormattingCondition, value: &str) -> FormattingRule {
+        FormattingRule::new(
+            column.to_string(),
+            condition,
+            value.to_string(),
+            FormattingTarget::Cell,
+            Color32::RED,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(FormattingRule::new(
+            "/hp".to_string(),
+            FormattingCondition::GreaterThan,
+            "high".to_string(),
+            FormattingTarget::Cell,
+            Color32::RED,
+        )
+        .is_err());
+        let scale = FormattingRule::new(
+            "/hp".to_string(),
+            FormattingCondition::ColorScale,
+            String::new(),
+            FormattingTarget::Row,
+            Color32::RED,
+        )
+        .unwrap();
+        assert_eq!(scale.target, FormattingTarget::Cell);
+        assert_eq!(
+            FormattingCondition::from_name(">="),
+            Some(FormattingCondition::GreaterOrEqual)
+        );
+        assert_eq!(
+            FormattingTarget::from_name("row"),
+            Some(FormattingTarget::Row)
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let equals = rule("/level", FormattingCondition::Equals, "10");
+        assert!(equals.matches(Some("10")));
+        assert!(equals.matches(Some("10.0")));
+        assert!(!equals.matches(Some("1")));
+        assert!(!equals.matches(None));
+        let not_equals = rule("/level", FormattingCondition::NotEquals, "10");
+        assert!(not_equals.matches(Some("abc")));
+        assert!(!not_equals.matches(Some("1e1")));
+        assert!(!not_equals.matches(None));
+        let greater = rule("/level", FormattingCondition::GreaterThan, " 2.5 ");
+        assert!(greater.matches(Some("3")));
+        assert!(!greater.matches(Some("2.5")));
+        assert!(!greater.matches(Some("high")));
+        assert!(rule("/level", FormattingCondition::GreaterOrEqual, "2.5").matches(Some("2.5")));
+        assert!(rule("/level", FormattingCondition::LessThan, "0").matches(Some("-1")));
+        assert!(!rule("/level", FormattingCondition::LessOrEqual, "0").matches(Some("1")));
+        let contains = rule("/name", FormattingCondition::Contains, "ori");
+        assert!(contains.matches(Some("Poring")));
+        assert!(!contains.matches(Some("Drops")));
+        assert!(!rule("/hp", FormattingCondition::ColorScale, "").matches(Some("1")));
+    }
+
+    #[test]
+    fn test_scale_color() {
+        let mut formatting = Formatting::new(vec![
+            rule("/name", FormattingCondition::Equals, "Poring"),
+            rule("/hp", FormattingCondition::ColorScale, ""),
+        ]);
+        assert_eq!(formatting.missing_ranges(), vec!["/hp".to_string()]);
+        // Range is not known yet
+        assert!(formatting.cell_style("/hp", Some("5")).background.is_none());
+        formatting.set_range("/hp".to_string(), (0.0, 10.0));
+        assert!(formatting.missing_ranges().is_empty());
+        let background = |value| formatting.cell_style("/hp", value).background;
+        assert_eq!(
+            background(Some("0")),
+            Some(Color32::RED.gamma_multiply(0.1))
+        );
+        assert_eq!(
+            background(Some("5")),
+            Some(Color32::RED.gamma_multiply(0.45))
+        );
+        assert_eq!(
+            background(Some("10")),
+            Some(Color32::RED.gamma_multiply(0.8))
+        );
+        assert_eq!(background(Some("20")), background(Some("10")));
+        assert_eq!(background(Some("n/a")), None);
+        assert_eq!(background(None), None);
+        assert_eq!(
+            formatting.cell_style("/name", Some("Poring")).color,
+            Some(Color32::RED)
+        );
+        formatting.clear_ranges();
+        assert_eq!(formatting.missing_ranges(), vec!["/hp".to_string()]);
+    }
+}
@@ -2,34 +2,52 @@
 
 extern crate core;
 
+mod aggregates;
 mod array_table;
+mod chart;
 mod compatibility;
 mod components;
+#[cfg(not(target_arch = "wasm32"))]
+mod compression;
+mod computed_column;
+mod cursor;
+#[cfg(not(target_arch = "wasm32"))]
+mod file_watcher;
 pub mod fonts;
+mod formatting;
 mod object_table;
 mod panels;
 pub mod parser;
+mod pivot;
+mod query;
+#[cfg(not(target_arch = "wasm32"))]
+mod recovery;
 mod replace_panel;
+mod schema;
 mod subtable_window;
 mod web;
+#[cfg(not(target_arch = "wasm32"))]
+mod workspace;
 
 use std::collections::BTreeSet;
 use std::fmt::Write;
-use std::fs::File;
-use std::io::Read;
 use std::{env, mem};
 
 use crate::components::fps::FrameHistory;
 use parking_lot_mpsc::{Receiver, SyncSender};
 use std::path::PathBuf;
 use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant, SystemTime};
 
+use crate::aggregates::Aggregate;
 use crate::array_table::{ArrayTable, ScrollToRowMode};
 use crate::components::icon;
 use crate::components::table::HoverData;
-use crate::fonts::{CHEVRON_DOWN, CHEVRON_UP};
-use crate::panels::{AboutPanel, PANEL_ABOUT};
-use crate::parser::{save_to_buffer, save_to_file};
+use crate::fonts::{CHEVRON_DOWN, CHEVRON_UP, FILTER, FILTER_CIRCLE_XMARK, TABLE};
+use crate::panels::{AboutPanel, ReplaceMode, SaveOptionsPanel, PANEL_ABOUT, PANEL_SAVE_OPTIONS};
+#[cfg(target_arch = "wasm32")]
+use crate::parser::save_to_buffer;
 use eframe::egui::Context;
 use eframe::egui::{
     Align, Align2, Button, Color32, ComboBox, CursorIcon, Id, Key, KeyboardShortcut, Label,
@@ -39,7 +57,7 @@ use eframe::egui::{
 use eframe::epaint::text::TextWrapMode;
 use eframe::{CreationContext, Renderer};
 use egui::style::ScrollStyle;
-use egui::ScrollArea;
+use egui::{ScrollArea, UiBuilder};
 use json_flat_parser::{FlatJsonValue, JSONParser, ParseOptions, PointerKey, ValueType};
 
 pub const ACTIVE_COLOR: Color32 = Color32::from_rgb(63, 142, 252);
@@ -51,6 +69,11 @@ pub const SHORTCUT_COPY: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COM
 pub const SHORTCUT_PASTE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::V);
 pub const SHORTCUT_DELETE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::D);
 pub const SHORTCUT_REPLACE: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::R);
+pub const SHORTCUT_NEXT_ERROR: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::F8);
+pub const SHORTCUT_MOVE_ROWS_UP: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::ALT, Key::ArrowUp);
+pub const SHORTCUT_MOVE_ROWS_DOWN: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::ALT, Key::ArrowDown);
 
 /// Something to view in the demo windows
 pub trait View<R> {
@@ -75,7 +98,6 @@ pub trait Window<R> {
 struct ArrayResponse {
     pub(crate) edited_value: Vec<FlatJsonValue<String>>,
     pub(crate) hover_data: HoverData,
-    pub(crate) focused_cell: bool,
 }
 
 impl ArrayResponse {
@@ -141,6 +163,7 @@ struct MyApp<'array> {
     table: Option<ArrayTable<'array>>,
     open: BTreeSet<String>,
     about_panel: AboutPanel,
+    save_options_panel: SaveOptionsPanel,
     max_depth: u8,
     depth: u8,
     selected_file: Option<PathBuf>,
@@ -151,14 +174,40 @@ struct MyApp<'array> {
     min_depth: u8,
     unsaved_changes: bool,
     show_fps: bool,
+    #[cfg(target_arch = "wasm32")]
     web_loaded_json: Option<Vec<u8>>,
     async_events_channel: (SyncSender<AsyncEvent>, Receiver<AsyncEvent>),
+    #[cfg(target_arch = "wasm32")]
     failed_to_load_sample_json: Option<String>,
     force_repaint: bool,
+    file_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    unjournaled_changes_since: Option<Instant>,
+    #[cfg(not(target_arch = "wasm32"))]
+    recovery_journal: Option<recovery::RecoveryJournal>,
+    #[cfg(not(target_arch = "wasm32"))]
+    file_watcher: file_watcher::FileWatcher,
+    #[cfg(not(target_arch = "wasm32"))]
+    confirm_overwrite: bool,
+    // Shown in external change banner, current table is kept when modified file can not be parsed
+    #[cfg(not(target_arch = "wasm32"))]
+    reload_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    file_compression: compression::Compression,
+    #[cfg(not(target_arch = "wasm32"))]
+    zip_member: Option<String>,
+    // Members to pick from when opened zip archive contains several files
+    #[cfg(not(target_arch = "wasm32"))]
+    zip_members: Vec<String>,
+    // Attached or detected from `$schema`, kept when file is reloaded
+    #[cfg(not(target_arch = "wasm32"))]
+    schema_path: Option<PathBuf>,
 }
 
 enum AsyncEvent {
+    #[cfg(target_arch = "wasm32")]
     LoadJson(Vec<u8>),
+    #[cfg(target_arch = "wasm32")]
     LoadSampleErr(String),
 }
 
@@ -181,6 +230,7 @@ impl MyApp<'_> {
             table: None,
             open: Default::default(),
             about_panel: Default::default(),
+            save_options_panel: Default::default(),
             max_depth: 0,
             depth: 0,
             selected_file: None,
@@ -191,10 +241,31 @@ impl MyApp<'_> {
             min_depth: 0,
             unsaved_changes: false,
             show_fps: true,
+            #[cfg(target_arch = "wasm32")]
             web_loaded_json: None,
             async_events_channel: (sender, receiver),
+            #[cfg(target_arch = "wasm32")]
             failed_to_load_sample_json: None,
             force_repaint: false,
+            file_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            unjournaled_changes_since: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            recovery_journal: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            file_watcher: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            confirm_overwrite: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            reload_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            file_compression: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            zip_member: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            zip_members: vec![],
+            #[cfg(not(target_arch = "wasm32"))]
+            schema_path: None,
         }
     }
     pub fn windows(&mut self, ctx: &Context) {
@@ -202,39 +273,99 @@ impl MyApp<'_> {
         let mut is_open = open.contains(self.about_panel.name());
         self.about_panel.show(ctx, &mut is_open);
         set_open(open, self.about_panel.name(), is_open);
+        let mut is_open = open.contains(self.save_options_panel.name());
+        self.save_options_panel.show(ctx, &mut is_open);
+        set_open(open, self.save_options_panel.name(), is_open);
     }
 
     pub fn open_json(&mut self) {
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let mut file = File::open(self.selected_file.as_ref().unwrap()).unwrap();
-            let metadata1 = file.metadata().unwrap();
+            let path = self.selected_file.clone().unwrap();
+            let content = match self.read_selected_file(&path) {
+                Ok(Some(content)) => content,
+                // Waiting for user to pick a member of the zip archive
+                Ok(None) => return,
+                Err(e) => {
+                    self.file_error = Some(format!("Failed to open {}: {}", path.display(), e));
+                    self.should_parse_again = false;
+                    return;
+                }
+            };
+            self.file_error = None;
+            self.recovery_journal = recovery::find_journal(&path);
 
-            let size = (metadata1.len() / 1024 / 1024) as usize;
+            let size = content.len() / 1024 / 1024;
             let max_depth = if size < 100 {
                 // 1
                 u8::MAX
             } else {
                 1 // should start after prefix
             };
-            let mut content = String::with_capacity(metadata1.len() as usize);
-            // let mut reader = LfToCrlfReader::new(file);
-            // reader.read_to_string(&mut content);
-            file.read_to_string(&mut content).unwrap();
 
-            self.open_json_content(max_depth, content.as_bytes());
+            if let Err(e) = self.open_json_content(max_depth, content.as_slice()) {
+                self.file_error = Some(format!("Failed to parse {}: {}", path.display(), e));
+                self.should_parse_again = false;
+                return;
+            }
+            self.file_watcher.mark_in_sync(&path);
+            self.reload_error = None;
+            if self.table.is_some() {
+                let schema_path = self.schema_path.clone().or_else(|| {
+                    schema::detect_schema_reference(content.as_slice())
+                        .and_then(|reference| schema::resolve_schema_reference(&reference, &path))
+                });
+                if let Some(schema_path) = schema_path {
+                    self.attach_schema(schema_path);
+                }
+                self.restore_workspace(&path);
+            }
         }
         #[cfg(target_arch = "wasm32")]
         {
             if self.web_loaded_json.is_some() {
                 let json = mem::take(&mut self.web_loaded_json);
-                self.open_json_content(u8::MAX, json.unwrap().as_slice());
+                if let Err(e) = self.open_json_content(u8::MAX, json.unwrap().as_slice()) {
+                    self.file_error = Some(format!("Failed to parse file: {}", e));
+                    self.should_parse_again = false;
+                    return;
+                }
                 self.selected_file = Some(PathBuf::default());
             }
         }
     }
 
-    fn open_json_content(&mut self, max_depth: u8, json: &[u8]) {
+    /// Decompressed content of selected file, `None` when a member of zip archive has to be picked first.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_selected_file(&mut self, path: &std::path::Path) -> std::io::Result<Option<Vec<u8>>> {
+        self.file_compression = compression::Compression::detect(path)?;
+        if matches!(self.file_compression, compression::Compression::Zip) {
+            if self.zip_member.is_none() {
+                let members = compression::zip_members(path)?;
+                let json_members_count = members
+                    .iter()
+                    .filter(|m| compression::is_json_member(m))
+                    .count();
+                if members.is_empty() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "archive contains no file",
+                    ));
+                } else if json_members_count == 1 || members.len() == 1 {
+                    self.zip_member = members.into_iter().next();
+                } else {
+                    self.zip_members = members;
+                    self.should_parse_again = false;
+                    return Ok(None);
+                }
+            }
+        } else {
+            self.zip_member = None;
+        }
+        compression::read_file(path, self.file_compression, self.zip_member.as_deref()).map(Some)
+    }
+
+    fn open_json_content(&mut self, max_depth: u8, json: &[u8]) -> Result<(), String> {
         let mut found_array = false;
         let size = json.len() / 1024 / 1024;
         for byte in json {
@@ -259,9 +390,7 @@ impl MyApp<'_> {
             if let Some(ref start_at) = self.selected_pointer {
                 options = options.start_parse_at(start_at.clone());
             }
-            let parse_result = JSONParser::parse_bytes(json, options);
-
-            let result = parse_result.unwrap().to_owned();
+            let result = JSONParser::parse_bytes(json, options)?.to_owned();
             let parsing_max_depth = result.parsing_max_depth;
             log!(
                 "Custom parser took {}ms for a {}mb file, max depth {}, {}",
@@ -273,7 +402,7 @@ impl MyApp<'_> {
             let parse_result = result.clone_except_json();
 
             let start = crate::compatibility::now();
-            let (result1, columns) = crate::parser::as_array(result).unwrap();
+            let (result1, columns) = crate::parser::as_array(result)?;
             log!(
                 "Transformation to array took {}ms, root array len {}, columns {}",
                 start.elapsed().as_millis(),
@@ -314,7 +443,7 @@ impl MyApp<'_> {
             let options = ParseOptions::default()
                 .parse_array(false)
                 .max_depth(max_depth);
-            let result = JSONParser::parse_bytes(json, options.clone()).unwrap();
+            let result = JSONParser::parse_bytes(json, options.clone())?;
             self.should_parse_again = true;
             self.parsing_invalid = true;
             self.unsaved_changes = false;
@@ -329,6 +458,7 @@ impl MyApp<'_> {
                 .map(|entry| entry.pointer.pointer.clone())
                 .collect();
         }
+        Ok(())
     }
 
     fn file_picker(&mut self) {
@@ -336,6 +466,8 @@ impl MyApp<'_> {
         {
             if let Some(path) = rfd::FileDialog::new().pick_file() {
                 self.selected_file = Some(path);
+                self.zip_member = None;
+                self.schema_path = None;
                 self.should_parse_again = true;
                 self.table = None;
             }
@@ -354,19 +486,6 @@ impl MyApp<'_> {
         }
     }
 
-    fn goto_next_matching_row_occurrence(table: &mut ArrayTable) -> bool {
-        if table.matching_rows.is_empty() {
-            return false;
-        }
-        if table.matching_row_selected == table.matching_rows.len() - 1 {
-            table.matching_row_selected = 0;
-        } else {
-            table.matching_row_selected += 1;
-        }
-        table.changed_matching_row_selected = true;
-        true
-    }
-
     fn goto_next_matching_column_occurrence(table: &mut ArrayTable) -> bool {
         if table.matching_columns.is_empty() {
             return false;
@@ -382,14 +501,83 @@ impl MyApp<'_> {
 
     #[cfg(not(target_arch = "wasm32"))]
     fn save(&mut self) {
+        if self
+            .file_watcher
+            .is_newer_on_disk(self.selected_file.as_ref().unwrap())
+        {
+            self.confirm_overwrite = true;
+            return;
+        }
+        self.write_file();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_file(&mut self) {
         let table = self.table.as_ref().unwrap();
-        save_to_file(
+        let path = self.selected_file.as_ref().unwrap();
+        match compression::save_to_file(
             table.parent_pointer.pointer.as_str(),
             table.nodes(),
-            self.selected_file.as_ref().unwrap(),
-        )
-        .unwrap();
-        self.unsaved_changes = false;
+            path,
+            self.file_compression,
+            self.zip_member.as_deref(),
+            &self.save_options_panel.save_options,
+            &self.save_options_panel.backup_options,
+        ) {
+            Ok(_) => {
+                recovery::delete_journal(path);
+                self.file_watcher.mark_in_sync(path);
+                self.unsaved_changes = false;
+                self.unjournaled_changes_since = None;
+                self.file_error = None;
+                self.write_workspace();
+            }
+            Err(e) => {
+                self.file_error = Some(format!("Failed to save {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    /// Save view settings of current table next to the selected file, unless they are the defaults.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_workspace(&self) {
+        let (Some(table), Some(path)) = (self.table.as_ref(), self.selected_file.as_ref()) else {
+            return;
+        };
+        if let Err(e) = workspace::write_workspace(
+            path,
+            &table.parent_pointer.pointer,
+            &table.view_state(),
+            &self.save_options_panel.save_options,
+            &self.save_options_panel.backup_options,
+        ) {
+            log!("Failed to write workspace for {}: {}", path.display(), e);
+        }
+    }
+
+    /// Apply view settings saved for the opened file, when they are about the displayed array.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore_workspace(&mut self, path: &std::path::Path) {
+        if let Some(save_options) = workspace::read_save_options(path) {
+            self.save_options_panel.save_options = save_options;
+        }
+        if let Some(backup_options) = workspace::read_backup_options(path) {
+            self.save_options_panel.backup_options = backup_options;
+        }
+        let Some((parent_pointer, mut view_state)) = workspace::read_workspace(path) else {
+            return;
+        };
+        let Some(ref mut table) = self.table else {
+            return;
+        };
+        if table.parent_pointer.pointer != parent_pointer {
+            return;
+        }
+        view_state.depth = view_state.depth.clamp(self.min_depth, self.max_depth);
+        if let Some(new_max_depth) = table.restore_view_state(view_state) {
+            self.max_depth = new_max_depth as u8;
+        }
+        self.depth = table.max_depth;
     }
 
     #[cfg(target_arch = "wasm32")]
@@ -400,6 +588,7 @@ impl MyApp<'_> {
             table.parent_pointer.pointer.as_str(),
             table.nodes(),
             &mut buffer,
+            &self.save_options_panel.save_options,
         )
         .unwrap();
         use eframe::wasm_bindgen::JsCast;
@@ -425,15 +614,303 @@ impl MyApp<'_> {
     #[cfg(not(target_arch = "wasm32"))]
     fn save_as(&mut self) {
         if let Some(path) = rfd::FileDialog::new().save_file() {
-            self.selected_file = Some(path);
-            let table = self.table.as_ref().unwrap();
-            save_to_file(
+            let previous_compression = mem::replace(
+                &mut self.file_compression,
+                compression::Compression::from_extension(&path),
+            );
+            let previous_path = self.selected_file.replace(path);
+            // User already confirmed overwriting the selected file in the dialog
+            self.write_file();
+            if self.file_error.is_none() {
+                if let Some(previous_path) = previous_path {
+                    recovery::delete_journal(&previous_path);
+                }
+            } else {
+                // Keep editing the previous file, its journal still holds unsaved changes
+                self.selected_file = previous_path;
+                self.file_compression = previous_compression;
+            }
+        }
+    }
+
+    /// Load again selected file, keeping columns, filters and scroll position of current view.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload(&mut self) {
+        let Some(table) = self.table.as_ref() else {
+            return;
+        };
+        let view_state = table.view_state();
+        if !table.parent_pointer.pointer.is_empty() {
+            self.selected_pointer = Some(table.parent_pointer.pointer.clone());
+        }
+        // Workspace restored when opening the file must not bring back removed settings
+        self.write_workspace();
+        self.open_json();
+        self.confirm_overwrite = false;
+        if let Some(error) = self.file_error.take() {
+            self.reload_error = Some(error);
+            self.selected_pointer = None;
+            return;
+        }
+        if let Some(ref mut table) = self.table {
+            let mut view_state = view_state;
+            view_state.depth = view_state.depth.clamp(self.min_depth, self.max_depth);
+            if let Some(new_max_depth) = table.restore_view_state(view_state) {
+                self.max_depth = new_max_depth as u8;
+            }
+            self.depth = table.max_depth;
+        }
+        self.unjournaled_changes_since = None;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn external_change_banner(&mut self, ctx: &Context) {
+        let Some(path) = self.selected_file.clone() else {
+            return;
+        };
+        if self.table.is_none() {
+            return;
+        }
+        self.file_watcher.poll(&path, ctx);
+        if self.file_watcher.changed_on_disk {
+            let mut reload = false;
+            egui::TopBottomPanel::top("external_change").show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(
+                        Color32::from_rgb(230, 160, 40),
+                        "File has been modified on disk since it was loaded.",
+                    );
+                    if self.unsaved_changes {
+                        ui.label("Reloading discards unsaved changes.");
+                    }
+                    if let Some(ref reload_error) = self.reload_error {
+                        ui.colored_label(Color32::RED, reload_error);
+                    }
+                    reload = ui.button("Reload").clicked();
+                    if ui.button("Ignore").clicked() {
+                        self.file_watcher.ignore_change(&path);
+                        self.reload_error = None;
+                    }
+                });
+            });
+            if reload {
+                self.reload();
+            }
+        }
+        if self.confirm_overwrite {
+            let mut overwrite = false;
+            let mut reload = false;
+            egui::Window::new("File modified on disk")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "{} has been modified on disk since it was loaded, saving will overwrite those modifications.",
+                        path.display()
+                    ));
+                    ui.horizontal(|ui| {
+                        overwrite = ui.button("Overwrite").clicked();
+                        reload = ui.button("Reload and discard my changes").clicked();
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_overwrite = false;
+                        }
+                    });
+                });
+            if overwrite {
+                self.confirm_overwrite = false;
+                self.write_file();
+            } else if reload {
+                self.reload();
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn autosave(&mut self, ctx: &Context) {
+        let Some(changes_since) = self.unjournaled_changes_since else {
+            return;
+        };
+        let backup_options = &self.save_options_panel.backup_options;
+        if !backup_options.autosave || !self.unsaved_changes {
+            return;
+        }
+        let due = changes_since
+            + Duration::from_secs(backup_options.autosave_interval_minutes as u64 * 60);
+        let now = Instant::now();
+        if now < due {
+            ctx.request_repaint_after(due - now);
+            return;
+        }
+        if let (Some(table), Some(path)) = (self.table.as_ref(), self.selected_file.as_ref()) {
+            if let Err(e) = recovery::write_journal(
+                path,
                 table.parent_pointer.pointer.as_str(),
                 table.nodes(),
-                self.selected_file.as_ref().unwrap(),
+                &self.save_options_panel.save_options,
+            ) {
+                log!(
+                    "Failed to write recovery journal for {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.unjournaled_changes_since = None;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore_journal(&mut self) {
+        let Some(journal) = self.recovery_journal.take() else {
+            return;
+        };
+        match recovery::read_journal(&journal.path) {
+            Ok((parent_pointer, content)) => {
+                self.selected_pointer = if parent_pointer.is_empty() {
+                    None
+                } else {
+                    Some(parent_pointer)
+                };
+                if let Err(e) = self.open_json_content(u8::MAX, content.as_slice()) {
+                    self.file_error = Some(format!("Failed to restore unsaved changes: {}", e));
+                    return;
+                }
+                self.unsaved_changes = true;
+                if let Some(schema_path) = self.schema_path.clone() {
+                    self.attach_schema(schema_path);
+                }
+            }
+            Err(e) => {
+                log!(
+                    "Failed to read recovery journal {}: {}",
+                    journal.path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn attach_schema(&mut self, schema_path: PathBuf) {
+        let Some(ref mut table) = self.table else {
+            return;
+        };
+        let validator = schema::load_schema(&schema_path).and_then(|schema| {
+            schema::RowValidator::new(
+                &schema,
+                table.parent_pointer.pointer.as_str(),
+                schema_path.display().to_string(),
             )
-            .unwrap();
-            self.unsaved_changes = false;
+        });
+        match validator {
+            Ok(validator) => {
+                table.set_schema_validator(Some(validator));
+                self.schema_path = Some(schema_path);
+            }
+            Err(e) => {
+                table.set_schema_validator(None);
+                self.schema_path = None;
+                self.file_error = Some(e);
+            }
+        }
+    }
+
+    /// Infer schema from all rows and save it in a file picked by user.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn generate_schema(&mut self) {
+        let Some(ref table) = self.table else {
+            return;
+        };
+        let schema = schema::infer_schema(table.nodes(), table.parent_pointer.pointer.as_str());
+        let file_name = self
+            .selected_file
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .map_or("schema.json".to_string(), |name| {
+                format!("{}.schema.json", name)
+            });
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(file_name)
+            .add_filter("JSON Schema", &["json"])
+            .save_file()
+        {
+            if let Err(e) = std::fs::write(&path, schema) {
+                self.file_error = Some(format!("Failed to write schema {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn zip_member_window(&mut self, ctx: &Context) {
+        if self.zip_members.is_empty() {
+            return;
+        }
+        let mut selected_member = None;
+        let mut open = true;
+        egui::Window::new("Select file to open in archive")
+            .collapsible(false)
+            .open(&mut open)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for member in self.zip_members.iter() {
+                        if ui.selectable_label(false, member.as_str()).clicked() {
+                            selected_member = Some(member.clone());
+                        }
+                    }
+                });
+            });
+        if let Some(member) = selected_member {
+            self.zip_members.clear();
+            self.zip_member = Some(member);
+            self.open_json();
+        } else if !open {
+            self.zip_members.clear();
+            self.selected_file = None;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recovery_window(&mut self, ctx: &Context) {
+        let Some(ref journal) = self.recovery_journal else {
+            return;
+        };
+        let minutes_ago = SystemTime::now()
+            .duration_since(journal.modified)
+            .unwrap_or_default()
+            .as_secs()
+            / 60;
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Recover unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Unsaved changes of this file from a previous session were found ({} minutes ago).",
+                    minutes_ago
+                ));
+                if journal.file_changed_since {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        "The file was modified since, restoring will overwrite those changes.",
+                    );
+                }
+                ui.horizontal(|ui| {
+                    restore = ui.button("Restore").clicked();
+                    discard = ui.button("Discard").clicked();
+                });
+            });
+        if restore {
+            self.restore_journal();
+        } else if discard {
+            self.recovery_journal = None;
+            if let Some(ref path) = self.selected_file {
+                recovery::delete_journal(path);
+            }
         }
     }
 }
@@ -460,13 +937,12 @@ impl eframe::App for MyApp<'_> {
         if let Ok(event) = self.async_events_channel.1.try_recv() {
             self.force_repaint = false;
             match event {
+                #[cfg(target_arch = "wasm32")]
                 AsyncEvent::LoadJson(json_bytes) => {
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        self.web_loaded_json = Some(json_bytes);
-                        self.open_json();
-                    }
+                    self.web_loaded_json = Some(json_bytes);
+                    self.open_json();
                 }
+                #[cfg(target_arch = "wasm32")]
                 AsyncEvent::LoadSampleErr(err) => {
                     self.failed_to_load_sample_json = Some(err);
                     ctx.request_repaint();
@@ -476,11 +952,16 @@ impl eframe::App for MyApp<'_> {
         #[cfg(not(target_arch = "wasm32"))]
         {
             let mut title = format!(
-                "json table editor - {}{}",
+                "json table editor - {}{}{}",
                 self.selected_file
                     .as_ref()
                     .map(|p| p.display().to_string())
                     .unwrap_or("No file selected".to_string()),
+                match (self.file_compression, self.zip_member.as_ref()) {
+                    (compression::Compression::Zip, Some(member)) => format!(" ({})", member),
+                    (compression::Compression::None, _) => "".to_string(),
+                    (compression, _) => format!(" ({})", compression.label()),
+                },
                 if self.unsaved_changes { " *" } else { "" }
             );
 
@@ -497,6 +978,12 @@ impl eframe::App for MyApp<'_> {
             );
         }
         self.windows(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.recovery_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.zip_member_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.external_change_banner(ctx);
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             ui.horizontal_wrapped(|ui| {
                 if self.table.is_some() {
@@ -521,6 +1008,11 @@ impl eframe::App for MyApp<'_> {
                                 self.save_as();
                             }
                         }
+                        ui.separator();
+                        if ui.button("Save options").clicked() {
+                            ui.close_menu();
+                            set_open(&mut self.open, PANEL_SAVE_OPTIONS, true);
+                        }
                     });
 
                     ui.separator();
@@ -531,6 +1023,84 @@ impl eframe::App for MyApp<'_> {
                             ui.close_menu();
                             self.table.as_mut().unwrap().open_replace_panel(None);
                         }
+                        if ui.button("Computed columns").clicked() {
+                            ui.close_menu();
+                            self.table.as_mut().unwrap().open_computed_columns_panel();
+                        }
+                        if ui.button("Pivot table").clicked() {
+                            ui.close_menu();
+                            self.table.as_mut().unwrap().open_pivot_panel();
+                        }
+                        if ui.button("Conditional formatting").clicked() {
+                            ui.close_menu();
+                            self.table.as_mut().unwrap().open_formatting_panel();
+                        }
+                    });
+
+                    ui.separator();
+                    ui.menu_button("View", |ui| {
+                        ui.set_min_width(220.0);
+                        let table = self.table.as_mut().unwrap();
+                        let mut show_aggregates_footer = table.show_aggregates_footer;
+                        if ui.checkbox(&mut show_aggregates_footer, "Aggregates footer").changed() {
+                            table.set_show_aggregates_footer(show_aggregates_footer);
+                        }
+                        let mut wrap_rows = table.wrap_rows;
+                        if ui.checkbox(&mut wrap_rows, "Wrap text").changed() {
+                            table.set_wrap_rows(wrap_rows);
+                        }
+                        if ui.button("Chart").clicked() {
+                            ui.close_menu();
+                            table.open_chart_panel(None);
+                        }
+                        if ui.button("Columns").clicked() {
+                            ui.close_menu();
+                            table.open_columns_panel();
+                        }
+                        if ui.button("Fit all columns").clicked() {
+                            ui.close_menu();
+                            table.fit_all_columns();
+                        }
+                    });
+
+                    ui.separator();
+                    ui.menu_button("Schema", |ui| {
+                        ui.set_min_width(220.0);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            if ui.button("Attach schema file").clicked() {
+                                ui.close_menu();
+                                if let Some(path) = rfd::FileDialog::new().add_filter("JSON Schema", &["json"]).pick_file() {
+                                    self.attach_schema(path);
+                                }
+                            }
+                            if ui.button("Generate JSON Schema").clicked() {
+                                ui.close_menu();
+                                self.generate_schema();
+                            }
+                        }
+                        let table = self.table.as_mut().unwrap();
+                        if table.schema_source().is_some() {
+                            if ui.button("Detach schema").clicked() {
+                                ui.close_menu();
+                                table.set_schema_validator(None);
+                                #[cfg(not(target_arch = "wasm32"))]
+                                {
+                                    self.schema_path = None;
+                                }
+                                return;
+                            }
+                            ui.separator();
+                            let button = Button::new("Go to next error").shortcut_text(ui.ctx().format_shortcut(&SHORTCUT_NEXT_ERROR));
+                            if ui.add_enabled(!table.schema_errors.is_empty(), button).clicked() {
+                                ui.close_menu();
+                                table.goto_next_schema_error();
+                            }
+                            let mut only_invalid_rows = table.only_invalid_rows;
+                            if ui.checkbox(&mut only_invalid_rows, "Show only invalid rows").changed() {
+                                table.set_only_invalid_rows(only_invalid_rows);
+                            }
+                        }
                     });
                 }
                 if let Some(ref mut table) = self.table {
@@ -567,7 +1137,7 @@ impl eframe::App for MyApp<'_> {
 
                     ui.add(Separator::default().vertical());
 
-                    let (scroll_to_row_mode_response, scroll_to_row_response) = ui.allocate_ui(Vec2::new(410.0, ui.spacing().interact_size.y), |ui| {
+                    let (scroll_to_row_mode_response, scroll_to_row_response) = ui.allocate_ui(Vec2::new(520.0, ui.spacing().interact_size.y), |ui| {
                         ui.horizontal(|ui| {
                             ui.add(Label::new("Scroll to row: ").extend());
                             let scroll_to_row_mode_response = ComboBox::from_id_salt("scroll_mode").selected_text(table.scroll_to_row_mode.as_str()).show_ui(ui, |ui| {
@@ -576,31 +1146,67 @@ impl eframe::App for MyApp<'_> {
                             });
                             let hint_text = match &table.scroll_to_row_mode {
                                 ScrollToRowMode::RowNumber => "Type row number",
-                                ScrollToRowMode::MatchingTerm => "Type term contained in string value"
+                                ScrollToRowMode::MatchingTerm => "Type term contained in a value or key"
                             };
                             let text_edit = TextEdit::singleline(&mut table.scroll_to_row).hint_text(hint_text);
                             let scroll_to_row_response = ui.add(text_edit);
-                            if !table.matching_rows.is_empty() {
+                            if matches!(table.scroll_to_row_mode, ScrollToRowMode::MatchingTerm) {
+                                let mut search_mode = table.search_mode.clone();
+                                for (mode, text, tooltip) in [(ReplaceMode::MatchingCase, "Cc", "Matching case"), (ReplaceMode::ExactWord, "W", "Exact word"), (ReplaceMode::Regex, ".*", "Regex")] {
+                                    let is_active = mem::discriminant(&search_mode) == mem::discriminant(&mode);
+                                    let text = if is_active { RichText::new(text).color(ACTIVE_COLOR) } else { RichText::new(text) };
+                                    if ui.add(Button::new(text)).on_hover_text(tooltip).clicked() {
+                                        search_mode = if is_active { ReplaceMode::Simple } else { mode };
+                                    }
+                                }
+                                if mem::discriminant(&search_mode) != mem::discriminant(&table.search_mode) {
+                                    table.search_mode = search_mode;
+                                    table.search();
+                                }
+                                if let Some(ref search_error) = table.search_error {
+                                    ui.colored_label(Color32::RED, "Invalid regex").on_hover_text(search_error);
+                                }
+                            }
+                            if !table.matching_cells.is_empty() {
                                 let response_prev = icon::button(ui, CHEVRON_UP, Some("Previous occurrence"), None);
                                 let response_next = icon::button(ui, CHEVRON_DOWN, Some("Next occurrence"), None);
-                                ui.label(RichText::new(format!("{}/{}", table.matching_row_selected + 1, table.matching_rows.len())));
+                                ui.label(RichText::new(format!("{}/{}", table.matching_cell_selected + 1, table.matching_cells.len())));
 
                                 if response_prev.clicked() {
-                                    if table.matching_row_selected == 0 {
-                                        table.matching_row_selected = table.matching_rows.len() - 1;
-                                    } else {
-                                        table.matching_row_selected -= 1;
-                                    }
-                                    table.changed_matching_row_selected = true;
+                                    table.previous_matching_cell();
                                 }
                                 if response_next.clicked() {
-                                    Self::goto_next_matching_row_occurrence(table);
+                                    table.next_matching_cell();
                                 }
                             }
                             (scroll_to_row_mode_response, scroll_to_row_response)
                         }).inner
                     }).inner;
 
+                    ui.add(Separator::default().vertical());
+                    ui.allocate_ui(Vec2::new(520.0, ui.spacing().interact_size.y), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(Label::new("Query: ").extend());
+                            let text_edit = TextEdit::singleline(&mut table.query).hint_text("$[?(@.a > 5)] or .[] | select(.a > 5)");
+                            let query_response = ui.add(text_edit);
+                            if query_response.lost_focus() && ctx.input(|i| i.key_pressed(Key::Enter)) {
+                                table.apply_query(false);
+                            }
+                            if icon::button(ui, FILTER, Some("Filter rows matching query"), None).clicked() {
+                                table.apply_query(false);
+                            }
+                            if icon::button(ui, TABLE, Some("Open query result in a sub table"), None).clicked() {
+                                table.apply_query(true);
+                            }
+                            if table.is_query_applied() && icon::button(ui, FILTER_CIRCLE_XMARK, Some("Clear query filter"), Some(ACTIVE_COLOR)).clicked() {
+                                table.clear_query();
+                            }
+                            if let Some(ref query_error) = table.query_error {
+                                ui.colored_label(Color32::RED, query_error);
+                            }
+                        });
+                    });
+
 
                     // interaction handling
                     if scroll_to_column_response.changed() {
@@ -613,7 +1219,7 @@ impl eframe::App for MyApp<'_> {
                         if table.scroll_to_row.is_empty() {
                             table.reset_search();
                         }
-                    } else if scroll_to_row_response.lost_focus() && ctx.input(|i| i.key_pressed(Key::Enter)) && Self::goto_next_matching_row_occurrence(table) {
+                    } else if scroll_to_row_response.lost_focus() && ctx.input(|i| i.key_pressed(Key::Enter)) && table.next_matching_cell() {
                         scroll_to_row_response.request_focus();
                     }
                     if scroll_to_row_mode_response.inner.is_some() && scroll_to_row_mode_response.inner.unwrap() {
@@ -637,8 +1243,7 @@ impl eframe::App for MyApp<'_> {
 
         egui::TopBottomPanel::bottom("bottom-panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                if self.table.is_some() {
-                    let table = self.table.as_ref().unwrap();
+                if let Some(table) = self.table.as_mut() {
                     ui.label(format!("{} rows ", table.nodes.len()));
                     ui.separator();
                     ui.label(format!("{} columns ", table.all_columns().len()));
@@ -670,6 +1275,55 @@ impl eframe::App for MyApp<'_> {
                             ui.ctx().set_cursor_icon(CursorIcon::Help);
                         }
                     }
+                    if let Some(schema_source) = table.schema_source() {
+                        ui.separator();
+                        if table.schema_errors.is_empty() {
+                            ui.label("Valid against schema")
+                        } else {
+                            ui.colored_label(
+                                Color32::RED,
+                                format!("{} invalid rows", table.schema_errors.len()),
+                            )
+                        }
+                        .on_hover_text(schema_source);
+                    }
+                    if !table.selected_rows.is_empty() {
+                        ui.separator();
+                        ui.label(format!("{} selected rows", table.selected_rows.len()));
+                        if ui.button("Clear selection").clicked() {
+                            table.clear_selection();
+                        }
+                    }
+                    if table.is_grouped() {
+                        ui.separator();
+                        ui.label(format!("Grouped by {}", table.group_by.join(", ")));
+                        let mut group_aggregate = table.group_aggregate;
+                        egui::ComboBox::from_id_salt("group_aggregate")
+                            .selected_text(group_aggregate.as_str())
+                            .show_ui(ui, |ui| {
+                                for aggregate in Aggregate::ALL {
+                                    ui.selectable_value(
+                                        &mut group_aggregate,
+                                        aggregate,
+                                        aggregate.as_str(),
+                                    );
+                                }
+                            });
+                        table.group_aggregate = group_aggregate;
+                        if ui.button("Expand all").clicked() {
+                            table.collapse_all_groups(false);
+                        }
+                        if ui.button("Collapse all").clicked() {
+                            table.collapse_all_groups(true);
+                        }
+                        if ui.button("Ungroup").clicked() {
+                            table.clear_group_by();
+                        }
+                    }
+                }
+                if let Some(ref file_error) = self.file_error {
+                    ui.separator();
+                    ui.colored_label(Color32::RED, file_error);
                 }
                 ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                     let about_button = ui.add(Button::new("About").frame(false));
@@ -722,9 +1376,17 @@ impl eframe::App for MyApp<'_> {
                     self.parsing_invalid = false;
                     self.parsing_invalid_pointers.clear();
                     if let Some(bytes) = file.bytes {
-                        self.open_json_content(u8::MAX, bytes.as_ref());
+                        if let Err(e) = self.open_json_content(u8::MAX, bytes.as_ref()) {
+                            self.file_error = Some(format!("Failed to parse dropped file: {}", e));
+                            self.should_parse_again = false;
+                        }
                     } else {
                         self.selected_file = Some(file.path.unwrap());
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            self.zip_member = None;
+                            self.schema_path = None;
+                        }
                     }
                 }
             });
@@ -733,20 +1395,18 @@ impl eframe::App for MyApp<'_> {
                 let response1 = table.ui(ui);
                 if !response1.edited_value.is_empty() {
                     self.unsaved_changes = true;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.unjournaled_changes_since.get_or_insert_with(Instant::now);
                 }
             } else if self.selected_file.is_none() {
                 let max_rect = ui.max_rect();
                 let mut rect = ui.max_rect();
                 rect.min.y = rect.max.y / 2.0 - 20.0;
-                let mut already_interact = false;
-
-                if !already_interact {
-                    let response = ui.interact(max_rect, Id::new("select_file"), Sense::click());
-                    if response.clicked() {
-                        self.file_picker();
-                    }
+                let response = ui.interact(max_rect, Id::new("select_file"), Sense::click());
+                if response.clicked() {
+                    self.file_picker();
                 }
-                ui.allocate_ui_at_rect(rect,
+                ui.allocate_new_ui(UiBuilder::new().max_rect(rect),
                                        |ui| {
                                            ui.vertical_centered(|ui| {
                                                ui.heading("Select or drop a json file");
@@ -754,7 +1414,6 @@ impl eframe::App for MyApp<'_> {
                                                #[cfg(target_arch = "wasm32")] {
                                                    if ui.button("Or load sample json file of 1mb").clicked() {
                                                        self.failed_to_load_sample_json = None;
-                                                       already_interact = true;
                                                        let request = ehttp::Request::get("https://raw.githubusercontent.com/nmeylan/json-table-editor/master/web/skill.json");
                                                        self.force_repaint = true;
                                                        let sender = self.async_events_channel.0.clone();
@@ -775,9 +1434,7 @@ impl eframe::App for MyApp<'_> {
                                                    if let Some(ref failed_to_load_sample_json) = self.failed_to_load_sample_json {
                                                        ui.colored_label(Color32::RED, failed_to_load_sample_json);
                                                    }
-                                                   if ui.hyperlink_to("Sample source available here", "https://raw.githubusercontent.com/nmeylan/json-table-editor/master/web/skill.json").clicked() {
-                                                       already_interact = true;
-                                                   }
+                                                   ui.hyperlink_to("Sample source available here", "https://raw.githubusercontent.com/nmeylan/json-table-editor/master/web/skill.json");
                                                }
                                            });
                                        },
@@ -787,7 +1444,7 @@ impl eframe::App for MyApp<'_> {
                 if self.parsing_invalid {
                     let mut rect = ui.max_rect();
                     rect.min.y = 40.0_f32.max(rect.max.y / 2.0 - (20.0 * self.parsing_invalid_pointers.len() as f32));
-                    ui.allocate_ui_at_rect(rect,
+                    ui.allocate_new_ui(UiBuilder::new().max_rect(rect),
                                            |ui| {
                                                ui.vertical_centered(|ui| {
                                                    let scroll_area = ScrollArea::vertical();
@@ -825,6 +1482,11 @@ impl eframe::App for MyApp<'_> {
                 }
             }
         });
+        if let Some(ref mut table) = self.table {
+            if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_NEXT_ERROR)) {
+                table.goto_next_schema_error();
+            }
+        }
         if self.table.is_some() {
             #[cfg(not(target_arch = "wasm32"))]
             {
@@ -839,8 +1501,22 @@ impl eframe::App for MyApp<'_> {
             }
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        self.autosave(ctx);
+
         if self.force_repaint {
             ctx.request_repaint();
         }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(ref path) = self.selected_file {
+            self.write_workspace();
+            // Keep the journal until the user decided whether to restore it
+            if self.recovery_journal.is_none() {
+                recovery::delete_journal(path);
+            }
+        }
+    }
 }
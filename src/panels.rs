@@ -1,21 +1,46 @@
+use crate::aggregates::Aggregate;
 use crate::array_table::Column;
+use crate::chart::{Chart, ChartKind};
+use crate::components::icon;
 use crate::components::popover::PopupMenu;
+use crate::computed_column::ComputedColumn;
+use crate::formatting::{FormattingCondition, FormattingRule, FormattingTarget};
+use crate::parser::{
+    rename_key, rename_pointer_keys, search_regex, BackupOptions, LineEnding, SaveFormat,
+    SaveOptions,
+};
+use crate::pivot::Pivot;
+use crate::replace_panel::{ReplacePreview, Transform, TransformKind};
 use crate::ACTIVE_COLOR;
 use eframe::egui::Context;
 use eframe::egui::Ui;
 use eframe::emath::Align;
 use eframe::epaint::text::TextWrapMode;
-use egui::{Button, Grid, Layout, RichText, Sense, TextBuffer, TextEdit};
+use egui::{
+    Button, Color32, ComboBox, DragValue, Grid, Layout, RichText, Sense, TextBuffer, TextEdit,
+};
 use json_flat_parser::ValueType;
 use std::borrow::Cow;
 use std::cell::RefCell;
 
 pub const PANEL_ABOUT: &str = "About";
+pub const PANEL_CHART: &str = "Chart";
+pub const PANEL_COLUMNS: &str = "Columns";
+pub const PANEL_COMPUTED_COLUMNS: &str = "Computed columns";
+pub const PANEL_FORMATTING: &str = "Conditional formatting";
+pub const PANEL_PIVOT: &str = "Pivot table";
 pub const PANEL_REPLACE: &str = "Replace";
+pub const PANEL_SAVE_OPTIONS: &str = "Save options";
 
 #[derive(Default)]
 pub struct AboutPanel {}
 
+#[derive(Default)]
+pub struct SaveOptionsPanel {
+    pub save_options: SaveOptions,
+    pub backup_options: BackupOptions,
+}
+
 #[derive(Default)]
 pub struct SearchReplacePanel<'array> {
     search_criteria: String,
@@ -24,8 +49,128 @@ pub struct SearchReplacePanel<'array> {
     columns: Vec<Column<'array>>,
     replace_mode: ReplaceMode,
     title: Option<String>,
+    scope: ReplaceScope,
+    // Number of selected rows of the table, to replace only in them
+    selected_rows_count: usize,
+    // Search criteria, mode, scope and column ids the pattern was last checked for
+    checked_inputs: Option<(String, ReplaceMode, ReplaceScope, Vec<usize>)>,
+    pub pattern_error: Option<String>,
+    // Number of values matching search criteria, computed by the table
+    pub match_count: Option<usize>,
+    edit_mode: EditMode,
+    transform_kind: TransformKind,
+    transform_operand: String,
+    transform_operand2: String,
+    transform_error: Option<String>,
+    // Number of rows whose keys were renamed last time
+    pub renamed_rows_count: Option<usize>,
+    // Occurrences to confirm before replacing
+    pub preview: Option<ReplacePreview>,
+}
+#[derive(Default)]
+pub struct ComputedColumnsPanel {
+    name: String,
+    expression: String,
+    pub error: Option<String>,
+    // Name and expression of existing computed columns
+    computed_columns: Vec<(String, String)>,
+}
+
+pub enum ComputedColumnsResponse {
+    Add { name: String, expression: String },
+    Remove(String),
+    Materialize(String),
 }
-#[derive(Clone)]
+
+#[derive(Default)]
+pub struct PivotPanel {
+    columns: Vec<String>,
+    row_column: String,
+    column_column: String,
+    value_column: String,
+    aggregate: Aggregate,
+    pub pivot: Option<Pivot>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ChartPanel {
+    // Name of columns and whether they are numeric
+    columns: Vec<(String, bool)>,
+    kind: ChartKind,
+    column: String,
+    y_column: String,
+    pub chart: Option<Chart>,
+}
+
+pub enum ChartResponse {
+    Plot {
+        kind: ChartKind,
+        column: String,
+        y_column: Option<String>,
+    },
+    // Filter rows on values of clicked bar or bin
+    Filter {
+        column: String,
+        values: Vec<String>,
+    },
+}
+
+pub struct FormattingPanel {
+    columns: Vec<String>,
+    column: String,
+    condition: FormattingCondition,
+    value: String,
+    target: FormattingTarget,
+    color: Color32,
+    pub error: Option<String>,
+    // Description and color of existing rules
+    rules: Vec<(String, Color32)>,
+}
+
+impl Default for FormattingPanel {
+    fn default() -> Self {
+        Self {
+            columns: vec![],
+            column: String::new(),
+            condition: FormattingCondition::Equals,
+            value: String::new(),
+            target: FormattingTarget::default(),
+            color: Color32::RED,
+            error: None,
+            rules: vec![],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ColumnsPanel {
+    // Name and visibility of columns, in table order
+    columns: Vec<(String, bool)>,
+    search: String,
+}
+
+pub enum ColumnsResponse {
+    // Name and visibility of columns, in new table order
+    Layout(Vec<(String, bool)>),
+    // Default order, all columns visible
+    Reset,
+}
+
+pub enum FormattingResponse {
+    Add(FormattingRule),
+    // Index of removed rule
+    Remove(usize),
+}
+
+pub struct PivotResponse {
+    pub row_column: String,
+    pub column_column: String,
+    pub value_column: String,
+    pub aggregate: Aggregate,
+}
+
+#[derive(Clone, PartialEq)]
 #[derive(Default)]
 pub enum ReplaceMode {
     #[default]
@@ -35,11 +180,60 @@ pub enum ReplaceMode {
     MatchingCase,
 }
 
+/// What the replace panel edits.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum EditMode {
+    // Replace text in values
+    #[default]
+    Values,
+    // Transform values according to their type
+    Transform,
+    // Rename keys at any depth
+    Keys,
+}
+
+/// Rows in which values are replaced or transformed.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum ReplaceScope {
+    #[default]
+    All,
+    Filtered,
+    Selected,
+}
+
+impl ReplaceScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplaceScope::All => "All rows",
+            ReplaceScope::Filtered => "Filtered rows",
+            ReplaceScope::Selected => "Selected rows",
+        }
+    }
+}
+
+pub enum SearchReplaceAction<'array> {
+    // Compute occurrences to preview
+    Preview(SearchReplaceResponse<'array>),
+    // Count values matching search criteria while typing
+    CountMatches(SearchReplaceResponse<'array>),
+    // Rename keys matching search criteria
+    RenameKeys(SearchReplaceResponse<'array>),
+    // Compute transformed values to preview
+    Transform {
+        columns: Vec<Column<'array>>,
+        transform: Transform,
+        scope: ReplaceScope,
+    },
+    // Replace ticked occurrences of the preview
+    Apply,
+}
+
 pub struct SearchReplaceResponse<'array> {
     pub search_criteria: String,
     pub replace_value: Option<String>,
     pub selected_column: Option<Vec<Column<'array>>>,
     pub replace_mode: ReplaceMode,
+    pub scope: ReplaceScope,
 }
 
 impl super::Window<()> for AboutPanel {
@@ -85,29 +279,486 @@ impl super::View<()> for AboutPanel {
     }
 }
 
+impl super::Window<()> for SaveOptionsPanel {
+    fn name(&self) -> &'static str {
+        PANEL_SAVE_OPTIONS
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) {
+        egui::Window::new(self.name())
+            .collapsible(false)
+            .open(open)
+            .resizable([true, true])
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui);
+            });
+    }
+}
+
+impl super::View<()> for SaveOptionsPanel {
+    fn ui(&mut self, ui: &mut Ui) {
+        let save_options = &mut self.save_options;
+        #[cfg(not(target_arch = "wasm32"))]
+        let backup_options = &mut self.backup_options;
+        Grid::new("save_options_panel:grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Format: ");
+                ComboBox::from_id_salt("save_options_format")
+                    .selected_text(save_options.format.as_str())
+                    .show_ui(ui, |ui| {
+                        for format in [
+                            SaveFormat::Unchanged,
+                            SaveFormat::Compact,
+                            SaveFormat::RowPerLine,
+                            SaveFormat::Pretty,
+                        ] {
+                            ui.selectable_value(&mut save_options.format, format, format.as_str());
+                        }
+                    });
+                ui.end_row();
+                if matches!(save_options.format, SaveFormat::Pretty) {
+                    ui.label("Indentation: ");
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            !save_options.indent_with_tabs,
+                            DragValue::new(&mut save_options.indent_size).range(0..=8),
+                        );
+                        ui.checkbox(&mut save_options.indent_with_tabs, "Use tabs");
+                    });
+                    ui.end_row();
+                }
+                ui.label("Line ending: ");
+                ComboBox::from_id_salt("save_options_line_ending")
+                    .selected_text(save_options.line_ending.label())
+                    .show_ui(ui, |ui| {
+                        for line_ending in [LineEnding::Lf, LineEnding::CrLf] {
+                            ui.selectable_value(
+                                &mut save_options.line_ending,
+                                line_ending,
+                                line_ending.label(),
+                            );
+                        }
+                    });
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(&mut save_options.trailing_new_line, "Trailing new line");
+                ui.end_row();
+                ui.label("");
+                ui.checkbox(&mut save_options.sort_keys, "Sort keys");
+                ui.end_row();
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    ui.label("");
+                    ui.checkbox(
+                        &mut backup_options.keep_backup,
+                        "Keep .bak of previous version",
+                    );
+                    ui.end_row();
+                    ui.label("Autosave: ");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut backup_options.autosave, "every");
+                        ui.add_enabled(
+                            backup_options.autosave,
+                            DragValue::new(&mut backup_options.autosave_interval_minutes)
+                                .range(1..=60)
+                                .suffix(" min"),
+                        );
+                    });
+                    ui.end_row();
+                }
+            });
+    }
+}
+
 impl<'array> SearchReplacePanel<'array> {
     pub fn set_columns(&mut self, columns: Vec<Column<'array>>) {
         self.columns = columns;
+        // Occurrences may point to values which are not displayed anymore
+        self.preview = None;
+        self.recount_matches();
+    }
+
+    /// Values changed, match count is computed again.
+    pub fn recount_matches(&mut self) {
+        self.checked_inputs = None;
+    }
+
+    fn response(&self, replace_value: Option<String>) -> SearchReplaceResponse<'array> {
+        SearchReplaceResponse {
+            search_criteria: self.search_criteria.clone(),
+            replace_value,
+            replace_mode: self.replace_mode.clone(),
+            selected_column: Some(self.selected_columns.borrow().clone()),
+            scope: self.scope,
+        }
+    }
+
+    /// Compile pattern when search inputs changed, and ask for the count of matching values when
+    /// it is valid.
+    fn check_pattern(&mut self) -> Option<SearchReplaceAction<'array>> {
+        let column_ids = self
+            .selected_columns
+            .borrow()
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<usize>>();
+        let inputs = (
+            self.search_criteria.clone(),
+            self.replace_mode.clone(),
+            self.scope,
+            column_ids,
+        );
+        if self.checked_inputs.as_ref() == Some(&inputs) {
+            return None;
+        }
+        self.match_count = None;
+        self.pattern_error = search_regex(&self.search_criteria, &self.replace_mode).err();
+        let count = self.pattern_error.is_none()
+            && !self.search_criteria.is_empty()
+            && !inputs.3.is_empty();
+        self.checked_inputs = Some(inputs);
+        count.then(|| SearchReplaceAction::CountMatches(self.response(None)))
     }
 
     pub fn set_title(&mut self, title: String) {
         self.title = Some(title);
     }
+    pub fn set_selected_rows_count(&mut self, selected_rows_count: usize) {
+        if self.selected_rows_count != selected_rows_count
+            && matches!(self.scope, ReplaceScope::Selected)
+        {
+            self.recount_matches();
+        }
+        self.selected_rows_count = selected_rows_count;
+        if selected_rows_count == 0 && matches!(self.scope, ReplaceScope::Selected) {
+            self.scope = ReplaceScope::All;
+        }
+    }
+
     pub fn set_select_column(&mut self, selected_column: Column<'array>) {
         *self.selected_columns.borrow_mut() = vec![selected_column];
     }
 
+    fn columns_menu(&self, ui: &mut Ui) {
+        PopupMenu::new("select_column_to_replace").show_ui(
+            ui,
+            |ui| {
+                let button_label = if self.selected_columns.borrow().is_empty() {
+                    "No column selected".to_lowercase()
+                } else if self.selected_columns.borrow().len() > 5 {
+                    format!("{} columns selected", self.selected_columns.borrow().len())
+                } else {
+                    self.selected_columns
+                        .borrow()
+                        .iter()
+                        .map(|c| c.name.clone())
+                        .collect::<Vec<Cow<'array, str>>>()
+                        .join(", ")
+                };
+                let response = ui.add(Button::new(button_label));
+                response.on_hover_ui(|ui| {
+                    // ui.set_min_width(140.0);
+                    ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                    self.selected_columns.borrow().iter().for_each(|c| {
+                        ui.label(c.name.as_str());
+                    })
+                })
+            },
+            |ui| {
+                for col in self.columns.iter().filter(|c| Self::can_be_replaced(c)) {
+                    if col.name.is_empty() {
+                        continue;
+                    }
+                    let mut chcked = false;
+
+                    for selected in self.selected_columns.borrow().iter() {
+                        if selected.eq(col) {
+                            chcked = true;
+                            break;
+                        }
+                    }
+                    if ui.checkbox(&mut chcked, col.name.as_str()).clicked() {
+                        if self.selected_columns.borrow().contains(col) {
+                            self.selected_columns.borrow_mut().retain(|c| !c.eq(col));
+                        } else {
+                            self.selected_columns.borrow_mut().push(col.clone());
+                        }
+                    }
+                }
+            },
+        );
+    }
+
+    fn replace_ui(&mut self, ui: &mut Ui) -> Option<SearchReplaceAction<'array>> {
+        let is_replace_value_empty = self.replace_value.is_empty();
+        let mut button_set_to_null = Button::new("Set to null").sense(Sense::hover());
+        let mut button = Button::new("Preview");
+        let grid_response = Grid::new("replace_panel:grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Column: ");
+                self.columns_menu(ui);
+
+                ui.end_row();
+                ui.label("Search: ");
+                ui.add(TextEdit::singleline(&mut self.search_criteria));
+                ui.end_row();
+                ui.label("Replace: ");
+                ui.add(TextEdit::singleline(&mut self.replace_value));
+                ui.end_row();
+
+                ui.label("");
+                let replace_response = ui
+                    .with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        if self.selected_columns.borrow().is_empty() || self.pattern_error.is_some()
+                        {
+                            button = button.sense(Sense::hover());
+                        }
+                        if is_replace_value_empty && self.pattern_error.is_none() {
+                            button_set_to_null = button_set_to_null.sense(Sense::click());
+                        }
+                        let response_button_replace_with_null = ui.add(button_set_to_null);
+                        let response_button_replace = ui.add(button);
+                        self.replace_mode_buttons(ui);
+                        (response_button_replace, response_button_replace_with_null)
+                    })
+                    .inner;
+                ui.end_row();
+
+                replace_response
+            });
+        if let Some(ref error) = self.pattern_error {
+            ui.colored_label(Color32::RED, error);
+        } else if let Some(match_count) = self.match_count {
+            ui.label(format!("{} matching values", match_count));
+        }
+        if grid_response.inner.0.clicked() {
+            return Some(SearchReplaceAction::Preview(
+                self.response(Some(self.replace_value.clone())),
+            ));
+        } else if grid_response.inner.1.clicked() {
+            return Some(SearchReplaceAction::Preview(self.response(None)));
+        }
+        self.check_pattern()
+    }
+
+    /// Toggle buttons of replace mode, laid out right to left.
+    fn replace_mode_buttons(&mut self, ui: &mut Ui) {
+        let mut replace_match_case_text = RichText::new("Cc");
+        let mut replace_exact_word_text = RichText::new("W");
+        let mut replace_regex_text = RichText::new(".*");
+        if matches!(self.replace_mode, ReplaceMode::MatchingCase) {
+            replace_match_case_text = replace_match_case_text.color(ACTIVE_COLOR);
+        }
+        if matches!(self.replace_mode, ReplaceMode::ExactWord) {
+            replace_exact_word_text = replace_exact_word_text.color(ACTIVE_COLOR);
+        }
+        if matches!(self.replace_mode, ReplaceMode::Regex) {
+            replace_regex_text = replace_regex_text.color(ACTIVE_COLOR);
+        }
+        let replace_regex_mode = Button::new(replace_regex_text);
+        let replace_exact_word_mode = Button::new(replace_exact_word_text);
+        let replace_match_case_mode = Button::new(replace_match_case_text);
+        let mut response_replace_regex_mode = ui.add(replace_regex_mode);
+        response_replace_regex_mode = response_replace_regex_mode.on_hover_ui(|ui| {
+            ui.label("Regex");
+        });
+        if response_replace_regex_mode.clicked() {
+            if matches!(self.replace_mode, ReplaceMode::Regex) {
+                self.replace_mode = ReplaceMode::Simple;
+            } else {
+                self.replace_mode = ReplaceMode::Regex;
+            }
+        }
+        let mut response_replace_exact_word_mode = ui.add(replace_exact_word_mode);
+        response_replace_exact_word_mode = response_replace_exact_word_mode.on_hover_ui(|ui| {
+            ui.label("Exact word");
+        });
+        if response_replace_exact_word_mode.clicked() {
+            if matches!(self.replace_mode, ReplaceMode::ExactWord) {
+                self.replace_mode = ReplaceMode::Simple;
+            } else {
+                self.replace_mode = ReplaceMode::ExactWord;
+            }
+        }
+        let mut response_replace_match_case_mode = ui.add(replace_match_case_mode);
+        response_replace_match_case_mode = response_replace_match_case_mode.on_hover_ui(|ui| {
+            ui.label("Matching case");
+        });
+        if response_replace_match_case_mode.clicked() {
+            if matches!(self.replace_mode, ReplaceMode::MatchingCase) {
+                self.replace_mode = ReplaceMode::Simple;
+            } else {
+                self.replace_mode = ReplaceMode::MatchingCase;
+            }
+        }
+    }
+
+    fn rename_keys_ui(&mut self, ui: &mut Ui) -> Option<SearchReplaceAction<'array>> {
+        let mut rename_clicked = false;
+        Grid::new("replace_panel:rename_keys_grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Search key: ");
+                ui.add(TextEdit::singleline(&mut self.search_criteria).hint_text("damageflags"));
+                ui.end_row();
+                ui.label("Rename to: ");
+                ui.add(TextEdit::singleline(&mut self.replace_value).hint_text("damageFlags"));
+                ui.end_row();
+                ui.label("");
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let enabled = !self.search_criteria.is_empty() && self.pattern_error.is_none();
+                    rename_clicked = ui
+                        .add_enabled(enabled, Button::new("Rename keys"))
+                        .on_hover_text("Keys of nested objects are renamed as well")
+                        .clicked();
+                    self.replace_mode_buttons(ui);
+                });
+                ui.end_row();
+            });
+        let regex = search_regex(&self.search_criteria, &self.replace_mode);
+        self.pattern_error = regex.as_ref().err().cloned();
+        match regex {
+            Err(ref error) => {
+                ui.colored_label(Color32::RED, error);
+            }
+            Ok(ref regex) if !self.search_criteria.is_empty() => {
+                let renamed_columns = self
+                    .columns
+                    .iter()
+                    .filter_map(|c| {
+                        let new_name = rename_pointer_keys(&c.name, &|key| {
+                            rename_key(key, regex, &self.replace_value, &self.replace_mode)
+                        })?;
+                        Some((c.name.to_string(), new_name))
+                    })
+                    .collect::<Vec<(String, String)>>();
+                ui.label(format!("{} columns renamed", renamed_columns.len()));
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for (name, new_name) in renamed_columns {
+                            ui.label(format!("{} → {}", name, new_name));
+                        }
+                    });
+            }
+            Ok(_) => {}
+        }
+        if let Some(renamed_rows_count) = self.renamed_rows_count {
+            ui.label(format!("Keys renamed in {} rows", renamed_rows_count));
+        }
+        rename_clicked.then(|| {
+            SearchReplaceAction::RenameKeys(self.response(Some(self.replace_value.clone())))
+        })
+    }
+
+    fn transform_ui(&mut self, ui: &mut Ui) -> Option<SearchReplaceAction<'array>> {
+        let mut preview_clicked = false;
+        Grid::new("replace_panel:transform_grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Column: ");
+                self.columns_menu(ui);
+                ui.end_row();
+
+                ui.label("Transform: ");
+                ComboBox::from_id_salt("replace_panel:transform_kind")
+                    .selected_text(self.transform_kind.as_str())
+                    .show_ui(ui, |ui| {
+                        for kind in TransformKind::ALL {
+                            ui.selectable_value(&mut self.transform_kind, kind, kind.as_str());
+                        }
+                    });
+                ui.end_row();
+
+                match self.transform_kind {
+                    TransformKind::Multiply | TransformKind::Add => {
+                        ui.label("n: ");
+                        ui.add(TextEdit::singleline(&mut self.transform_operand).hint_text("1.1"));
+                        ui.end_row();
+                    }
+                    TransformKind::Round => {
+                        ui.label("Decimals: ");
+                        ui.add(TextEdit::singleline(&mut self.transform_operand).hint_text("0"));
+                        ui.end_row();
+                    }
+                    TransformKind::Clamp => {
+                        ui.label("Min: ");
+                        ui.add(TextEdit::singleline(&mut self.transform_operand));
+                        ui.end_row();
+                        ui.label("Max: ");
+                        ui.add(TextEdit::singleline(&mut self.transform_operand2));
+                        ui.end_row();
+                    }
+                    TransformKind::Toggle => {}
+                    TransformKind::Set => {
+                        ui.label("Value: ");
+                        ui.add(
+                            TextEdit::singleline(&mut self.transform_operand)
+                                .hint_text("42, true, null or text"),
+                        );
+                        ui.end_row();
+                    }
+                }
+
+                ui.label("");
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let enabled = !self.selected_columns.borrow().is_empty();
+                    preview_clicked = ui
+                        .add_enabled(enabled, Button::new("Preview"))
+                        .on_hover_text("Preview transformed values")
+                        .clicked();
+                });
+                ui.end_row();
+            });
+        if let Some(ref error) = self.transform_error {
+            ui.colored_label(Color32::RED, error);
+        }
+        if !preview_clicked {
+            return None;
+        }
+        match Transform::new(
+            self.transform_kind,
+            &self.transform_operand,
+            &self.transform_operand2,
+        ) {
+            Ok(transform) => {
+                self.transform_error = None;
+                Some(SearchReplaceAction::Transform {
+                    columns: self.selected_columns.borrow().clone(),
+                    transform,
+                    scope: self.scope,
+                })
+            }
+            Err(error) => {
+                self.transform_error = Some(error);
+                None
+            }
+        }
+    }
+
     pub fn can_be_replaced(c: &Column<'array>) -> bool {
         !(matches!(c.value_type, ValueType::Array(_))
             || matches!(c.value_type, ValueType::Object(..)))
     }
 }
-impl<'array> super::Window<Option<SearchReplaceResponse<'array>>> for SearchReplacePanel<'array> {
+impl<'array> super::Window<Option<SearchReplaceAction<'array>>> for SearchReplacePanel<'array> {
     fn name(&self) -> &'static str {
         PANEL_REPLACE
     }
 
-    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<SearchReplaceResponse<'array>> {
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<SearchReplaceAction<'array>> {
         let window_title = if let Some(ref title) = self.title {
             title.as_str()
         } else {
@@ -117,7 +768,7 @@ impl<'array> super::Window<Option<SearchReplaceResponse<'array>>> for SearchRepl
             .collapsible(true)
             .open(open)
             .resizable([true, true])
-            .default_width(280.0)
+            .default_width(480.0)
             .show(ctx, |ui| {
                 use super::View as _;
                 self.ui(ui)
@@ -132,160 +783,727 @@ impl<'array> super::Window<Option<SearchReplaceResponse<'array>>> for SearchRepl
     }
 }
 
-impl<'array> super::View<Option<SearchReplaceResponse<'array>>> for SearchReplacePanel<'array> {
-    fn ui(&mut self, ui: &mut Ui) -> Option<SearchReplaceResponse<'array>> {
-        let is_replace_value_empty = self.replace_value.is_empty();
-        let search = TextEdit::singleline(&mut self.search_criteria);
-        let replace = TextEdit::singleline(&mut self.replace_value);
-        let mut button_set_to_null = Button::new("Set to null").sense(Sense::hover());
-        let mut button = Button::new("Replace all");
-        let grid_response = Grid::new("replace_panel:grid")
+impl<'array> super::View<Option<SearchReplaceAction<'array>>> for SearchReplacePanel<'array> {
+    fn ui(&mut self, ui: &mut Ui) -> Option<SearchReplaceAction<'array>> {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.edit_mode, EditMode::Values, "Replace text");
+            ui.selectable_value(&mut self.edit_mode, EditMode::Transform, "Transform");
+            ui.selectable_value(&mut self.edit_mode, EditMode::Keys, "Rename keys");
+        });
+        ui.horizontal(|ui| {
+            ui.label("In: ");
+            ui.radio_value(
+                &mut self.scope,
+                ReplaceScope::All,
+                ReplaceScope::All.as_str(),
+            );
+            ui.radio_value(
+                &mut self.scope,
+                ReplaceScope::Filtered,
+                ReplaceScope::Filtered.as_str(),
+            );
+            ui.add_enabled_ui(self.selected_rows_count > 0, |ui| {
+                ui.radio_value(
+                    &mut self.scope,
+                    ReplaceScope::Selected,
+                    format!(
+                        "{} ({})",
+                        ReplaceScope::Selected.as_str(),
+                        self.selected_rows_count
+                    ),
+                )
+                .on_disabled_hover_text("Ctrl or shift click rows to select them");
+            });
+        });
+        let action = match self.edit_mode {
+            EditMode::Values => self.replace_ui(ui),
+            EditMode::Transform => self.transform_ui(ui),
+            EditMode::Keys => return self.rename_keys_ui(ui),
+        };
+        if action.is_some() {
+            return action;
+        }
+        if let Some(ref mut preview) = self.preview {
+            ui.separator();
+            if preview.occurrences.is_empty() {
+                ui.label("No value to replace");
+            } else {
+                preview.ui(ui);
+            }
+            let confirmed_count = preview.confirmed_count();
+            let mut apply = false;
+            ui.horizontal(|ui| {
+                let apply_button = Button::new(format!("Replace {} occurrences", confirmed_count));
+                apply = ui.add_enabled(confirmed_count > 0, apply_button).clicked();
+                if ui.button("Cancel").clicked() {
+                    self.preview = None;
+                }
+            });
+            if apply {
+                return Some(SearchReplaceAction::Apply);
+            }
+        }
+        None
+        // return grid_response.inner
+    }
+}
+
+impl ComputedColumnsPanel {
+    pub fn set_computed_columns(&mut self, computed_columns: &[ComputedColumn]) {
+        self.computed_columns = computed_columns
+            .iter()
+            .map(|c| (c.name.clone(), c.expression_source.clone()))
+            .collect();
+    }
+
+    pub fn clear_form(&mut self) {
+        self.name.clear();
+        self.expression.clear();
+    }
+}
+
+impl super::Window<Option<ComputedColumnsResponse>> for ComputedColumnsPanel {
+    fn name(&self) -> &'static str {
+        PANEL_COMPUTED_COLUMNS
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<ComputedColumnsResponse> {
+        egui::Window::new(self.name())
+            .collapsible(true)
+            .open(open)
+            .resizable([true, true])
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui)
+            })
+            .and_then(|inner_response| inner_response.inner)
+            .flatten()
+    }
+}
+
+impl super::View<Option<ComputedColumnsResponse>> for ComputedColumnsPanel {
+    fn ui(&mut self, ui: &mut Ui) -> Option<ComputedColumnsResponse> {
+        let mut response = None;
+        if !self.computed_columns.is_empty() {
+            Grid::new("computed_columns_panel:list")
+                .num_columns(3)
+                .spacing([12.0, 8.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    for (name, expression) in self.computed_columns.iter() {
+                        ui.label(name.as_str());
+                        ui.label(RichText::new(expression.as_str()).monospace());
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Materialize")
+                                .on_hover_text("Write computed value as a key in every row")
+                                .clicked()
+                            {
+                                response = Some(ComputedColumnsResponse::Materialize(name.clone()));
+                            }
+                            if ui.button("Remove").clicked() {
+                                response = Some(ComputedColumnsResponse::Remove(name.clone()));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+        }
+        Grid::new("computed_columns_panel:grid")
             .num_columns(2)
             .spacing([12.0, 8.0])
             .striped(false)
             .show(ui, |ui| {
-                ui.label("Column: ");
-                PopupMenu::new("select_column_to_replace").show_ui(
-                    ui,
-                    |ui| {
-                        let button_label = if self.selected_columns.borrow().is_empty() {
-                            "No column selected".to_lowercase()
-                        } else if self.selected_columns.borrow().len() > 5 {
-                            format!("{} columns selected", self.selected_columns.borrow().len())
-                        } else {
-                            self.selected_columns
-                                .borrow()
-                                .iter()
-                                .map(|c| c.name.clone())
-                                .collect::<Vec<Cow<'array, str>>>()
-                                .join(", ")
-                        };
-                        let response = ui.add(Button::new(button_label));
-                        response.on_hover_ui(|ui| {
-                            // ui.set_min_width(140.0);
-                            ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
-                            self.selected_columns.borrow().iter().for_each(|c| {
-                                ui.label(c.name.as_str());
-                            })
-                        })
-                    },
-                    |ui| {
-                        for col in self.columns.iter().filter(|c| Self::can_be_replaced(c)) {
-                            if col.name.is_empty() {
-                                continue;
-                            }
-                            let mut chcked = false;
-
-                            for selected in self.selected_columns.borrow().iter() {
-                                if selected.eq(col) {
-                                    chcked = true;
-                                    break;
-                                }
-                            }
-                            if ui.checkbox(&mut chcked, col.name.as_str()).clicked() {
-                                if self.selected_columns.borrow().contains(col) {
-                                    self.selected_columns.borrow_mut().retain(|c| !c.eq(col));
-                                } else {
-                                    self.selected_columns.borrow_mut().push(col.clone());
-                                }
-                            }
-                        }
-                    },
+                ui.label("Name: ");
+                ui.add(TextEdit::singleline(&mut self.name).hint_text("/doubleLevel"));
+                ui.end_row();
+                ui.label("Expression: ");
+                let expression_response = ui.add(
+                    TextEdit::singleline(&mut self.expression)
+                        .hint_text("/maxLevel * 2")
+                        .font(egui::TextStyle::Monospace),
                 );
+                ui.end_row();
+                ui.label("");
+                let can_add = !self.name.trim().is_empty() && !self.expression.trim().is_empty();
+                let add_response = ui.add_enabled(can_add, Button::new("Add column"));
+                if can_add
+                    && (add_response.clicked()
+                        || (expression_response.lost_focus()
+                            && ui.input(|i| i.key_pressed(egui::Key::Enter))))
+                {
+                    response = Some(ComputedColumnsResponse::Add {
+                        name: self.name.clone(),
+                        expression: self.expression.clone(),
+                    });
+                }
+                ui.end_row();
+            });
+        if let Some(ref error) = self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        ui.label(
+            RichText::new(
+                "Columns are referenced by pointer, e.g. concat(/name, \"-\", /id), len(/description). \
+                Functions: concat, len, upper, lower, trim, round, floor, ceil, abs, min, max, if, coalesce, number, string.",
+            )
+            .small()
+            .weak(),
+        );
+        response
+    }
+}
+
+impl PivotPanel {
+    pub fn set_columns(&mut self, columns: &[Column]) {
+        self.columns = columns
+            .iter()
+            .filter(|c| !c.name.is_empty())
+            .map(|c| c.name.to_string())
+            .collect();
+    }
+
+    fn column_combo_box(ui: &mut Ui, id: &str, columns: &[String], selected: &mut String) {
+        ComboBox::from_id_salt(id)
+            .width(200.0)
+            .selected_text(selected.as_str())
+            .show_ui(ui, |ui| {
+                for column in columns.iter() {
+                    ui.selectable_value(selected, column.clone(), column.as_str());
+                }
+            });
+    }
 
+    fn pivot_table_ui(ui: &mut Ui, pivot: &Pivot) {
+        use crate::components::table::{Column, TableBuilder};
+        let text_height = egui::TextStyle::Body
+            .resolve(ui.style())
+            .size
+            .max(ui.spacing().interact_size.y);
+        let mut table = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(Align::Center))
+            .min_scrolled_height(0.0)
+            .max_scroll_height(400.0);
+        table = table.column(Column::auto().clip(true).resizable(true));
+        for _ in pivot.column_keys.iter() {
+            table = table.column(Column::auto().clip(true).resizable(true));
+        }
+        table
+            .header(text_height * 1.5, |mut header| {
+                header.col(|ui, _| Some(ui.strong(pivot.row_column.as_str())));
+                for column_label in pivot.column_labels.iter() {
+                    header.col(|ui, _| Some(ui.strong(column_label.as_str())));
+                }
+            })
+            .body(None, None, None, |body| {
+                body.rows(text_height, pivot.row_keys.len(), |mut row| {
+                    let row_index = row.index();
+                    row.col(|ui, _| Some(ui.strong(pivot.row_labels[row_index].as_str())));
+                    for column_index in 0..pivot.column_keys.len() {
+                        row.col(|ui, _| {
+                            Some(ui.label(pivot.formatted_value(row_index, column_index)))
+                        });
+                    }
+                });
+            });
+    }
+}
+
+impl super::Window<Option<PivotResponse>> for PivotPanel {
+    fn name(&self) -> &'static str {
+        PANEL_PIVOT
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<PivotResponse> {
+        egui::Window::new(self.name())
+            .collapsible(true)
+            .open(open)
+            .resizable([true, true])
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui)
+            })
+            .and_then(|inner_response| inner_response.inner)
+            .flatten()
+    }
+}
+
+impl super::View<Option<PivotResponse>> for PivotPanel {
+    fn ui(&mut self, ui: &mut Ui) -> Option<PivotResponse> {
+        let mut response = None;
+        Grid::new("pivot_panel:grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Rows: ");
+                Self::column_combo_box(ui, "pivot_row_column", &self.columns, &mut self.row_column);
                 ui.end_row();
-                ui.label("Search: ");
-                ui.add(search);
+                ui.label("Columns: ");
+                Self::column_combo_box(
+                    ui,
+                    "pivot_column_column",
+                    &self.columns,
+                    &mut self.column_column,
+                );
                 ui.end_row();
-                ui.label("Replace: ");
-                ui.add(replace);
+                ui.label("Values: ");
+                ui.horizontal(|ui| {
+                    ComboBox::from_id_salt("pivot_aggregate")
+                        .selected_text(self.aggregate.as_str())
+                        .show_ui(ui, |ui| {
+                            for aggregate in Aggregate::ALL {
+                                ui.selectable_value(
+                                    &mut self.aggregate,
+                                    aggregate,
+                                    aggregate.as_str(),
+                                );
+                            }
+                        });
+                    Self::column_combo_box(
+                        ui,
+                        "pivot_value_column",
+                        &self.columns,
+                        &mut self.value_column,
+                    );
+                });
                 ui.end_row();
-
                 ui.label("");
-                let mut replace_match_case_text = RichText::new("Cc");
-                let mut replace_exact_word_text = RichText::new("W");
-                let mut replace_regex_text = RichText::new(".*");
-                if matches!(self.replace_mode, ReplaceMode::MatchingCase) {
-                    replace_match_case_text = replace_match_case_text.color(ACTIVE_COLOR);
+                let can_compute = !self.row_column.is_empty()
+                    && !self.column_column.is_empty()
+                    && !self.value_column.is_empty();
+                if ui
+                    .add_enabled(can_compute, Button::new("Compute"))
+                    .on_hover_text("Pivot rows matching current filters")
+                    .clicked()
+                {
+                    response = Some(PivotResponse {
+                        row_column: self.row_column.clone(),
+                        column_column: self.column_column.clone(),
+                        value_column: self.value_column.clone(),
+                        aggregate: self.aggregate,
+                    });
                 }
-                if matches!(self.replace_mode, ReplaceMode::ExactWord) {
-                    replace_exact_word_text = replace_exact_word_text.color(ACTIVE_COLOR);
+                ui.end_row();
+            });
+        if let Some(ref pivot) = self.pivot {
+            ui.separator();
+            ui.label(
+                RichText::new(format!(
+                    "{} of {} by {} and {}",
+                    pivot.aggregate.as_str(),
+                    pivot.value_column,
+                    pivot.row_column,
+                    pivot.column_column
+                ))
+                .weak(),
+            );
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui| {
+                if ui.button("Export CSV").clicked() {
+                    self.error = pivot.export(crate::pivot::PivotExportFormat::Csv).err();
                 }
-                if matches!(self.replace_mode, ReplaceMode::Regex) {
-                    replace_regex_text = replace_regex_text.color(ACTIVE_COLOR);
+                if ui.button("Export JSON").clicked() {
+                    self.error = pivot.export(crate::pivot::PivotExportFormat::Json).err();
                 }
-                let replace_regex_mode = Button::new(replace_regex_text);
-                let replace_exact_word_mode = Button::new(replace_exact_word_text);
-                let replace_match_case_mode = Button::new(replace_match_case_text);
-                let replace_response = ui
-                    .with_layout(Layout::right_to_left(Align::Center), |ui| {
-                        if self.selected_columns.borrow().len() == 0 {
-                            button = button.sense(Sense::hover());
-                        }
-                        if is_replace_value_empty {
-                            button_set_to_null = button_set_to_null.sense(Sense::click());
+            });
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.push_id("pivot_table", |ui| Self::pivot_table_ui(ui, pivot));
+            });
+        }
+        if let Some(ref error) = self.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        response
+    }
+}
+
+impl ChartPanel {
+    pub fn set_columns(&mut self, columns: &[Column]) {
+        self.columns = columns
+            .iter()
+            .filter(|c| !c.name.is_empty())
+            .map(|c| {
+                (
+                    c.name.to_string(),
+                    matches!(c.value_type, ValueType::Number),
+                )
+            })
+            .collect();
+    }
+
+    /// Preselect column, with a chart kind suited to its type.
+    pub fn set_column(&mut self, column: String) {
+        let is_numeric = self
+            .columns
+            .iter()
+            .any(|(name, is_numeric)| *name == column && *is_numeric);
+        self.kind = if is_numeric {
+            ChartKind::Histogram
+        } else {
+            ChartKind::ValueCounts
+        };
+        self.column = column;
+    }
+
+    pub fn plot_request(&self) -> Option<ChartResponse> {
+        if self.column.is_empty() || (self.kind == ChartKind::Scatter && self.y_column.is_empty()) {
+            return None;
+        }
+        Some(ChartResponse::Plot {
+            kind: self.kind,
+            column: self.column.clone(),
+            y_column: (self.kind == ChartKind::Scatter).then(|| self.y_column.clone()),
+        })
+    }
+
+    fn column_combo_box(
+        ui: &mut Ui,
+        id: &str,
+        columns: &[(String, bool)],
+        numeric_only: bool,
+        selected: &mut String,
+    ) {
+        ComboBox::from_id_salt(id)
+            .width(200.0)
+            .selected_text(selected.as_str())
+            .show_ui(ui, |ui| {
+                for (column, _) in columns
+                    .iter()
+                    .filter(|(_, is_numeric)| !numeric_only || *is_numeric)
+                {
+                    ui.selectable_value(selected, column.clone(), column.as_str());
+                }
+            });
+    }
+}
+
+impl super::Window<Option<ChartResponse>> for ChartPanel {
+    fn name(&self) -> &'static str {
+        PANEL_CHART
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<ChartResponse> {
+        egui::Window::new(self.name())
+            .collapsible(true)
+            .open(open)
+            .resizable([true, true])
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui)
+            })
+            .and_then(|inner_response| inner_response.inner)
+            .flatten()
+    }
+}
+
+impl super::View<Option<ChartResponse>> for ChartPanel {
+    fn ui(&mut self, ui: &mut Ui) -> Option<ChartResponse> {
+        let mut response = None;
+        Grid::new("chart_panel:grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Chart: ");
+                ui.horizontal(|ui| {
+                    for kind in ChartKind::ALL {
+                        ui.selectable_value(&mut self.kind, kind, kind.as_str());
+                    }
+                });
+                ui.end_row();
+                let numeric_only = self.kind.is_numeric();
+                ui.label(if self.kind == ChartKind::Scatter {
+                    "X: "
+                } else {
+                    "Column: "
+                });
+                Self::column_combo_box(
+                    ui,
+                    "chart_column",
+                    &self.columns,
+                    numeric_only,
+                    &mut self.column,
+                );
+                ui.end_row();
+                if self.kind == ChartKind::Scatter {
+                    ui.label("Y: ");
+                    Self::column_combo_box(
+                        ui,
+                        "chart_y_column",
+                        &self.columns,
+                        true,
+                        &mut self.y_column,
+                    );
+                    ui.end_row();
+                }
+                ui.label("");
+                let plot_request = self.plot_request();
+                if ui
+                    .add_enabled(plot_request.is_some(), Button::new("Plot"))
+                    .on_hover_text("Plot rows matching current filters")
+                    .clicked()
+                {
+                    response = plot_request;
+                }
+                ui.end_row();
+            });
+        if let Some(ref chart) = self.chart {
+            ui.separator();
+            if chart.is_empty() {
+                ui.label("No value to plot");
+            } else if let Some(values) = chart.ui(ui) {
+                response = Some(ChartResponse::Filter {
+                    column: chart.column.clone(),
+                    values,
+                });
+            }
+        }
+        response
+    }
+}
+
+impl FormattingPanel {
+    pub fn set_columns(&mut self, columns: &[Column]) {
+        self.columns = columns
+            .iter()
+            .filter(|c| !c.name.is_empty())
+            .map(|c| c.name.to_string())
+            .collect();
+    }
+
+    pub fn set_rules(&mut self, rules: &[FormattingRule]) {
+        self.rules = rules
+            .iter()
+            .map(|rule| (rule.description(), rule.color))
+            .collect();
+    }
+
+    pub fn clear_form(&mut self) {
+        self.value.clear();
+    }
+}
+
+impl super::Window<Option<FormattingResponse>> for FormattingPanel {
+    fn name(&self) -> &'static str {
+        PANEL_FORMATTING
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<FormattingResponse> {
+        egui::Window::new(self.name())
+            .collapsible(true)
+            .open(open)
+            .resizable([true, true])
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui)
+            })
+            .and_then(|inner_response| inner_response.inner)
+            .flatten()
+    }
+}
+
+impl super::View<Option<FormattingResponse>> for FormattingPanel {
+    fn ui(&mut self, ui: &mut Ui) -> Option<FormattingResponse> {
+        let mut response = None;
+        if !self.rules.is_empty() {
+            Grid::new("formatting_panel:list")
+                .num_columns(2)
+                .spacing([12.0, 8.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, (description, color)) in self.rules.iter().enumerate() {
+                        ui.colored_label(*color, description.as_str());
+                        if ui.button("Remove").clicked() {
+                            response = Some(FormattingResponse::Remove(index));
                         }
-                        let response_button_replace_with_null = ui.add(button_set_to_null);
-                        let response_button_replace = ui.add(button);
-                        let mut response_replace_regex_mode = ui.add(replace_regex_mode);
-                        response_replace_regex_mode =
-                            response_replace_regex_mode.on_hover_ui(|ui| {
-                                ui.label("Regex");
-                            });
-                        if response_replace_regex_mode.clicked() {
-                            if matches!(self.replace_mode, ReplaceMode::Regex) {
-                                self.replace_mode = ReplaceMode::Simple;
-                            } else {
-                                self.replace_mode = ReplaceMode::Regex;
-                            }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+        }
+        Grid::new("formatting_panel:grid")
+            .num_columns(2)
+            .spacing([12.0, 8.0])
+            .striped(false)
+            .show(ui, |ui| {
+                ui.label("Column: ");
+                ComboBox::from_id_salt("formatting_column")
+                    .width(200.0)
+                    .selected_text(self.column.as_str())
+                    .show_ui(ui, |ui| {
+                        for column in self.columns.iter() {
+                            ui.selectable_value(&mut self.column, column.clone(), column.as_str());
                         }
-                        let mut response_replace_exact_word_mode = ui.add(replace_exact_word_mode);
-                        response_replace_exact_word_mode = response_replace_exact_word_mode
-                            .on_hover_ui(|ui| {
-                                ui.label("Exact word");
-                            });
-                        if response_replace_exact_word_mode.clicked() {
-                            if matches!(self.replace_mode, ReplaceMode::ExactWord) {
-                                self.replace_mode = ReplaceMode::Simple;
-                            } else {
-                                self.replace_mode = ReplaceMode::ExactWord;
-                            }
+                    });
+                ui.end_row();
+                ui.label("Condition: ");
+                ComboBox::from_id_salt("formatting_condition")
+                    .width(200.0)
+                    .selected_text(self.condition.as_str())
+                    .show_ui(ui, |ui| {
+                        for condition in FormattingCondition::ALL {
+                            ui.selectable_value(&mut self.condition, condition, condition.as_str());
                         }
-                        let mut response_replace_match_case_mode = ui.add(replace_match_case_mode);
-                        response_replace_match_case_mode = response_replace_match_case_mode
-                            .on_hover_ui(|ui| {
-                                ui.label("Matching case");
-                            });
-                        if response_replace_match_case_mode.clicked() {
-                            if matches!(self.replace_mode, ReplaceMode::MatchingCase) {
-                                self.replace_mode = ReplaceMode::Simple;
-                            } else {
-                                self.replace_mode = ReplaceMode::MatchingCase;
-                            }
+                    });
+                ui.end_row();
+                if self.condition.has_value() {
+                    ui.label("Value: ");
+                    ui.add(TextEdit::singleline(&mut self.value).hint_text("10"));
+                    ui.end_row();
+                    ui.label("Apply to: ");
+                    ui.horizontal(|ui| {
+                        for target in [FormattingTarget::Cell, FormattingTarget::Row] {
+                            ui.selectable_value(&mut self.target, target, target.as_str());
                         }
-                        (response_button_replace, response_button_replace_with_null)
-                    })
-                    .inner;
+                    });
+                    ui.end_row();
+                }
+                ui.label("Color: ");
+                ui.color_edit_button_srgba(&mut self.color);
+                ui.end_row();
+                ui.label("");
+                if ui
+                    .add_enabled(!self.column.is_empty(), Button::new("Add rule"))
+                    .clicked()
+                {
+                    match FormattingRule::new(
+                        self.column.clone(),
+                        self.condition,
+                        self.value.clone(),
+                        self.target,
+                        self.color,
+                    ) {
+                        Ok(rule) => response = Some(FormattingResponse::Add(rule)),
+                        Err(error) => self.error = Some(error),
+                    }
+                }
                 ui.end_row();
-
-                replace_response
-            });
-        if grid_response.inner.0.clicked() {
-            return Some(SearchReplaceResponse {
-                search_criteria: self.search_criteria.clone(),
-                replace_value: Some(self.replace_value.clone()),
-                replace_mode: self.replace_mode.clone(),
-                selected_column: Some(self.selected_columns.borrow().clone()),
             });
-        } else if grid_response.inner.1.clicked() {
-            return Some(SearchReplaceResponse {
-                search_criteria: self.search_criteria.clone(),
-                replace_value: None,
-                replace_mode: self.replace_mode.clone(),
-                selected_column: Some(self.selected_columns.borrow().clone()),
+        if let Some(ref error) = self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+        ui.label(
+            RichText::new(
+                "Cell rules color text of matching cells, row rules color background of matching rows. \
+                Color scale colors cells from column min to column max.",
+            )
+            .small()
+            .weak(),
+        );
+        response
+    }
+}
+
+impl ColumnsPanel {
+    pub fn set_columns(&mut self, columns: Vec<(String, bool)>) {
+        self.columns = columns;
+    }
+
+    fn matches_search(&self, name: &str) -> bool {
+        self.search.is_empty() || name.to_lowercase().contains(&self.search.to_lowercase())
+    }
+}
+
+impl super::Window<Option<ColumnsResponse>> for ColumnsPanel {
+    fn name(&self) -> &'static str {
+        PANEL_COLUMNS
+    }
+
+    fn show(&mut self, ctx: &Context, open: &mut bool) -> Option<ColumnsResponse> {
+        egui::Window::new(self.name())
+            .collapsible(true)
+            .open(open)
+            .resizable([true, true])
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                use super::View as _;
+                self.ui(ui)
+            })
+            .and_then(|inner_response| inner_response.inner)
+            .flatten()
+    }
+}
+
+impl super::View<Option<ColumnsResponse>> for ColumnsPanel {
+    fn ui(&mut self, ui: &mut Ui) -> Option<ColumnsResponse> {
+        let mut changed = false;
+        ui.add(
+            TextEdit::singleline(&mut self.search)
+                .hint_text("Search columns")
+                .desired_width(f32::INFINITY),
+        );
+        ui.horizontal(|ui| {
+            for (label, visible) in [("Show all", true), ("Hide all", false)] {
+                if ui.button(label).clicked() {
+                    // Only columns matching search
+                    for index in 0..self.columns.len() {
+                        if self.matches_search(&self.columns[index].0) {
+                            self.columns[index].1 = visible;
+                        }
+                    }
+                    changed = true;
+                }
+            }
+        });
+        if ui.button("Reset to default").clicked() {
+            return Some(ColumnsResponse::Reset);
+        }
+        ui.separator();
+        // Dragged column index and index it is dropped before
+        let mut moved_column: Option<(usize, usize)> = None;
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for index in 0..self.columns.len() {
+                    if !self.matches_search(&self.columns[index].0) {
+                        continue;
+                    }
+                    let row = ui.horizontal(|ui| {
+                        ui.dnd_drag_source(egui::Id::new(("columns_panel", index)), index, |ui| {
+                            ui.label(icon::icon(crate::fonts::GRIP_VERTICAL))
+                                .on_hover_text("Drag to reorder");
+                        });
+                        let (name, visible) = &mut self.columns[index];
+                        changed |= ui.checkbox(visible, name.as_str()).changed();
+                    });
+                    let rect = row.response.rect;
+                    // Drop before or after this column depending on pointer position
+                    let after = ui
+                        .ctx()
+                        .pointer_hover_pos()
+                        .is_some_and(|pointer| pointer.y > rect.center().y);
+                    if row.response.dnd_hover_payload::<usize>().is_some() {
+                        let y = if after { rect.bottom() } else { rect.top() };
+                        ui.painter()
+                            .hline(rect.x_range(), y, ui.visuals().selection.stroke);
+                    }
+                    if let Some(dragged_index) = row.response.dnd_release_payload::<usize>() {
+                        moved_column = Some((*dragged_index, index + after as usize));
+                    }
+                }
             });
+        if let Some((from, to)) = moved_column {
+            if from != to && from + 1 != to {
+                let column = self.columns.remove(from);
+                let to = if from < to { to - 1 } else { to };
+                self.columns.insert(to, column);
+                changed = true;
+            }
         }
-        None
-        // return grid_response.inner
+        ui.label(
+            RichText::new("Hidden columns stay hidden at every depth, pinned ones are not listed.")
+                .small()
+                .weak(),
+        );
+        changed.then(|| ColumnsResponse::Layout(self.columns.clone()))
     }
 }
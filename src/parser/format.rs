@@ -0,0 +1,273 @@
+//! Minimal JSON tree used to re-format serialized rows before saving.
+//!
+//! Scalars and keys are kept as raw slices of the source so numbers and escaped strings are
+//! written back exactly as they were read, only whitespace, key order and renamed keys can change.
+//! Nodes built by code own their raw scalars and keys, which also keeps keys in insertion order
+//! when writing a document, unlike `serde_json::Map`.
+
+use std::borrow::Cow;
+
+pub enum JsonNode<'a> {
+    Scalar(Cow<'a, str>),
+    Array(Vec<JsonNode<'a>>),
+    // Keys are kept with their surrounding quotes
+    Object(Vec<(Cow<'a, str>, JsonNode<'a>)>),
+}
+
+pub struct JsonWriteOptions<'a> {
+    pub pretty: bool,
+    pub indent: &'a str,
+    pub new_line: &'a str,
+}
+
+impl<'a> JsonNode<'a> {
+    pub fn parse(raw: &'a str) -> Result<Self, String> {
+        let mut reader = Reader {
+            raw,
+            bytes: raw.as_bytes(),
+            position: 0,
+        };
+        let node = reader.parse_value()?;
+        reader.skip_whitespace();
+        if reader.position < reader.bytes.len() {
+            return Err(format!(
+                "Unexpected trailing content at position {}",
+                reader.position
+            ));
+        }
+        Ok(node)
+    }
+
+    pub fn sort_keys(&mut self) {
+        match self {
+            JsonNode::Scalar(_) => {}
+            JsonNode::Array(elements) => elements.iter_mut().for_each(|e| e.sort_keys()),
+            JsonNode::Object(entries) => {
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                entries.iter_mut().for_each(|(_, e)| e.sort_keys());
+            }
+        }
+    }
+
+    /// Replace values by defaults of their type, keeping object keys: empty string, 0, false,
+    /// null and empty array.
+    pub fn set_default_values(&mut self) {
+        match self {
+            JsonNode::Scalar(value) => {
+                let default_value = match value.as_ref() {
+                    "null" => "null",
+                    "true" | "false" => "false",
+                    value if value.starts_with('"') => "\"\"",
+                    _ => "0",
+                };
+                *value = Cow::Borrowed(default_value);
+            }
+            JsonNode::Array(elements) => elements.clear(),
+            JsonNode::Object(entries) => entries
+                .iter_mut()
+                .for_each(|(_, value)| value.set_default_values()),
+        }
+    }
+
+    /// Rename object keys at any depth, `rename` gets and returns keys without quotes nor escapes.
+    /// Returns whether a key was renamed.
+    pub fn rename_keys(&mut self, rename: &impl Fn(&str) -> Option<String>) -> bool {
+        match self {
+            JsonNode::Scalar(_) => false,
+            JsonNode::Array(elements) => elements
+                .iter_mut()
+                .fold(false, |renamed, e| e.rename_keys(rename) | renamed),
+            JsonNode::Object(entries) => {
+                let mut renamed = false;
+                for (key, value) in entries.iter_mut() {
+                    let new_key = serde_json::from_str::<String>(key)
+                        .ok()
+                        .and_then(|unquoted_key| rename(&unquoted_key))
+                        .and_then(|new_key| serde_json::to_string(&new_key).ok());
+                    if let Some(new_key) = new_key {
+                        *key = Cow::Owned(new_key);
+                        renamed = true;
+                    }
+                    renamed |= value.rename_keys(rename);
+                }
+                renamed
+            }
+        }
+    }
+
+    /// Write node, `level` is the indentation level of the line on which the node starts.
+    pub fn write(&self, out: &mut String, options: &JsonWriteOptions, level: usize) {
+        match self {
+            JsonNode::Scalar(value) => out.push_str(value),
+            JsonNode::Array(elements) => {
+                if elements.is_empty() {
+                    out.push_str("[]");
+                    return;
+                }
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    Self::new_line(out, options, level + 1);
+                    element.write(out, options, level + 1);
+                }
+                Self::new_line(out, options, level);
+                out.push(']');
+            }
+            JsonNode::Object(entries) => {
+                if entries.is_empty() {
+                    out.push_str("{}");
+                    return;
+                }
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    Self::new_line(out, options, level + 1);
+                    out.push_str(key);
+                    out.push(':');
+                    if options.pretty {
+                        out.push(' ');
+                    }
+                    value.write(out, options, level + 1);
+                }
+                Self::new_line(out, options, level);
+                out.push('}');
+            }
+        }
+    }
+
+    #[inline]
+    fn new_line(out: &mut String, options: &JsonWriteOptions, level: usize) {
+        if options.pretty {
+            out.push_str(options.new_line);
+            for _ in 0..level {
+                out.push_str(options.indent);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    raw: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn parse_value(&mut self) -> Result<JsonNode<'a>, String> {
+        self.skip_whitespace();
+        match self.bytes.get(self.position) {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => Ok(JsonNode::Scalar(Cow::Borrowed(self.parse_string()?))),
+            Some(_) => Ok(JsonNode::Scalar(Cow::Borrowed(self.parse_literal()?))),
+            None => Err("Unexpected end of json".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonNode<'a>, String> {
+        self.position += 1;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.consume(b'}') {
+            return Ok(JsonNode::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.position) != Some(&b'"') {
+                return Err(format!("Expected object key at position {}", self.position));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if !self.consume(b':') {
+                return Err(format!("Expected ':' at position {}", self.position));
+            }
+            let value = self.parse_value()?;
+            entries.push((Cow::Borrowed(key), value));
+            self.skip_whitespace();
+            if self.consume(b',') {
+                continue;
+            }
+            if self.consume(b'}') {
+                return Ok(JsonNode::Object(entries));
+            }
+            return Err(format!(
+                "Expected ',' or '}}' at position {}",
+                self.position
+            ));
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonNode<'a>, String> {
+        self.position += 1;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.consume(b']') {
+            return Ok(JsonNode::Array(elements));
+        }
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            if self.consume(b',') {
+                continue;
+            }
+            if self.consume(b']') {
+                return Ok(JsonNode::Array(elements));
+            }
+            return Err(format!("Expected ',' or ']' at position {}", self.position));
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<&'a str, String> {
+        let start = self.position;
+        self.position += 1;
+        while let Some(b) = self.bytes.get(self.position) {
+            match b {
+                b'\\' => self.position += 2,
+                b'"' => {
+                    self.position += 1;
+                    return Ok(&self.raw[start..self.position]);
+                }
+                _ => self.position += 1,
+            }
+        }
+        Err(format!("Unterminated string at position {}", start))
+    }
+
+    fn parse_literal(&mut self) -> Result<&'a str, String> {
+        let start = self.position;
+        while let Some(b) = self.bytes.get(self.position) {
+            if matches!(b, b',' | b'}' | b']' | b' ' | b'\n' | b'\r' | b'\t') {
+                break;
+            }
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(format!("Unexpected character at position {}", start));
+        }
+        Ok(&self.raw[start..self.position])
+    }
+
+    #[inline]
+    fn consume(&mut self, expected: u8) -> bool {
+        if self.bytes.get(self.position) == Some(&expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[inline]
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.bytes.get(self.position) {
+            if !matches!(b, b' ' | b'\n' | b'\r' | b'\t') {
+                break;
+            }
+            self.position += 1;
+        }
+    }
+}
@@ -2,19 +2,24 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hasher};
 use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::{fs, mem};
 
+pub mod format;
+
 use crate::array_table::{Column, NON_NULL_FILTER_VALUE};
 use crate::panels::{ReplaceMode, SearchReplaceResponse};
+use crate::parser::format::{JsonNode, JsonWriteOptions};
 use json_flat_parser::{
     FlatJsonValue, JSONParser, JsonArrayEntries, ParseOptions, ParseResult, PointerKey, ValueType,
 };
+use rayon::iter::IndexedParallelIterator;
 use rayon::iter::IntoParallelIterator;
+use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
 use rayon::prelude::ParallelSliceMut;
-use regex_lite::Regex;
+use regex_lite::{NoExpand, Regex};
 
 #[macro_export]
 macro_rules! concat_string {
@@ -29,11 +34,13 @@ macro_rules! concat_string {
     }};
 }
 
+type ArrayAtDepth<'array> = (Vec<JsonArrayEntries<String>>, Vec<Column<'array>>, usize);
+
 pub fn change_depth_array<'array>(
     previous_parse_result: ParseResult<String>,
     mut json_array: Vec<JsonArrayEntries<String>>,
     depth: usize,
-) -> Result<(Vec<JsonArrayEntries<String>>, Vec<Column<'array>>, usize), String> {
+) -> Result<ArrayAtDepth<'array>, String> {
     let mut len = json_array.len();
     let new_json_array = Arc::new(Mutex::new(Vec::with_capacity(json_array.len())));
 
@@ -48,6 +55,8 @@ pub fn change_depth_array<'array>(
             let mut unique_keys: Vec<Column> = Vec::with_capacity(16);
             for json_array_entry in chunk {
                 let mut parse_result = previous_parse_result.clone_except_json();
+                // Rows keep the pointer they were parsed at, which may not be their index
+                let prefix_len = row_pointer(&json_array_entry.entries).len();
                 parse_result.json = mem::take(&mut json_array_entry.entries);
                 let options = ParseOptions::default()
                     .parse_array(false)
@@ -58,21 +67,7 @@ pub fn change_depth_array<'array>(
                 parse_result.json.swap(last_index, new_last_index);
                 let mut vec = parse_result.json;
 
-                for j in 0..vec.len() {
-                    let entry = &mut vec[j];
-                    let _i = json_array_entry.index.to_string();
-                    let prefix_len = if let Some(ref started_parsing_at) =
-                        previous_parse_result.started_parsing_at
-                    {
-                        let prefix = concat_string!(started_parsing_at, "/", _i);
-                        prefix.len()
-                    } else if let Some(ref prefix) = previous_parse_result.parsing_prefix {
-                        let prefix = concat_string!(prefix, "/", _i);
-                        prefix.len()
-                    } else {
-                        let prefix = concat_string!("/", _i);
-                        prefix.len()
-                    };
+                for entry in vec.iter_mut() {
                     if !entry.pointer.pointer.is_empty() {
                         if entry.pointer.pointer.len() <= prefix_len {
                             // panic!("ERROR, depth {} out of bounds of {}, expected to have a prefix of len {}", depth, entry.pointer.pointer, prefix_len);
@@ -91,9 +86,7 @@ pub fn change_depth_array<'array>(
                             entry.pointer.column_id = column.id;
                             column.seen_count += 1;
                         } else if !column.name.contains('#') {
-                            let mut hasher = DefaultHasher::new();
-                            hasher.write(column.name.as_bytes());
-                            column.id = hasher.finish() as usize;
+                            column.id = column_id(&column.name);
                             entry.pointer.column_id = column.id;
                             unique_keys.push(column);
                         }
@@ -119,7 +112,7 @@ pub fn change_depth_array<'array>(
         }
     }
     let mut new_json_array_guard = new_json_array.lock().unwrap();
-    new_json_array_guard.sort_unstable_by(|a, b| a.index.cmp(&b.index));
+    new_json_array_guard.sort_unstable_by_key(|a| a.index);
     unique_keys.sort();
 
     Ok((mem::take(&mut new_json_array_guard), unique_keys, 4))
@@ -127,19 +120,17 @@ pub fn change_depth_array<'array>(
 pub fn as_array<'array>(
     mut previous_parse_result: ParseResult<String>,
 ) -> Result<(Vec<JsonArrayEntries<String>>, Vec<Column<'array>>), String> {
-    let (root_value, start_index, mut end_index) =
-        if let Some(ref started_parsing_at) = previous_parse_result.started_parsing_at {
-            let root_value = previous_parse_result.json
-                [previous_parse_result.started_parsing_at_index_start]
-                .clone();
-            (
-                root_value,
-                previous_parse_result.started_parsing_at_index_start,
-                previous_parse_result.started_parsing_at_index_end,
-            )
-        } else {
-            (previous_parse_result.json[0].clone(), 0, 0)
-        };
+    let (root_value, mut end_index) = if previous_parse_result.started_parsing_at.is_some() {
+        let root_value = previous_parse_result.json
+            [previous_parse_result.started_parsing_at_index_start]
+            .clone();
+        (
+            root_value,
+            previous_parse_result.started_parsing_at_index_end,
+        )
+    } else {
+        (previous_parse_result.json[0].clone(), 0)
+    };
 
     if !matches!(root_value.pointer.value_type, ValueType::Array(_)) {
         return Err("Parsed json root is not an array".to_string());
@@ -202,9 +193,7 @@ pub fn as_array<'array>(
                             }
                             entry.pointer.column_id = existing_column.id;
                         } else {
-                            let mut hasher = DefaultHasher::new();
-                            hasher.write(column.name.as_bytes());
-                            column.id = hasher.finish() as usize;
+                            column.id = column_id(&column.name);
                             entry.pointer.column_id = column.id;
                             unique_keys.push(column);
                         }
@@ -239,6 +228,37 @@ pub fn as_array<'array>(
     Ok((res, unique_keys))
 }
 
+/// Default value of a value type, for rows inserted from a template: empty string, 0, false, null,
+/// empty arrays and objects having default values.
+pub fn template_value(value_type: ValueType, value: Option<&str>) -> Option<String> {
+    match value_type {
+        ValueType::String => Some(String::new()),
+        ValueType::Number => Some("0".to_string()),
+        ValueType::Bool => Some("false".to_string()),
+        ValueType::Array(_) => Some("[]".to_string()),
+        ValueType::Object(..) => {
+            let mut node = JsonNode::parse(value?).ok()?;
+            node.set_default_values();
+            let mut default_value = String::new();
+            let write_options = JsonWriteOptions {
+                pretty: false,
+                indent: "",
+                new_line: "",
+            };
+            node.write(&mut default_value, &write_options, 0);
+            Some(default_value)
+        }
+        _ => None,
+    }
+}
+
+/// Id of a column, the same for every column having this name.
+pub fn column_id(name: &str) -> usize {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(name.as_bytes());
+    hasher.finish() as usize
+}
+
 pub fn row_number_entry(i: usize, position: usize, prefix: &str) -> FlatJsonValue<String> {
     FlatJsonValue {
         pointer: PointerKey::from_pointer(
@@ -251,58 +271,256 @@ pub fn row_number_entry(i: usize, position: usize, prefix: &str) -> FlatJsonValu
     }
 }
 
-#[cfg(windows)]
-const LINE_ENDING: &'static [u8] = ",\r\n".as_bytes();
-#[cfg(not(windows))]
-const LINE_ENDING: &[u8] = ",\n".as_bytes();
+/// Pointer of the row root entry, prefix of pointers of all entries of the row. It is the pointer
+/// the row was parsed or inserted at, so it is not the row index once rows above were inserted or
+/// deleted: rows are not rewritten when they move.
+#[inline]
+pub fn row_pointer(row_entries: &[FlatJsonValue<String>]) -> &str {
+    row_entries
+        .last()
+        .map_or("", |root_entry| root_entry.pointer.pointer.as_str())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveFormat {
+    // Rows written as they were read or edited, one after another
+    #[default]
+    Unchanged,
+    Compact,
+    RowPerLine,
+    Pretty,
+}
+
+impl SaveFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unchanged => "rows as they are",
+            Self::Compact => "compact",
+            Self::RowPerLine => "one row per line",
+            Self::Pretty => "pretty",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Unchanged,
+            Self::Compact,
+            Self::RowPerLine,
+            Self::Pretty,
+        ]
+        .into_iter()
+        .find(|format| format.as_str() == name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl Default for LineEnding {
+    #[cfg(windows)]
+    fn default() -> Self {
+        Self::CrLf
+    }
+    #[cfg(not(windows))]
+    fn default() -> Self {
+        Self::Lf
+    }
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [Self::Lf, Self::CrLf]
+            .into_iter()
+            .find(|line_ending| line_ending.label() == label)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SaveOptions {
+    pub format: SaveFormat,
+    pub indent_size: usize,
+    pub indent_with_tabs: bool,
+    pub line_ending: LineEnding,
+    pub trailing_new_line: bool,
+    pub sort_keys: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            format: Default::default(),
+            indent_size: 2,
+            indent_with_tabs: false,
+            line_ending: Default::default(),
+            trailing_new_line: false,
+            sort_keys: false,
+        }
+    }
+}
+
+/// How a saved file is protected against losing changes, unlike [SaveOptions] it does not change
+/// the written content.
+#[derive(Clone, PartialEq)]
+pub struct BackupOptions {
+    pub keep_backup: bool,
+    pub autosave: bool,
+    pub autosave_interval_minutes: u32,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self {
+            keep_backup: false,
+            autosave: false,
+            autosave_interval_minutes: 2,
+        }
+    }
+}
+
+impl BackupOptions {
+    pub fn backup_path(&self, file_path: &Path) -> Option<PathBuf> {
+        if self.keep_backup {
+            Some(sibling_path(file_path, "", ".bak"))
+        } else {
+            None
+        }
+    }
+}
+
+impl SaveOptions {
+    fn indent(&self) -> String {
+        if self.indent_with_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.indent_size)
+        }
+    }
+
+    fn new_line_at(&self, level: usize) -> String {
+        concat_string!(self.line_ending.as_str(), self.indent().repeat(level))
+    }
+
+    /// Whether rows are parsed and written again, otherwise they are saved byte for byte.
+    pub fn reformats_rows(&self) -> bool {
+        !matches!(self.format, SaveFormat::Unchanged) || self.sort_keys
+    }
+
+    /// Re-format a serialized row, `level` is the indentation level of the row in pretty format.
+    pub fn format_row(&self, serialized_row: &str, level: usize) -> String {
+        let Ok(mut node) = JsonNode::parse(serialized_row) else {
+            // Should not happen, but better to save value as is than losing it
+            return serialized_row.to_string();
+        };
+        if self.sort_keys {
+            node.sort_keys();
+        }
+        let indent = self.indent();
+        let write_options = JsonWriteOptions {
+            pretty: matches!(self.format, SaveFormat::Pretty),
+            indent: indent.as_str(),
+            new_line: self.line_ending.as_str(),
+        };
+        let mut formatted_row = String::with_capacity(serialized_row.len());
+        node.write(&mut formatted_row, &write_options, level);
+        formatted_row
+    }
+}
 
 pub fn save_to_buffer<T: Write>(
     parent_pointer: &str,
     array: &[JsonArrayEntries<String>],
     buffer: &mut T,
+    save_options: &SaveOptions,
 ) -> std::io::Result<()> {
-    if !parent_pointer.is_empty() {
-        let split = parent_pointer.split('/');
-        for frag in split {
-            if frag.is_empty() {
-                continue;
-            }
-            let b = &frag.as_bytes()[0];
-            if *b >= 0x30 && *b <= 0x39 {
-                buffer.write_all("[".as_bytes()).unwrap();
-            } else {
-                buffer
-                    .write_all(format!("{{\"{}\":", frag).as_bytes())
-                    .unwrap();
+    let is_pretty = matches!(save_options.format, SaveFormat::Pretty);
+    let parent_fragments = parent_pointer
+        .split('/')
+        .filter(|frag| !frag.is_empty())
+        .collect::<Vec<&str>>();
+    for (level, frag) in parent_fragments.iter().enumerate() {
+        let b = &frag.as_bytes()[0];
+        if *b >= 0x30 && *b <= 0x39 {
+            buffer.write_all("[".as_bytes())?;
+            if is_pretty {
+                buffer.write_all(save_options.new_line_at(level + 1).as_bytes())?;
             }
+        } else if is_pretty {
+            buffer.write_all(
+                format!("{{{}\"{}\": ", save_options.new_line_at(level + 1), frag).as_bytes(),
+            )?;
+        } else {
+            buffer.write_all(format!("{{\"{}\":", frag).as_bytes())?;
         }
     }
-    buffer.write_all("[".as_bytes()).unwrap();
+    let level = parent_fragments.len();
+    let rows_separator = match save_options.format {
+        SaveFormat::Compact => ",".to_string(),
+        SaveFormat::Unchanged | SaveFormat::RowPerLine => {
+            concat_string!(",", save_options.line_ending.as_str())
+        }
+        SaveFormat::Pretty => concat_string!(",", save_options.new_line_at(level + 1)),
+    };
+    let formatted_rows = save_options.reformats_rows().then(|| {
+        array
+            .par_iter()
+            .map(|entry| {
+                entry.entries.last().map(|serialized_entry| {
+                    save_options.format_row(serialized_entry.value.as_ref().unwrap(), level + 1)
+                })
+            })
+            .collect::<Vec<Option<String>>>()
+    });
+    buffer.write_all("[".as_bytes())?;
+    if is_pretty && !array.is_empty() {
+        buffer.write_all(save_options.new_line_at(level + 1).as_bytes())?;
+    }
     for (i, entry) in array.iter().enumerate() {
         if let Some(serialized_entry) = entry.entries.last() {
-            buffer
-                .write_all(serialized_entry.value.as_ref().unwrap().as_bytes())
-                .unwrap();
+            let row = match formatted_rows {
+                Some(ref formatted_rows) => formatted_rows[i].as_deref().unwrap_or_default(),
+                None => serialized_entry.value.as_deref().unwrap(),
+            };
+            buffer.write_all(row.as_bytes())?;
             if i < array.len() - 1 {
-                buffer.write_all(LINE_ENDING).unwrap();
+                buffer.write_all(rows_separator.as_bytes())?;
             }
         }
     }
+    if is_pretty && !array.is_empty() {
+        buffer.write_all(save_options.new_line_at(level).as_bytes())?;
+    }
     buffer.write_all("]".as_bytes())?;
-    if !parent_pointer.is_empty() {
-        let split = parent_pointer.split('/');
-        for frag in split {
-            if frag.is_empty() {
-                continue;
-            }
-            let b = &frag.as_bytes()[0];
-            if *b >= 0x30 && *b <= 0x39 {
-                buffer.write_all("]".as_bytes()).unwrap();
-            } else {
-                buffer.write_all("}".as_bytes()).unwrap();
-            }
+    for (level, frag) in parent_fragments.iter().enumerate().rev() {
+        if is_pretty {
+            buffer.write_all(save_options.new_line_at(level).as_bytes())?;
+        }
+        let b = &frag.as_bytes()[0];
+        if *b >= 0x30 && *b <= 0x39 {
+            buffer.write_all("]".as_bytes())?;
+        } else {
+            buffer.write_all("}".as_bytes())?;
         }
     }
+    if save_options.trailing_new_line {
+        buffer.write_all(save_options.line_ending.as_str().as_bytes())?;
+    }
     buffer.flush()?;
     Ok(())
 }
@@ -311,25 +529,82 @@ pub fn save_to_file(
     parent_pointer: &str,
     array: &[JsonArrayEntries<String>],
     file_path: &Path,
+    save_options: &SaveOptions,
+    backup_options: &BackupOptions,
 ) -> std::io::Result<()> {
     // let start = crate::compatibility::now();
-    let file = fs::File::create(file_path)?;
-    let mut file = BufWriter::new(file);
-    save_to_buffer(parent_pointer, array, &mut file)?;
+    let backup_path = backup_options.backup_path(file_path);
+    write_atomically(file_path, backup_path.as_deref(), |file| {
+        save_to_buffer(parent_pointer, array, file, save_options)
+    })?;
     // println!("serialize and save file took {}ms", start.elapsed().as_millis());
     Ok(())
 }
 
+/// Content is written in a temporary file next to `file_path` which then replaces `file_path`
+/// with a single rename, so an error while writing (crash, full disk) never leaves a truncated or
+/// missing file behind. The backup is a hard link (or a copy) of the previous version, it replaces
+/// the previous backup the same way. When `file_path` is a symlink, its target is replaced and
+/// keeps its permissions.
+pub fn write_atomically(
+    file_path: &Path,
+    backup_path: Option<&Path>,
+    write: impl FnOnce(&mut BufWriter<fs::File>) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    // Fails when the file does not exist yet, then there is no link to follow either
+    let file_path = fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf());
+    let previous_permissions = fs::metadata(&file_path).map(|m| m.permissions()).ok();
+    let file_exists = previous_permissions.is_some();
+    let temporary_path = sibling_path(&file_path, ".", ".tmp");
+    let result = fs::File::create(&temporary_path).and_then(|file| {
+        let mut file = BufWriter::new(file);
+        write(&mut file)?;
+        file.into_inner()?.sync_all()?;
+        match previous_permissions {
+            Some(permissions) => fs::set_permissions(&temporary_path, permissions),
+            None => Ok(()),
+        }
+    });
+    let result = result.and_then(|_| match backup_path {
+        Some(backup_path) if file_exists => replace_backup(&file_path, backup_path),
+        _ => Ok(()),
+    });
+    let result = result.and_then(|_| fs::rename(&temporary_path, &file_path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result
+}
+
+fn replace_backup(file_path: &Path, backup_path: &Path) -> std::io::Result<()> {
+    let temporary_backup_path = sibling_path(backup_path, ".", ".tmp");
+    let _ = fs::remove_file(&temporary_backup_path);
+    let result = fs::hard_link(file_path, &temporary_backup_path)
+        .or_else(|_| fs::copy(file_path, &temporary_backup_path).map(|_| ()))
+        .and_then(|_| fs::rename(&temporary_backup_path, backup_path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_backup_path);
+    }
+    result
+}
+
+pub fn sibling_path(file_path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    file_path.with_file_name(concat_string!(prefix, file_name, suffix))
+}
+
 pub fn filter_columns(
     previous_parse_result: &Vec<JsonArrayEntries<String>>,
-    prefix: &str,
     filters: &HashMap<String, Vec<String>>,
 ) -> Vec<usize> {
     let mut res: Vec<usize> = Vec::with_capacity(previous_parse_result.len());
     for row in previous_parse_result {
         let mut should_add_row = true;
         for (pointer, filters) in filters {
-            let pointer_to_find = concat_string!(prefix, "/", row.index().to_string(), pointer);
+            let pointer_to_find = concat_string!(row_pointer(row.entries()), pointer);
             let mut filters_clone = Vec::with_capacity(filters.len());
             let mut should_filter_by_non_null = false;
             for filter in filters {
@@ -363,31 +638,147 @@ pub fn filter_columns(
     }
     res
 }
-pub fn search_occurrences(
-    previous_parse_result: &[JsonArrayEntries<String>],
-    term: &str,
-) -> Vec<usize> {
-    let mut res: Vec<usize> = vec![];
-    for json_array_entry in previous_parse_result.iter() {
-        for entry in &json_array_entry.entries {
-            if !matches!(entry.pointer.value_type, ValueType::String) {
-                continue;
+/// Regex matching `term` the way `mode` compares values: case insensitive for `Simple`, as a
+/// whole word for `ExactWord`, as is for `Regex`.
+pub fn search_regex(term: &str, mode: &ReplaceMode) -> Result<Regex, String> {
+    let pattern = match mode {
+        ReplaceMode::Simple => concat_string!("(?i)", regex_lite::escape(term)),
+        ReplaceMode::MatchingCase => regex_lite::escape(term),
+        ReplaceMode::ExactWord => concat_string!(r"\b", regex_lite::escape(term), r"\b"),
+        ReplaceMode::Regex => term.to_string(),
+    };
+    Regex::new(&pattern).map_err(|e| e.to_string())
+}
+
+/// Key renamed by replacing what `regex` matches, `None` when it does not match or when new key
+/// would be empty or contain a `/`.
+pub fn rename_key(
+    key: &str,
+    regex: &Regex,
+    replace_value: &str,
+    mode: &ReplaceMode,
+) -> Option<String> {
+    if !regex.is_match(key) {
+        return None;
+    }
+    let new_key = if matches!(mode, ReplaceMode::Regex) {
+        regex.replace_all(key, replace_value)
+    } else {
+        regex.replace_all(key, NoExpand(replace_value))
+    };
+    (!new_key.is_empty() && !new_key.contains('/') && new_key != key).then(|| new_key.to_string())
+}
+
+/// Pointer with its keys renamed, array indexes and row number are kept. `None` when no key was
+/// renamed.
+pub fn rename_pointer_keys(
+    pointer: &str,
+    rename: &impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let mut renamed = false;
+    let new_pointer = pointer
+        .split('/')
+        .map(|key| {
+            if key.is_empty() || key == "#" || key.bytes().all(|b| b.is_ascii_digit()) {
+                return Cow::Borrowed(key);
             }
-            if let Some(ref value) = entry.value {
-                if value.to_lowercase().contains(term) {
-                    res.push(json_array_entry.index);
-                    break;
+            match rename(key) {
+                Some(new_key) => {
+                    renamed = true;
+                    Cow::Owned(new_key)
                 }
+                None => Cow::Borrowed(key),
             }
-        }
+        })
+        .collect::<Vec<Cow<str>>>()
+        .join("/");
+    renamed.then_some(new_pointer)
+}
+
+/// Serialized object or array with its keys renamed at any depth, `None` when no key was renamed.
+pub fn rename_serialized_keys(
+    serialized_value: &str,
+    rename: &impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let mut node = JsonNode::parse(serialized_value).ok()?;
+    if !node.rename_keys(rename) {
+        return None;
     }
-    res
+    let mut new_value = String::with_capacity(serialized_value.len());
+    let write_options = JsonWriteOptions {
+        pretty: false,
+        indent: "",
+        new_line: "",
+    };
+    node.write(&mut new_value, &write_options, 0);
+    Some(new_value)
 }
 
+/// Cells whose value, of any type, matches `regex`. Returns position in `rows` and column name of
+/// each matching cell, ordered by row then by entry.
+pub fn search_occurrences(
+    previous_parse_result: &[JsonArrayEntries<String>],
+    rows: &[usize],
+    regex: &Regex,
+) -> Vec<(usize, String)> {
+    rows.par_iter()
+        .enumerate()
+        .flat_map_iter(|(position, row_index)| {
+            let entries = &previous_parse_result[*row_index].entries;
+            let prefix = row_pointer(entries);
+            entries.iter().filter_map(move |entry| {
+                let column = entry.pointer.pointer.strip_prefix(prefix)?;
+                // Skip row root and row number entries
+                if column.is_empty() || column == "/#" {
+                    return None;
+                }
+                let is_match = entry
+                    .value
+                    .as_ref()
+                    .is_some_and(|value| regex.is_match(value));
+                is_match.then(|| (position, column.to_string()))
+            })
+        })
+        .collect()
+}
+
+/// Names of columns whose key matches `regex`, a key is shared by all cells of its column.
+pub fn search_keys(columns: &[Column], regex: &Regex) -> Vec<String> {
+    columns
+        .iter()
+        // Skip row number column
+        .filter(|column| !column.name.is_empty() && column.name != "/#")
+        .filter(|column| regex.is_match(column.name.rsplit('/').next().unwrap_or_default()))
+        .map(|column| column.name.to_string())
+        .collect()
+}
+
+/// Number of values of these columns in these rows matching `regex`.
+pub fn count_matching_values(
+    previous_parse_result: &[JsonArrayEntries<String>],
+    rows: &[usize],
+    column_ids: &[usize],
+    regex: &Regex,
+) -> usize {
+    rows.par_iter()
+        .map(|row_index| {
+            previous_parse_result[*row_index]
+                .entries
+                .iter()
+                .filter(|entry| column_ids.contains(&entry.pointer.column_id))
+                .filter(|entry| entry.value.as_ref().is_some_and(|v| regex.is_match(v)))
+                .count()
+        })
+        .sum()
+}
+
+/// New values of selected columns in these rows, with their row index. Fails when search criteria
+/// is not a valid pattern.
 pub fn replace_occurrences(
-    previous_parse_result: &Vec<JsonArrayEntries<String>>,
+    previous_parse_result: &[JsonArrayEntries<String>],
+    rows: &[usize],
     search_replace_response: SearchReplaceResponse,
-) -> Vec<(FlatJsonValue<String>, usize)> {
+) -> Result<Vec<(FlatJsonValue<String>, usize)>, String> {
     let column_ids = if let Some(ref selected_columns) = search_replace_response.selected_column {
         selected_columns
             .iter()
@@ -396,108 +787,51 @@ pub fn replace_occurrences(
     } else {
         vec![]
     };
+    let re = search_regex(
+        search_replace_response.search_criteria.as_str(),
+        &search_replace_response.replace_mode,
+    )?;
     let mut new_values: Vec<(FlatJsonValue<String>, usize)> = vec![];
-    for json_array_entry in previous_parse_result.iter() {
+    for json_array_entry in rows
+        .iter()
+        .map(|row_index| &previous_parse_result[*row_index])
+    {
         for entry in json_array_entry.entries.iter() {
             if column_ids.contains(&entry.pointer.column_id) {
                 if let Some(ref value) = entry.value {
-                    match search_replace_response.replace_mode {
-                        ReplaceMode::MatchingCase => {
-                            let new_value = if let Some(ref replace_value) =
-                                search_replace_response.replace_value
-                            {
-                                Some(value.replace(
-                                    search_replace_response.search_criteria.as_str(),
-                                    replace_value,
-                                ))
-                            } else if (search_replace_response.search_criteria.is_empty()
-                                && value.is_empty())
-                                || (!search_replace_response
-                                    .search_criteria
-                                    .is_empty()
-                                    && value.contains(
-                                        search_replace_response.search_criteria.as_str(),
-                                    ))
-                            {
-                                None
-                            } else {
-                                Some(value.clone())
-                            };
-                            new_values.push((
-                                FlatJsonValue {
-                                    pointer: entry.pointer.clone(),
-                                    value: new_value,
-                                },
-                                json_array_entry.index,
-                            ));
-                        }
-                        ReplaceMode::Regex => {
-                            let re = Regex::new(search_replace_response.search_criteria.as_str())
-                                .unwrap();
-                            let new_value = replace_with_regex(&search_replace_response, value, re);
-                            new_values.push((
-                                FlatJsonValue {
-                                    pointer: entry.pointer.clone(),
-                                    value: new_value,
-                                },
-                                json_array_entry.index,
-                            ));
-                        }
-                        ReplaceMode::ExactWord => {
-                            let re = Regex::new(&format!(
-                                r"\b{}\b",
-                                regex_lite::escape(
-                                    search_replace_response.search_criteria.as_str()
-                                )
-                            ))
-                            .unwrap();
-                            let new_value = replace_with_regex(&search_replace_response, value, re);
-                            new_values.push((
-                                FlatJsonValue {
-                                    pointer: entry.pointer.clone(),
-                                    value: new_value,
-                                },
-                                json_array_entry.index,
-                            ));
-                        }
-                        ReplaceMode::Simple => {
-                            let re = Regex::new(&format!(
-                                "(?i){}",
-                                regex_lite::escape(
-                                    search_replace_response.search_criteria.as_str()
-                                )
-                            ))
-                            .unwrap();
-                            let new_value = replace_with_regex(&search_replace_response, value, re);
-                            new_values.push((
-                                FlatJsonValue {
-                                    pointer: entry.pointer.clone(),
-                                    value: new_value,
-                                },
-                                json_array_entry.index,
-                            ));
-                        }
-                    }
+                    let new_value = replace_with_regex(&search_replace_response, value, &re);
+                    new_values.push((
+                        FlatJsonValue {
+                            pointer: entry.pointer.clone(),
+                            value: new_value,
+                        },
+                        json_array_entry.index,
+                    ));
                 }
             }
         }
     }
-    new_values
+    Ok(new_values)
 }
 
 fn replace_with_regex(
     search_replace_response: &SearchReplaceResponse,
-    value: &String,
-    re: Regex,
+    value: &str,
+    re: &Regex,
 ) -> Option<String> {
     let new_value = if let Some(ref replace_value) = search_replace_response.replace_value {
-        Some(re.replace_all(value, replace_value.as_str()).to_string())
+        // Only regex replacement expands capture groups like $1
+        if matches!(search_replace_response.replace_mode, ReplaceMode::Regex) {
+            Some(re.replace_all(value, replace_value.as_str()).to_string())
+        } else {
+            Some(re.replace_all(value, NoExpand(replace_value)).to_string())
+        }
     } else if (search_replace_response.search_criteria.is_empty() && value.is_empty())
         || (!search_replace_response.search_criteria.is_empty() && re.is_match(value))
     {
         None
     } else {
-        Some(value.clone())
+        Some(value.to_string())
     };
     new_value
 }
@@ -505,9 +839,37 @@ fn replace_with_regex(
 #[cfg(test)]
 mod tests {
     use crate::array_table::Column;
-    use crate::panels::{ReplaceMode, SearchReplaceResponse};
-    use crate::parser::{as_array, replace_occurrences};
-    use json_flat_parser::{JSONParser, ParseOptions};
+    use crate::panels::{ReplaceMode, ReplaceScope, SearchReplaceResponse};
+    use crate::parser::{
+        as_array, rename_key, rename_pointer_keys, rename_serialized_keys, replace_occurrences,
+        save_to_buffer, search_keys, search_occurrences, search_regex, sibling_path,
+        template_value, write_atomically, LineEnding, SaveFormat, SaveOptions,
+    };
+    use json_flat_parser::{
+        FlatJsonValue, JSONParser, JsonArrayEntries, ParseOptions, PointerKey, ValueType,
+    };
+    use std::borrow::Cow;
+    use std::fs;
+    use std::io::Write;
+
+    fn serialized_rows(rows: &[&str]) -> Vec<JsonArrayEntries<String>> {
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| JsonArrayEntries {
+                entries: vec![FlatJsonValue {
+                    pointer: PointerKey {
+                        pointer: format!("/{}", index),
+                        value_type: ValueType::Object(true, 0),
+                        depth: 1,
+                        position: 0,
+                        column_id: 0,
+                    },
+                    value: Some(row.to_string()),
+                }],
+                index,
+            })
+            .collect()
+    }
 
     #[test]
     fn test_replace() {
@@ -551,16 +913,342 @@ mod tests {
             .collect::<Vec<Column>>();
         let replaced_values = replace_occurrences(
             &array,
+            &(0..array.len()).collect::<Vec<usize>>(),
             SearchReplaceResponse {
                 search_criteria: "(.*)".to_string(),
                 replace_value: Some("A$1".to_string()),
                 selected_column: Some(filter_column),
                 replace_mode: ReplaceMode::Regex,
+                scope: ReplaceScope::All,
             },
-        );
+        )
+        .unwrap();
         assert_eq!(
             replaced_values[0].0.value.as_ref().unwrap().as_str(),
             "ACart Termination"
         );
     }
+
+    #[test]
+    fn test_replace_value_is_literal_out_of_regex_mode() {
+        let column = Column {
+            name: Cow::Borrowed("/price"),
+            depth: 1,
+            value_type: ValueType::String,
+            seen_count: 1,
+            order: 0,
+            id: 7,
+        };
+        let array = vec![JsonArrayEntries {
+            entries: vec![FlatJsonValue {
+                pointer: PointerKey {
+                    pointer: "/0/price".to_string(),
+                    value_type: ValueType::String,
+                    depth: 2,
+                    position: 0,
+                    column_id: 7,
+                },
+                value: Some("10 zeny".to_string()),
+            }],
+            index: 0,
+        }];
+        let replace = |search_criteria: &str, replace_mode: ReplaceMode| {
+            let replaced_values = replace_occurrences(
+                &array,
+                &[0],
+                SearchReplaceResponse {
+                    search_criteria: search_criteria.to_string(),
+                    replace_value: Some("$1 USD".to_string()),
+                    selected_column: Some(vec![column.clone()]),
+                    replace_mode,
+                    scope: ReplaceScope::All,
+                },
+            )
+            .unwrap();
+            replaced_values[0].0.value.clone().unwrap()
+        };
+        // Capture groups are only expanded in regex mode, elsewhere `$1` is replaced as typed
+        assert_eq!(replace("(zeny)", ReplaceMode::Regex), "10 zeny USD");
+        assert_eq!(replace("zeny", ReplaceMode::Simple), "10 $1 USD");
+        assert_eq!(replace("zeny", ReplaceMode::MatchingCase), "10 $1 USD");
+        assert_eq!(replace("zeny", ReplaceMode::ExactWord), "10 $1 USD");
+    }
+
+    #[test]
+    fn test_replace_invalid_regex() {
+        let replaced_values = replace_occurrences(
+            &[],
+            &[],
+            SearchReplaceResponse {
+                search_criteria: "(unbalanced".to_string(),
+                replace_value: Some("A".to_string()),
+                selected_column: None,
+                replace_mode: ReplaceMode::Regex,
+                scope: ReplaceScope::All,
+            },
+        );
+        assert!(replaced_values.is_err());
+    }
+
+    #[test]
+    fn test_rename_keys() {
+        let regex = search_regex("^damageflags$", &ReplaceMode::Regex).unwrap();
+        let rename = |key: &str| rename_key(key, &regex, "damageFlags", &ReplaceMode::Regex);
+        assert_eq!(
+            rename_pointer_keys("/damageflags/0/damageflags", &rename).as_deref(),
+            Some("/damageFlags/0/damageFlags")
+        );
+        assert_eq!(rename_pointer_keys("/name", &rename), None);
+        assert_eq!(
+            rename_serialized_keys(
+                r#"{"id": 1, "damageflags": {"splash": 1.50}, "list": [{"damageflags": true}]}"#,
+                &rename
+            )
+            .as_deref(),
+            Some(r#"{"id":1,"damageFlags":{"splash":1.50},"list":[{"damageFlags":true}]}"#)
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        // Rows keep the pointer they were parsed at, `parsed_at` differs from `index` once rows
+        // were inserted above
+        let row =
+            |index: usize, parsed_at: usize, cells: &[(&str, &str, ValueType)]| JsonArrayEntries {
+                entries: cells
+                    .iter()
+                    .chain([("", "{}", ValueType::Object(true, 0))].iter())
+                    .map(|(column, value, value_type)| FlatJsonValue {
+                        pointer: PointerKey {
+                            pointer: format!("/skills/{}{}", parsed_at, column),
+                            value_type: *value_type,
+                            depth: 2,
+                            position: 0,
+                            column_id: 0,
+                        },
+                        value: Some(value.to_string()),
+                    })
+                    .collect(),
+                index,
+            };
+        let array = vec![
+            row(
+                0,
+                0,
+                &[
+                    ("/#", "0", ValueType::Number),
+                    ("/id", "1", ValueType::Number),
+                    ("/type", "Offensive", ValueType::String),
+                    ("/passive", "false", ValueType::Bool),
+                ],
+            ),
+            row(
+                1,
+                3,
+                &[
+                    ("/#", "1", ValueType::Number),
+                    ("/id", "485", ValueType::Number),
+                    ("/type", "Supportive", ValueType::String),
+                    ("/damageflags/ignoreAtkCard", "true", ValueType::Bool),
+                ],
+            ),
+        ];
+        let rows = [0, 1];
+        let search = |term: &str, mode: ReplaceMode| {
+            let regex = search_regex(term, &mode).unwrap();
+            search_occurrences(&array, &rows, &regex)
+        };
+        assert_eq!(
+            search("offensive", ReplaceMode::Simple),
+            vec![(0, "/type".to_string())]
+        );
+        assert!(search("offensive", ReplaceMode::MatchingCase).is_empty());
+        assert_eq!(
+            search("485", ReplaceMode::ExactWord),
+            vec![(1, "/id".to_string())]
+        );
+        assert_eq!(
+            search("^(false|ignoreAtkCard)$", ReplaceMode::Regex),
+            vec![(0, "/passive".to_string())]
+        );
+        // Keys are matched once per column, not once per cell
+        let columns = ["", "/#", "/passive", "/damageflags/ignoreAtkCard"].map(|name| Column {
+            name: Cow::Borrowed(name),
+            depth: 1,
+            value_type: ValueType::Bool,
+            seen_count: 1,
+            order: 0,
+            id: 0,
+        });
+        let regex = search_regex("^(false|ignoreAtkCard)$", &ReplaceMode::Regex).unwrap();
+        assert_eq!(
+            search_keys(&columns, &regex),
+            vec!["/damageflags/ignoreAtkCard".to_string()]
+        );
+        let regex = search_regex("#", &ReplaceMode::Simple).unwrap();
+        assert!(search_keys(&columns, &regex).is_empty());
+        assert!(search_regex("(", &ReplaceMode::Regex).is_err());
+    }
+
+    #[test]
+    fn test_write_atomically_with_backup() {
+        let directory = std::env::temp_dir().join(format!("json-editor-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file_path = directory.join("data.json");
+        let backup_path = sibling_path(&file_path, "", ".bak");
+        let temporary_path = sibling_path(&file_path, ".", ".tmp");
+        fs::write(&file_path, "[1]").unwrap();
+        fs::write(&backup_path, "[0]").unwrap();
+
+        write_atomically(&file_path, Some(&backup_path), |file| {
+            file.write_all(b"[2]")
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[2]");
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "[1]");
+        assert!(!temporary_path.exists());
+
+        // A failing write keeps both the file and its backup
+        let result = write_atomically(&file_path, Some(&backup_path), |file| {
+            file.write_all(b"[3")?;
+            Err(std::io::Error::other("disk full"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[2]");
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "[1]");
+        assert!(!temporary_path.exists());
+
+        write_atomically(&directory.join("new.json"), Some(&backup_path), |file| {
+            file.write_all(b"[]")
+        })
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(directory.join("new.json")).unwrap(),
+            "[]"
+        );
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), "[1]");
+        assert!(!sibling_path(&backup_path, ".", ".tmp").exists());
+
+        // A failing rename does not leave the temporary file behind
+        let directory_path = directory.join("directory.json");
+        fs::create_dir_all(directory_path.join("child")).unwrap();
+        let result = write_atomically(&directory_path, None, |file| file.write_all(b"[]"));
+        assert!(result.is_err());
+        assert!(!sibling_path(&directory_path, ".", ".tmp").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_through_symlink() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let directory =
+            std::env::temp_dir().join(format!("json-editor-symlink-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file_path = directory.join("data.json");
+        let link_path = directory.join("link.json");
+        fs::write(&file_path, "[1]").unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&file_path, &link_path).unwrap();
+
+        write_atomically(&link_path, None, |file| file.write_all(b"[2]")).unwrap();
+        assert!(fs::symlink_metadata(&link_path)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "[2]");
+        assert_eq!(
+            fs::metadata(&file_path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_save_unchanged_by_default() {
+        let rows = ["{\n  \"b\": 1\n}", "{\"a\": \"x y\", \"c\": [ ]}"];
+        let array = serialized_rows(&rows);
+        let mut buffer = vec![];
+        save_to_buffer("/skills", &array, &mut buffer, &SaveOptions::default()).unwrap();
+        let separator = format!(",{}", LineEnding::default().as_str());
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            format!("{{\"skills\":[{}]}}", rows.join(&separator))
+        );
+    }
+
+    #[test]
+    fn test_save_row_per_line() {
+        let array = serialized_rows(&["{\n  \"b\": 1\n}", "{\"a\": \"x y\"}"]);
+        let mut buffer = vec![];
+        let save_options = SaveOptions {
+            format: SaveFormat::RowPerLine,
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        save_to_buffer("", &array, &mut buffer, &save_options).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "[{\"b\":1},\r\n{\"a\":\"x y\"}]"
+        );
+    }
+
+    #[test]
+    fn test_save_compact() {
+        let array = serialized_rows(&["{\n  \"b\": 1\n}", "{\"a\": \"x y\", \"c\": [ ]}"]);
+        let mut buffer = vec![];
+        let save_options = SaveOptions {
+            format: SaveFormat::Compact,
+            ..Default::default()
+        };
+        save_to_buffer("", &array, &mut buffer, &save_options).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            r#"[{"b":1},{"a":"x y","c":[]}]"#
+        );
+    }
+
+    #[test]
+    fn test_save_pretty_with_parent_pointer() {
+        let array = serialized_rows(&[r#"{"b":1,"a":[1,2.50]}"#, "{}"]);
+        let mut buffer = vec![];
+        let save_options = SaveOptions {
+            format: SaveFormat::Pretty,
+            indent_size: 2,
+            indent_with_tabs: false,
+            line_ending: LineEnding::Lf,
+            trailing_new_line: true,
+            sort_keys: true,
+        };
+        save_to_buffer("/skills", &array, &mut buffer, &save_options).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "{\n  \"skills\": [\n    {\n      \"a\": [\n        1,\n        2.50\n      ],\n      \"b\": 1\n    },\n    {}\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_template_value() {
+        assert_eq!(
+            template_value(ValueType::String, Some("orc")),
+            Some(String::new())
+        );
+        assert_eq!(
+            template_value(ValueType::Number, Some("12.5")),
+            Some("0".to_string())
+        );
+        assert_eq!(
+            template_value(ValueType::Array(2), Some("[1, 2]")),
+            Some("[]".to_string())
+        );
+        // Keys keep their order, nested arrays are emptied
+        assert_eq!(
+            template_value(
+                ValueType::Object(true, 4),
+                Some(r#"{"z": "a", "b": [{"c": 1}], "y": {"t": true, "n": null, "d": 1.5}}"#)
+            ),
+            Some(r#"{"z":"","b":[],"y":{"t":false,"n":null,"d":0}}"#.to_string())
+        );
+        assert_eq!(template_value(ValueType::Null, None), None);
+    }
 }
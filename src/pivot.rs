@@ -0,0 +1,331 @@
+//! Cross tabulation of rows: one pivot row per value of a column, one pivot column per value of
+//! another column, cells aggregate values of a third column.
+
+use crate::aggregates::{Aggregate, Aggregates};
+use crate::array_table::ArrayTable;
+use crate::parser::format::{JsonNode, JsonWriteOptions};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+const EMPTY_KEY: &str = "(empty)";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PivotExportFormat {
+    Csv,
+    Json,
+}
+
+pub struct Pivot {
+    pub row_column: String,
+    pub column_column: String,
+    pub value_column: String,
+    pub aggregate: Aggregate,
+    pub row_keys: Vec<Option<String>>,
+    pub column_keys: Vec<Option<String>>,
+    // Distinct labels of keys, in same order, a missing key and a "(empty)" value have distinct
+    // labels. Column labels also differ from row key name of json export.
+    pub row_labels: Vec<String>,
+    pub column_labels: Vec<String>,
+    // Indexed by row key then column key position
+    cells: Vec<Vec<Aggregates>>,
+}
+
+impl Pivot {
+    /// `values` are (row key, column key, value) of each row.
+    pub fn new(
+        row_column: String,
+        column_column: String,
+        value_column: String,
+        aggregate: Aggregate,
+        values: Vec<(Option<String>, Option<String>, Option<String>)>,
+    ) -> Self {
+        let row_keys = Self::sorted_keys(values.iter().map(|(row_key, _, _)| row_key));
+        let column_keys = Self::sorted_keys(values.iter().map(|(_, column_key, _)| column_key));
+        let row_positions = Self::positions(&row_keys);
+        let column_positions = Self::positions(&column_keys);
+        let mut cells = vec![vec![Aggregates::default(); column_keys.len()]; row_keys.len()];
+        for (row_key, column_key, value) in values.iter() {
+            let value = match aggregate {
+                // Count non empty values, whatever their type
+                Aggregate::Count => value.as_ref().map(|_| 0.0),
+                _ => value.as_ref().and_then(|value| value.parse::<f64>().ok()),
+            };
+            if let Some(value) = value {
+                cells[row_positions[row_key]][column_positions[column_key]].add(value);
+            }
+        }
+        let row_labels = unique_labels(&row_keys, &[]);
+        let column_labels = unique_labels(&column_keys, &[Self::row_key_name(&row_column)]);
+        Self {
+            row_column,
+            column_column,
+            value_column,
+            aggregate,
+            row_keys,
+            column_keys,
+            row_labels,
+            column_labels,
+            cells,
+        }
+    }
+
+    fn sorted_keys<'a>(keys: impl Iterator<Item = &'a Option<String>>) -> Vec<Option<String>> {
+        let mut keys = keys.cloned().collect::<Vec<Option<String>>>();
+        keys.sort_by(|a, b| ArrayTable::compare_cell_values(a.as_ref(), b.as_ref()));
+        keys.dedup();
+        keys
+    }
+
+    fn positions(keys: &[Option<String>]) -> HashMap<Option<String>, usize> {
+        keys.iter()
+            .enumerate()
+            .map(|(position, key)| (key.clone(), position))
+            .collect()
+    }
+
+    fn row_key_name(row_column: &str) -> &str {
+        row_column.trim_start_matches('/')
+    }
+
+    pub fn value(&self, row: usize, column: usize) -> Option<f64> {
+        self.cells[row][column].get(self.aggregate)
+    }
+
+    pub fn formatted_value(&self, row: usize, column: usize) -> String {
+        self.cells[row][column].format(self.aggregate)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let header = std::iter::once(self.row_column.as_str())
+            .chain(self.column_labels.iter().map(String::as_str))
+            .map(csv_field)
+            .collect::<Vec<String>>()
+            .join(",");
+        csv.push_str(&header);
+        csv.push('\n');
+        for (row, row_label) in self.row_labels.iter().enumerate() {
+            csv.push_str(&csv_field(row_label));
+            for column in 0..self.column_keys.len() {
+                csv.push(',');
+                csv.push_str(&self.formatted_value(row, column));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// An object per pivot row, keyed by row column name then column labels in pivot order.
+    pub fn to_json(&self) -> String {
+        let row_key_name = quote(Self::row_key_name(&self.row_column));
+        let rows = self
+            .row_keys
+            .iter()
+            .enumerate()
+            .map(|(row, row_key)| {
+                let mut object = Vec::with_capacity(self.column_keys.len() + 1);
+                let row_key = row_key.as_deref().map_or("null".to_string(), quote);
+                object.push((
+                    Cow::Borrowed(row_key_name.as_str()),
+                    JsonNode::Scalar(Cow::Owned(row_key)),
+                ));
+                for (column, column_label) in self.column_labels.iter().enumerate() {
+                    let value = self
+                        .value(row, column)
+                        .and_then(serde_json::Number::from_f64)
+                        .map_or("null".to_string(), |number| number.to_string());
+                    object.push((
+                        Cow::Owned(quote(column_label)),
+                        JsonNode::Scalar(Cow::Owned(value)),
+                    ));
+                }
+                JsonNode::Object(object)
+            })
+            .collect::<Vec<JsonNode>>();
+        let mut json = String::new();
+        let write_options = JsonWriteOptions {
+            pretty: true,
+            indent: "  ",
+            new_line: "\n",
+        };
+        JsonNode::Array(rows).write(&mut json, &write_options, 0);
+        json
+    }
+
+    /// Write pivot in a file picked by user.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export(&self, format: PivotExportFormat) -> Result<(), String> {
+        let (filter_name, extension, content) = match format {
+            PivotExportFormat::Csv => ("CSV", "csv", self.to_csv()),
+            PivotExportFormat::Json => ("JSON", "json", self.to_json()),
+        };
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("pivot.{}", extension))
+            .add_filter(filter_name, &[extension])
+            .save_file()
+        {
+            std::fs::write(&path, content)
+                .map_err(|e| format!("Failed to write pivot {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Label of each key, `EMPTY_KEY` for a missing key. A label already taken, or `reserved`, gets
+/// a numbered suffix.
+fn unique_labels(keys: &[Option<String>], reserved: &[&str]) -> Vec<String> {
+    let mut taken = reserved
+        .iter()
+        .map(|label| label.to_string())
+        .collect::<HashSet<String>>();
+    // Present keys keep their label, a missing key or a reserved one gets the suffix
+    taken.extend(keys.iter().flatten().cloned());
+    let mut labels = Vec::with_capacity(keys.len());
+    for key in keys.iter() {
+        let label = match key {
+            Some(key) if !reserved.contains(&key.as_str()) => key.clone(),
+            key => {
+                let base = key.as_deref().unwrap_or(EMPTY_KEY);
+                let mut label = base.to_string();
+                let mut suffix = 2;
+                while taken.contains(&label) {
+                    label = format!("{} ({})", base, suffix);
+                    suffix += 1;
+                }
+                taken.insert(label.clone());
+                label
+            }
+        };
+        labels.push(label);
+    }
+    labels
+}
+
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aggregates::Aggregate;
+    use crate::pivot::{csv_field, Pivot};
+
+    fn key(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    fn pivot(aggregate: Aggregate) -> Pivot {
+        let values = vec![
+            (key("orc"), key("forest"), key("10")),
+            (key("orc"), key("forest"), key("20")),
+            (key("orc"), key("cave"), key("5")),
+            (key("elf"), key("forest"), key("8")),
+            (key("elf"), None, key("not a number")),
+            (None, key("cave"), key("1")),
+        ];
+        Pivot::new(
+            "/race".to_string(),
+            "/area".to_string(),
+            "/hp".to_string(),
+            aggregate,
+            values,
+        )
+    }
+
+    #[test]
+    fn test_new() {
+        let pivot = pivot(Aggregate::Sum);
+        // Missing keys come last
+        assert_eq!(pivot.row_keys, vec![key("elf"), key("orc"), None]);
+        assert_eq!(pivot.column_keys, vec![key("cave"), key("forest"), None]);
+        assert_eq!(pivot.row_labels, vec!["elf", "orc", "(empty)"]);
+        assert_eq!(pivot.value(1, 1), Some(30.0));
+        assert_eq!(pivot.value(1, 0), Some(5.0));
+        assert_eq!(pivot.value(2, 0), Some(1.0));
+        assert_eq!(pivot.value(2, 1), None);
+        // Non numeric values are ignored, except by count
+        assert_eq!(pivot.value(0, 2), None);
+        let pivot = self::pivot(Aggregate::Count);
+        assert_eq!(pivot.value(0, 2), Some(1.0));
+        assert_eq!(pivot.value(1, 1), Some(2.0));
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(
+            pivot(Aggregate::Avg).to_csv(),
+            "/race,cave,forest,(empty)\nelf,,8,\norc,5,15,\n(empty),1,,\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = pivot(Aggregate::Max).to_json();
+        // Keys are in pivot order, row key first
+        let expected = r#"[
+  {
+    "race": "elf",
+    "cave": null,
+    "forest": 8.0,
+    "(empty)": null
+  },
+  {
+    "race": "orc",
+    "cave": 5.0,
+    "forest": 20.0,
+    "(empty)": null
+  },
+  {
+    "race": null,
+    "cave": 1.0,
+    "forest": null,
+    "(empty)": null
+  }
+]"#;
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_duplicate_labels() {
+        let values = vec![
+            (key("(empty)"), key("race"), key("1")),
+            (None, key("(empty)"), key("2")),
+            (key("orc"), None, key("3")),
+        ];
+        let pivot = Pivot::new(
+            "/race".to_string(),
+            "/area".to_string(),
+            "/hp".to_string(),
+            Aggregate::Sum,
+            values,
+        );
+        assert_eq!(pivot.row_labels, vec!["(empty)", "orc", "(empty) (2)"]);
+        assert_eq!(
+            pivot.column_labels,
+            vec!["(empty)", "race (2)", "(empty) (2)"]
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&pivot.to_json()).unwrap();
+        assert_eq!(json[0]["race"], "(empty)");
+        assert_eq!(json[0]["race (2)"], 1.0);
+        assert_eq!(json[1]["race"], "orc");
+        assert_eq!(json[1]["(empty) (2)"], 3.0);
+        assert_eq!(json[2]["race"], serde_json::Value::Null);
+        assert_eq!(json[2]["(empty)"], 2.0);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("orc"), "orc");
+        assert_eq!(csv_field("orc, elf"), "\"orc, elf\"");
+        assert_eq!(csv_field("the \"boss\""), "\"the \"\"boss\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,697 @@
+//! Query rows with a subset of JSONPath or jq.
+//!
+//! Both syntaxes are compiled to the same [`Query`]: a selection of rows, optionally followed by a path
+//! projecting a value out of each selected row.
+//!
+//! Supported JSONPath: `$`, `$[*]`, `$[n]`, `$[?(predicate)]` then `.key`, `['key']`, `[n]`, `[*]`, `.*`.
+//! Supported jq: `.[]`, `.[n]`, `map(select(predicate))`, `| select(predicate)` then `| .key.path`.
+//! Predicates combine comparisons (`==`, `!=`, `<`, `<=`, `>`, `>=`) of row paths (`@.key` or `.key`) and
+//! literals with `&&`/`and`, `||`/`or`, `!`/`not`. Regex match is `@.key =~ /re/i` or `.key | test("re"; "i")`.
+
+use std::cmp::Ordering;
+
+use json_flat_parser::JsonArrayEntries;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use regex_lite::Regex;
+use serde_json::Value;
+
+use crate::cursor::Cursor;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    // Negative index counts from the end
+    Index(i64),
+    Wildcard,
+}
+
+#[derive(Debug)]
+enum Operand {
+    Path(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+enum Predicate {
+    Compare(Operand, CompareOperator, Operand),
+    Matches(Operand, Regex),
+    Truthy(Operand),
+    Not(Box<Predicate>),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+#[derive(Debug)]
+enum RowSelector {
+    All,
+    Index(i64),
+    Filter(Vec<Predicate>),
+}
+
+#[derive(Debug)]
+pub struct Query {
+    rows: RowSelector,
+    projection: Vec<Segment>,
+}
+
+pub enum QueryResult {
+    // Index of selected rows, in ascending order
+    Rows(Vec<usize>),
+    // Values projected out of selected rows, `name` is the last key of the projection
+    Values { name: String, values: Vec<Value> },
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut parser = QueryParser {
+            cursor: Cursor::new(query.trim()),
+            jq: false,
+        };
+        let query = if parser.cursor.eat("$") {
+            parser.parse_jsonpath()?
+        } else if parser.cursor.peek() == Some('.') || parser.cursor.input.starts_with("map(") {
+            parser.jq = true;
+            parser.parse_jq()?
+        } else {
+            return Err("Query should start with \"$\" (JSONPath) or \".\" (jq)".to_string());
+        };
+        if !parser.cursor.is_at_end() {
+            return Err(parser.cursor.error("Unexpected content"));
+        }
+        Ok(query)
+    }
+
+    pub fn run(&self, rows: &[JsonArrayEntries<String>]) -> QueryResult {
+        let selected_rows = match self.rows {
+            RowSelector::All => (0..rows.len()).collect::<Vec<usize>>(),
+            RowSelector::Index(index) => {
+                let index = if index < 0 {
+                    rows.len() as i64 + index
+                } else {
+                    index
+                };
+                if index >= 0 && (index as usize) < rows.len() {
+                    vec![index as usize]
+                } else {
+                    vec![]
+                }
+            }
+            RowSelector::Filter(ref predicates) => rows
+                .par_iter()
+                .enumerate()
+                .filter(|(_, row)| {
+                    row_value(row).is_some_and(|value| predicates.iter().all(|p| p.eval(&value)))
+                })
+                .map(|(index, _)| index)
+                .collect(),
+        };
+        if self.projection.is_empty() {
+            return QueryResult::Rows(selected_rows);
+        }
+        let values = selected_rows
+            .par_iter()
+            .flat_map_iter(|index| {
+                let mut values = vec![];
+                if let Some(row) = row_value(&rows[*index]) {
+                    resolve(&row, &self.projection, &mut |value| {
+                        values.push(value.clone())
+                    });
+                }
+                values
+            })
+            .collect();
+        let name = self
+            .projection
+            .iter()
+            .rev()
+            .find_map(|segment| match segment {
+                Segment::Key(key) => Some(key.clone()),
+                _ => None,
+            })
+            .unwrap_or("value".to_string());
+        QueryResult::Values { name, values }
+    }
+}
+
+#[inline]
+pub(crate) fn row_value(row: &JsonArrayEntries<String>) -> Option<Value> {
+    let serialized_row = row.entries().last()?.value.as_ref()?;
+    serde_json::from_str(serialized_row).ok()
+}
+
+/// Call `f` with every value matching `path`, several when path contains wildcards.
+fn resolve<'a>(value: &'a Value, path: &[Segment], f: &mut impl FnMut(&'a Value)) {
+    let Some((segment, rest)) = path.split_first() else {
+        f(value);
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => {
+            if let Some(child) = map.get(key) {
+                resolve(child, rest, f);
+            }
+        }
+        (Segment::Index(index), Value::Array(elements)) => {
+            let index = if *index < 0 {
+                elements.len() as i64 + index
+            } else {
+                *index
+            };
+            if let Some(child) = usize::try_from(index).ok().and_then(|i| elements.get(i)) {
+                resolve(child, rest, f);
+            }
+        }
+        (Segment::Wildcard, Value::Array(elements)) => {
+            elements.iter().for_each(|child| resolve(child, rest, f))
+        }
+        (Segment::Wildcard, Value::Object(map)) => {
+            map.values().for_each(|child| resolve(child, rest, f))
+        }
+        _ => {}
+    }
+}
+
+impl Operand {
+    fn values<'a>(&'a self, row: &'a Value) -> Vec<&'a Value> {
+        match self {
+            Operand::Literal(value) => vec![value],
+            Operand::Path(path) => {
+                let mut values = vec![];
+                resolve(row, path, &mut |value| values.push(value));
+                values
+            }
+        }
+    }
+}
+
+impl Predicate {
+    fn eval(&self, row: &Value) -> bool {
+        match self {
+            // A path matching several values (wildcard) satisfies the comparison when any of them does
+            Predicate::Compare(left, operator, right) => {
+                let right_values = right.values(row);
+                left.values(row).iter().any(|left| {
+                    right_values
+                        .iter()
+                        .any(|right| compare(left, *operator, right))
+                })
+            }
+            Predicate::Matches(operand, regex) => operand
+                .values(row)
+                .iter()
+                .any(|value| value.as_str().is_some_and(|value| regex.is_match(value))),
+            Predicate::Truthy(operand) => operand
+                .values(row)
+                .iter()
+                .any(|value| !matches!(value, Value::Null | Value::Bool(false))),
+            Predicate::Not(predicate) => !predicate.eval(row),
+            Predicate::And(left, right) => left.eval(row) && right.eval(row),
+            Predicate::Or(left, right) => left.eval(row) || right.eval(row),
+        }
+    }
+}
+
+fn compare(left: &Value, operator: CompareOperator, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64().partial_cmp(&right.as_f64()),
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (left, right) if left == right => Some(Ordering::Equal),
+        _ => None,
+    };
+    match operator {
+        CompareOperator::Eq => ordering == Some(Ordering::Equal),
+        CompareOperator::Ne => ordering != Some(Ordering::Equal),
+        CompareOperator::Lt => ordering == Some(Ordering::Less),
+        CompareOperator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        CompareOperator::Gt => ordering == Some(Ordering::Greater),
+        CompareOperator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+struct QueryParser<'a> {
+    cursor: Cursor<'a>,
+    // Relative paths start with "." in jq instead of "@." in JSONPath
+    jq: bool,
+}
+
+impl QueryParser<'_> {
+    fn parse_jsonpath(&mut self) -> Result<Query, String> {
+        let mut rows = RowSelector::All;
+        if self.cursor.eat("[?") {
+            let has_parenthesis = self.cursor.eat("(");
+            let predicate = self.parse_or()?;
+            if has_parenthesis {
+                self.cursor.expect(")")?;
+            }
+            self.cursor.expect("]")?;
+            rows = RowSelector::Filter(vec![predicate]);
+        } else if self.cursor.eat("[*]") || self.cursor.eat(".*") {
+        } else if self.cursor.peek() == Some('[') {
+            match self.parse_bracket_segment()? {
+                Segment::Index(index) => rows = RowSelector::Index(index),
+                Segment::Wildcard => {}
+                Segment::Key(_) => {
+                    return Err(self.cursor.error("Root is an array, expected index"))
+                }
+            }
+        }
+        let projection = self.parse_path()?;
+        Ok(Query { rows, projection })
+    }
+
+    fn parse_jq(&mut self) -> Result<Query, String> {
+        let rows = if self.cursor.eat("map(") {
+            self.expect_keyword("select")?;
+            let predicate = self.parse_select_arguments()?;
+            self.cursor.expect(")")?;
+            self.expect_pipe_iterate()?;
+            RowSelector::Filter(vec![predicate])
+        } else if self.cursor.eat(".[]") {
+            RowSelector::All
+        } else if self.cursor.eat(".[") {
+            let index = self.parse_integer()?;
+            self.cursor.expect("]")?;
+            RowSelector::Index(index)
+        } else {
+            return Err(self.cursor.error("Expected \".[]\""));
+        };
+        let mut predicates = vec![];
+        let mut projection = self.parse_path()?;
+        while self.cursor.eat("|") {
+            self.cursor.skip_whitespace();
+            if self.eat_keyword("select") {
+                if !projection.is_empty() {
+                    return Err(self.cursor.error("select after a path is not supported"));
+                }
+                predicates.push(self.parse_select_arguments()?);
+            } else if self.cursor.peek() == Some('.') {
+                self.cursor.advance();
+                projection.extend(self.parse_path_after_dot()?);
+            } else {
+                return Err(self.cursor.error("Expected select(...) or a path"));
+            }
+        }
+        let rows = match rows {
+            RowSelector::All if !predicates.is_empty() => RowSelector::Filter(predicates),
+            RowSelector::Filter(mut existing) => {
+                existing.extend(predicates);
+                RowSelector::Filter(existing)
+            }
+            rows if predicates.is_empty() => rows,
+            _ => {
+                return Err(self
+                    .cursor
+                    .error("select can not be applied to a single row"))
+            }
+        };
+        Ok(Query { rows, projection })
+    }
+
+    // After "map(select(...))" rows are an array again, they have to be iterated with "| .[]"
+    fn expect_pipe_iterate(&mut self) -> Result<(), String> {
+        let position = self.cursor.position;
+        if self.cursor.eat("|") && self.cursor.eat(".[]") {
+            return Ok(());
+        }
+        self.cursor.position = position;
+        if !self.cursor.is_at_end() {
+            return Err(self
+                .cursor
+                .error("Expected \"| .[]\" after map(select(...))"));
+        }
+        Ok(())
+    }
+
+    fn parse_select_arguments(&mut self) -> Result<Predicate, String> {
+        self.cursor.expect("(")?;
+        let mut predicate = self.parse_or()?;
+        // jq negation: "select(.a == 1 | not)"
+        while self.cursor.eat("|") {
+            self.expect_keyword("not")?;
+            predicate = Predicate::Not(Box::new(predicate));
+        }
+        self.cursor.expect(")")?;
+        Ok(predicate)
+    }
+
+    fn parse_or(&mut self) -> Result<Predicate, String> {
+        let mut left = self.parse_and()?;
+        while self.cursor.eat("||") || self.eat_keyword("or") {
+            let right = self.parse_and()?;
+            left = Predicate::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Predicate, String> {
+        let mut left = self.parse_unary()?;
+        while self.cursor.eat("&&") || self.eat_keyword("and") {
+            let right = self.parse_unary()?;
+            left = Predicate::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Predicate, String> {
+        if self.cursor.eat("!") && !self.cursor.rest().starts_with('=') {
+            return Ok(Predicate::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.cursor.eat("(") {
+            let predicate = self.parse_or()?;
+            self.cursor.expect(")")?;
+            return Ok(predicate);
+        }
+        let left = self.parse_operand()?;
+        if self.cursor.eat("=~") {
+            return Ok(Predicate::Matches(left, self.parse_regex_literal()?));
+        }
+        let position = self.cursor.position;
+        if self.jq && self.cursor.eat("|") && self.eat_keyword("test") {
+            return Ok(Predicate::Matches(left, self.parse_test_arguments()?));
+        }
+        self.cursor.position = position;
+        let operator = if self.cursor.eat("==") {
+            CompareOperator::Eq
+        } else if self.cursor.eat("!=") {
+            CompareOperator::Ne
+        } else if self.cursor.eat("<=") {
+            CompareOperator::Le
+        } else if self.cursor.eat(">=") {
+            CompareOperator::Ge
+        } else if self.cursor.eat("<") {
+            CompareOperator::Lt
+        } else if self.cursor.eat(">") {
+            CompareOperator::Gt
+        } else {
+            return Ok(Predicate::Truthy(left));
+        };
+        let right = self.parse_operand()?;
+        Ok(Predicate::Compare(left, operator, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        self.cursor.skip_whitespace();
+        match self.cursor.peek() {
+            Some('@') if !self.jq => {
+                self.cursor.advance();
+                Ok(Operand::Path(self.parse_path()?))
+            }
+            Some('.') if self.jq => {
+                self.cursor.advance();
+                Ok(Operand::Path(self.parse_path_after_dot()?))
+            }
+            Some('"') | Some('\'') => {
+                Ok(Operand::Literal(Value::String(self.cursor.parse_string()?)))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                self.cursor.parse_number().map(Operand::Literal)
+            }
+            _ => {
+                if self.eat_keyword("true") {
+                    Ok(Operand::Literal(Value::Bool(true)))
+                } else if self.eat_keyword("false") {
+                    Ok(Operand::Literal(Value::Bool(false)))
+                } else if self.eat_keyword("null") {
+                    Ok(Operand::Literal(Value::Null))
+                } else {
+                    Err(self.cursor.error("Expected a path or a literal"))
+                }
+            }
+        }
+    }
+
+    /// jq path right after its leading ".", which alone stands for the current value.
+    fn parse_path_after_dot(&mut self) -> Result<Vec<Segment>, String> {
+        let mut path = vec![];
+        if self.cursor.peek().is_some_and(is_identifier_char) {
+            path.push(Segment::Key(self.parse_identifier()));
+        }
+        path.extend(self.parse_path()?);
+        Ok(path)
+    }
+
+    fn parse_path(&mut self) -> Result<Vec<Segment>, String> {
+        let mut path = vec![];
+        loop {
+            match self.cursor.peek() {
+                Some('.') if !self.cursor.rest().starts_with(".[]") || self.jq => {
+                    self.cursor.advance();
+                    if self.cursor.eat("*") {
+                        path.push(Segment::Wildcard);
+                    } else if self.cursor.peek() == Some('[') {
+                        path.push(self.parse_bracket_segment()?);
+                    } else if self.cursor.peek().is_some_and(is_identifier_char) {
+                        path.push(Segment::Key(self.parse_identifier()));
+                    } else {
+                        return Err(self.cursor.error("Expected a key"));
+                    }
+                }
+                Some('[') => path.push(self.parse_bracket_segment()?),
+                _ => return Ok(path),
+            }
+        }
+    }
+
+    fn parse_bracket_segment(&mut self) -> Result<Segment, String> {
+        self.cursor.expect("[")?;
+        self.cursor.skip_whitespace();
+        let segment = match self.cursor.peek() {
+            Some(']') => Segment::Wildcard,
+            Some('*') => {
+                self.cursor.advance();
+                Segment::Wildcard
+            }
+            Some('"') | Some('\'') => Segment::Key(self.cursor.parse_string()?),
+            _ => Segment::Index(self.parse_integer()?),
+        };
+        self.cursor.expect("]")?;
+        Ok(segment)
+    }
+
+    fn parse_integer(&mut self) -> Result<i64, String> {
+        self.cursor.skip_whitespace();
+        let start = self.cursor.position;
+        if self.cursor.peek() == Some('-') {
+            self.cursor.advance();
+        }
+        self.cursor.take_while(|c| c.is_ascii_digit());
+        self.cursor.input[start..self.cursor.position]
+            .parse::<i64>()
+            .map_err(|_| self.cursor.error("Expected an integer"))
+    }
+
+    fn parse_identifier(&mut self) -> String {
+        self.cursor.take_while(is_identifier_char).to_string()
+    }
+
+    fn parse_regex_literal(&mut self) -> Result<Regex, String> {
+        self.cursor.skip_whitespace();
+        if !self.cursor.eat("/") {
+            return Err(self.cursor.error("Expected /regex/"));
+        }
+        let start = self.cursor.position;
+        while let Some(c) = self.cursor.peek() {
+            if c == '/' {
+                break;
+            }
+            self.cursor.advance();
+            if c == '\\' {
+                self.cursor.advance();
+            }
+        }
+        let pattern = self.cursor.input[start..self.cursor.position].replace("\\/", "/");
+        self.cursor.expect("/")?;
+        let case_insensitive = self.cursor.peek() == Some('i');
+        if case_insensitive {
+            self.cursor.advance();
+        }
+        build_regex(&pattern, case_insensitive)
+    }
+
+    // test("regex") or test("regex"; "flags")
+    fn parse_test_arguments(&mut self) -> Result<Regex, String> {
+        self.cursor.expect("(")?;
+        self.cursor.skip_whitespace();
+        let pattern = self.cursor.parse_string()?;
+        let flags = if self.cursor.eat(";") {
+            self.cursor.skip_whitespace();
+            self.cursor.parse_string()?
+        } else {
+            String::new()
+        };
+        self.cursor.expect(")")?;
+        build_regex(&pattern, flags.contains('i'))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.cursor.skip_whitespace();
+        let rest = self.cursor.rest();
+        if rest.starts_with(keyword)
+            && !rest[keyword.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_identifier_char)
+        {
+            self.cursor.position += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.cursor.error(&format!("Expected \"{}\"", keyword)))
+        }
+    }
+}
+
+#[inline]
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '$'
+}
+
+fn build_regex(pattern: &str, case_insensitive: bool) -> Result<Regex, String> {
+    let pattern = if case_insensitive {
+        format!("(?i){}", pattern)
+    } else {
+        pattern.to_string()
+    };
+    Regex::new(&pattern).map_err(|e| format!("Invalid regex: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{Query, QueryResult};
+    use json_flat_parser::{FlatJsonValue, JsonArrayEntries, PointerKey, ValueType};
+    use serde_json::json;
+
+    fn rows(rows: &[&str]) -> Vec<JsonArrayEntries<String>> {
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| JsonArrayEntries {
+                entries: vec![FlatJsonValue {
+                    pointer: PointerKey {
+                        pointer: format!("/{}", index),
+                        value_type: ValueType::Object(true, 0),
+                        depth: 1,
+                        position: 0,
+                        column_id: 0,
+                    },
+                    value: Some(row.to_string()),
+                }],
+                index,
+            })
+            .collect()
+    }
+
+    fn selected_rows(query: &str, rows: &[JsonArrayEntries<String>]) -> Vec<usize> {
+        match Query::parse(query).unwrap().run(rows) {
+            QueryResult::Rows(selected_rows) => selected_rows,
+            QueryResult::Values { .. } => panic!("{} should select rows", query),
+        }
+    }
+
+    #[test]
+    fn test_jsonpath() {
+        let rows = rows(&[
+            r#"{"name": "Poring", "level": 1, "stats": {"hp": 50}}"#,
+            r#"{"name": "Drops", "level": 3, "stats": {"hp": 55}}"#,
+            r#"{"name": "Baphomet", "level": 81, "stats": {"hp": 668000}}"#,
+        ]);
+        assert_eq!(selected_rows("$[*]", &rows), vec![0, 1, 2]);
+        assert_eq!(selected_rows("$[-1]", &rows), vec![2]);
+        assert_eq!(selected_rows("$[?(@.level > 2)]", &rows), vec![1, 2]);
+        assert_eq!(
+            selected_rows("$[?(@.stats.hp < 100 && !(@.name == 'Poring'))]", &rows),
+            vec![1]
+        );
+        assert_eq!(selected_rows("$[?(@.name =~ /^b/i)]", &rows), vec![2]);
+        let QueryResult::Values { name, values } =
+            Query::parse("$[*].stats.hp").unwrap().run(&rows)
+        else {
+            panic!("projection should return values");
+        };
+        assert_eq!(name, "hp");
+        assert_eq!(values, vec![json!(50), json!(55), json!(668000)]);
+    }
+
+    #[test]
+    fn test_jq() {
+        let rows = rows(&[
+            r#"{"name": "Poring", "level": 1, "tags": ["plant"]}"#,
+            r#"{"name": "Drops", "level": 3, "tags": []}"#,
+            r#"{"name": "Baphomet", "level": 81, "tags": ["boss", "demon"]}"#,
+        ]);
+        assert_eq!(selected_rows(".[]", &rows), vec![0, 1, 2]);
+        assert_eq!(selected_rows(".[1]", &rows), vec![1]);
+        assert_eq!(
+            selected_rows("map(select(.level >= 3)) | .[]", &rows),
+            vec![1, 2]
+        );
+        assert_eq!(
+            selected_rows(
+                ".[] | select(.level > 1) | select(.name == \"Drops\" | not)",
+                &rows
+            ),
+            vec![2]
+        );
+        assert_eq!(
+            selected_rows(".[] | select(.name | test(\"^p\"; \"i\"))", &rows),
+            vec![0]
+        );
+        let QueryResult::Values { name, values } =
+            Query::parse(".[] | .tags[0]").unwrap().run(&rows)
+        else {
+            panic!("projection should return values");
+        };
+        assert_eq!(name, "tags");
+        assert_eq!(values, vec![json!("plant"), json!("boss")]);
+    }
+
+    #[test]
+    fn test_non_ascii_query() {
+        let rows = rows(&[r#"{"nom": "é", "ville": "Genève"}"#, r#"{"nom": "a"}"#]);
+        assert_eq!(
+            selected_rows("$[?(@.nom\u{a0}==\u{3000}'é')]", &rows),
+            vec![0]
+        );
+        assert_eq!(
+            selected_rows(".[] | select(.ville == \"Genève\")", &rows),
+            vec![0]
+        );
+        assert!(Query::parse("$[?(@.é == 1)]\u{2003}").is_ok());
+    }
+
+    #[test]
+    fn test_malformed_query() {
+        for query in [
+            "",
+            "name",
+            "$[?(@.level > )]",
+            "$[?(@.level > 1]",
+            "$.a.",
+            "$[?(@.name == 'Poring)]",
+            "$[?(@.name =~ /[/)]",
+            ".[] | select(.a",
+            ".[1] | select(.a == 1)",
+            "map(select(.a)) | .name",
+            ".[] | é",
+            "$[?(@.a == 1)] é",
+        ] {
+            assert!(Query::parse(query).is_err(), "{} should not parse", query);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Autosave journal of unsaved edits.
+//!
+//! The journal is written next to the edited file as `.{file name}.recovery`. Its first line is
+//! the parent pointer of the edited array serialized as a json string, the rest is the document
+//! as it would have been saved. It is removed after a successful save or when the app exits
+//! normally, so finding one means the previous session did not end well. It is only deleted once
+//! the user decided to restore or discard it.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use json_flat_parser::JsonArrayEntries;
+
+use crate::parser::{save_to_buffer, sibling_path, write_atomically, SaveOptions};
+
+pub struct RecoveryJournal {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    /// The file was modified after the journal was written, restoring would drop those changes.
+    pub file_changed_since: bool,
+}
+
+pub fn journal_path(file_path: &Path) -> PathBuf {
+    sibling_path(file_path, ".", ".recovery")
+}
+
+pub fn write_journal(
+    file_path: &Path,
+    parent_pointer: &str,
+    array: &[JsonArrayEntries<String>],
+    save_options: &SaveOptions,
+) -> std::io::Result<()> {
+    write_atomically(&journal_path(file_path), None, |file| {
+        serde_json::to_writer(&mut *file, parent_pointer)?;
+        file.write_all(b"\n")?;
+        save_to_buffer(parent_pointer, array, file, save_options)
+    })
+}
+
+/// Journal left by a previous session for `file_path`, if any.
+pub fn find_journal(file_path: &Path) -> Option<RecoveryJournal> {
+    let path = journal_path(file_path);
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    let file_modified = fs::metadata(file_path).and_then(|m| m.modified()).ok();
+    Some(RecoveryJournal {
+        path,
+        modified,
+        file_changed_since: file_modified.is_some_and(|file_modified| file_modified > modified),
+    })
+}
+
+/// Returns parent pointer and content of the journal.
+pub fn read_journal(journal_path: &Path) -> std::io::Result<(String, Vec<u8>)> {
+    let mut reader = BufReader::new(fs::File::open(journal_path)?);
+    let mut first_line = String::new();
+    reader.read_line(&mut first_line)?;
+    let parent_pointer: String = serde_json::from_str(first_line.trim_end())?;
+    let mut content = vec![];
+    reader.read_to_end(&mut content)?;
+    Ok((parent_pointer, content))
+}
+
+pub fn delete_journal(file_path: &Path) {
+    let path = journal_path(file_path);
+    if path.exists() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::SaveOptions;
+    use crate::recovery::{delete_journal, find_journal, read_journal, write_journal};
+    use json_flat_parser::{FlatJsonValue, JsonArrayEntries, PointerKey, ValueType};
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_journal_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("json-editor-recovery-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file_path = directory.join("data.json");
+        fs::write(&file_path, "{\"skills\":[]}").unwrap();
+        let array = vec![JsonArrayEntries {
+            entries: vec![FlatJsonValue {
+                pointer: PointerKey {
+                    pointer: "/skills/0".to_string(),
+                    value_type: ValueType::Object(true, 0),
+                    depth: 2,
+                    position: 0,
+                    column_id: 0,
+                },
+                value: Some("{\"id\": 1}".to_string()),
+            }],
+            index: 0,
+        }];
+
+        write_journal(&file_path, "/skills", &array, &SaveOptions::default()).unwrap();
+        let journal = find_journal(&file_path).unwrap();
+        assert!(!journal.file_changed_since);
+        let (parent_pointer, content) = read_journal(&journal.path).unwrap();
+        assert_eq!(parent_pointer, "/skills");
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "{\"skills\":[{\"id\": 1}]}"
+        );
+
+        delete_journal(&file_path);
+        assert!(find_journal(&file_path).is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_journal_older_than_file_is_kept() {
+        let directory =
+            std::env::temp_dir().join(format!("json-editor-recovery-older-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file_path = directory.join("data.json");
+        fs::write(&file_path, "{\"skills\":[]}").unwrap();
+        write_journal(&file_path, "/skills", &[], &SaveOptions::default()).unwrap();
+        let journal_modified = find_journal(&file_path).unwrap().modified;
+        let file = fs::File::options().write(true).open(&file_path).unwrap();
+        file.set_modified(journal_modified + Duration::from_secs(60))
+            .unwrap();
+        drop(file);
+
+        let journal = find_journal(&file_path).unwrap();
+        assert!(journal.file_changed_since);
+        // Looking the journal up twice does not remove it, only the user decision does
+        assert!(find_journal(&file_path).is_some());
+        assert!(read_journal(&journal.path).is_ok());
+
+        delete_journal(&file_path);
+        assert!(find_journal(&file_path).is_none());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1 +1,406 @@
+//! Preview of a search and replace or of a transform: every value that would change, with its old
+//! and new value, so occurrences can be unticked before replacements are applied.
 
+use crate::computed_column::number_value;
+use crate::panels::ReplaceScope;
+use eframe::emath::Align;
+use egui::{RichText, Ui};
+use json_flat_parser::FlatJsonValue;
+use serde_json::Value;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransformKind {
+    #[default]
+    Multiply,
+    Add,
+    Round,
+    Clamp,
+    Toggle,
+    Set,
+}
+
+impl TransformKind {
+    pub const ALL: [TransformKind; 6] = [
+        TransformKind::Multiply,
+        TransformKind::Add,
+        TransformKind::Round,
+        TransformKind::Clamp,
+        TransformKind::Toggle,
+        TransformKind::Set,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransformKind::Multiply => "x * n",
+            TransformKind::Add => "x + n",
+            TransformKind::Round => "round",
+            TransformKind::Clamp => "clamp",
+            TransformKind::Toggle => "toggle boolean",
+            TransformKind::Set => "set to value",
+        }
+    }
+}
+
+// f64 has about 15 significant decimal digits
+const MAX_ROUND_DECIMALS: i32 = 15;
+
+/// Typed transform of a value, numeric ones skip values which are not numbers.
+#[derive(Clone, Debug)]
+pub enum Transform {
+    Multiply(f64),
+    Add(f64),
+    // Number of decimals, up to MAX_ROUND_DECIMALS
+    Round(i32),
+    Clamp(f64, f64),
+    Toggle,
+    // Also set on rows missing the key
+    Set(Value),
+}
+
+impl Transform {
+    pub fn new(kind: TransformKind, operand: &str, operand2: &str) -> Result<Self, String> {
+        let number = |operand: &str| {
+            operand
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| format!("\"{}\" is not a number", operand))
+        };
+        Ok(match kind {
+            TransformKind::Multiply => Transform::Multiply(number(operand)?),
+            TransformKind::Add => Transform::Add(number(operand)?),
+            TransformKind::Round => Transform::Round(if operand.trim().is_empty() {
+                0
+            } else {
+                operand
+                    .trim()
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|decimals| (0..=MAX_ROUND_DECIMALS).contains(decimals))
+                    .ok_or_else(|| {
+                        format!(
+                            "\"{}\" is not a number of decimals between 0 and {}",
+                            operand, MAX_ROUND_DECIMALS
+                        )
+                    })?
+            }),
+            TransformKind::Clamp => {
+                let (min, max) = (number(operand)?, number(operand2)?);
+                if min > max {
+                    return Err(format!("min {} is greater than max {}", min, max));
+                }
+                Transform::Clamp(min, max)
+            }
+            TransformKind::Toggle => Transform::Toggle,
+            // Json literal, e.g. 42, true or null, otherwise a string
+            TransformKind::Set => Transform::Set(
+                serde_json::from_str::<Value>(operand.trim())
+                    .ok()
+                    .filter(|value| !value.is_array() && !value.is_object())
+                    .unwrap_or_else(|| Value::String(operand.to_string())),
+            ),
+        })
+    }
+
+    /// New value, `None` when transform does not apply to this value or when its result is not a
+    /// finite number.
+    pub fn apply(&self, value: Option<&str>) -> Option<Value> {
+        let number = || {
+            value
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|number| number.is_finite())
+        };
+        let finite = |number: f64| number.is_finite().then(|| number_value(number));
+        match self {
+            Transform::Multiply(operand) => finite(Self::tidy(number()? * operand)),
+            Transform::Add(operand) => finite(Self::tidy(number()? + operand)),
+            Transform::Round(decimals) => {
+                let factor = 10f64.powi(*decimals);
+                let rounded = (number()? * factor).round() / factor;
+                // Too large to have decimals, multiplying it by factor overflowed
+                finite(rounded).or_else(|| finite(number()?))
+            }
+            Transform::Clamp(min, max) => finite(number()?.clamp(*min, *max)),
+            Transform::Toggle => match value? {
+                "true" => Some(Value::Bool(false)),
+                "false" => Some(Value::Bool(true)),
+                _ => None,
+            },
+            Transform::Set(new_value) => Some(new_value.clone()),
+        }
+    }
+
+    /// Drop floating point noise, so 10 * 1.1 is 11 and not 11.000000000000002.
+    fn tidy(number: f64) -> f64 {
+        format!("{:.12}", number).parse().unwrap_or(number)
+    }
+}
+
+pub struct ReplaceOccurrence {
+    pub row_index: usize,
+    pub column: String,
+    pub old_value: Option<String>,
+    pub new_value: FlatJsonValue<String>,
+    // Unticked occurrences are not replaced
+    pub confirmed: bool,
+}
+
+pub struct ReplacePreview {
+    pub occurrences: Vec<ReplaceOccurrence>,
+    // Number of occurrences by column, in order of first occurrence
+    pub column_counts: Vec<(String, usize)>,
+    // Rows occurrences were searched in
+    pub scope: ReplaceScope,
+}
+
+impl ReplacePreview {
+    pub fn new(occurrences: Vec<ReplaceOccurrence>, scope: ReplaceScope) -> Self {
+        let mut column_counts: Vec<(String, usize)> = vec![];
+        for occurrence in occurrences.iter() {
+            match column_counts
+                .iter_mut()
+                .find(|(column, _)| *column == occurrence.column)
+            {
+                Some((_, count)) => *count += 1,
+                None => column_counts.push((occurrence.column.clone(), 1)),
+            }
+        }
+        Self {
+            occurrences,
+            column_counts,
+            scope,
+        }
+    }
+
+    pub fn confirmed_count(&self) -> usize {
+        self.occurrences.iter().filter(|o| o.confirmed).count()
+    }
+
+    pub fn set_all_confirmed(&mut self, confirmed: bool) {
+        self.occurrences
+            .iter_mut()
+            .for_each(|o| o.confirmed = confirmed);
+    }
+
+    /// New values of ticked occurrences, with their row index.
+    pub fn into_confirmed(self) -> Vec<(FlatJsonValue<String>, usize)> {
+        self.occurrences
+            .into_iter()
+            .filter(|o| o.confirmed)
+            .map(|o| (o.new_value, o.row_index))
+            .collect()
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        let counts = self
+            .column_counts
+            .iter()
+            .map(|(column, count)| format!("{}: {}", column, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        ui.label(format!(
+            "{} occurrences in {} ({})",
+            self.occurrences.len(),
+            self.scope.as_str().to_lowercase(),
+            counts
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Tick all").clicked() {
+                self.set_all_confirmed(true);
+            }
+            if ui.button("Untick all").clicked() {
+                self.set_all_confirmed(false);
+            }
+        });
+        self.occurrences_table_ui(ui);
+    }
+
+    fn occurrences_table_ui(&mut self, ui: &mut Ui) {
+        use crate::components::table::{Column, TableBuilder};
+        let text_height = egui::TextStyle::Body
+            .resolve(ui.style())
+            .size
+            .max(ui.spacing().interact_size.y);
+        let value_text = |value: Option<&String>| match value {
+            Some(value) => RichText::new(value.as_str()),
+            None => RichText::new("null").italics().weak(),
+        };
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(Align::Center))
+            .min_scrolled_height(0.0)
+            .max_scroll_height(400.0)
+            .column(Column::initial(24.0))
+            .column(Column::initial(60.0).clip(true).resizable(true))
+            .column(Column::initial(120.0).clip(true).resizable(true))
+            .column(Column::initial(200.0).clip(true).resizable(true))
+            .column(Column::remainder().clip(true).resizable(true))
+            .header(text_height * 1.5, |mut header| {
+                header.col(|ui, _| Some(ui.label("")));
+                header.col(|ui, _| Some(ui.strong("Row")));
+                header.col(|ui, _| Some(ui.strong("Column")));
+                header.col(|ui, _| Some(ui.strong("Old value")));
+                header.col(|ui, _| Some(ui.strong("New value")));
+            })
+            .body(None, None, None, |body| {
+                body.rows(text_height, self.occurrences.len(), |mut row| {
+                    let occurrence = &mut self.occurrences[row.index()];
+                    row.col(|ui, _| Some(ui.checkbox(&mut occurrence.confirmed, "")));
+                    row.col(|ui, _| Some(ui.label(occurrence.row_index.to_string())));
+                    row.col(|ui, _| Some(ui.label(occurrence.column.as_str())));
+                    row.col(|ui, _| Some(ui.label(value_text(occurrence.old_value.as_ref()))));
+                    row.col(|ui, _| {
+                        Some(ui.label(value_text(occurrence.new_value.value.as_ref())))
+                    });
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::panels::ReplaceScope;
+    use crate::replace_panel::{ReplaceOccurrence, ReplacePreview, Transform, TransformKind};
+    use json_flat_parser::{FlatJsonValue, PointerKey, ValueType};
+    use serde_json::{json, Value};
+
+    fn occurrence(row_index: usize, column: &str, new_value: &str) -> ReplaceOccurrence {
+        ReplaceOccurrence {
+            row_index,
+            column: column.to_string(),
+            old_value: None,
+            new_value: FlatJsonValue {
+                pointer: PointerKey {
+                    pointer: format!("/{}{}", row_index, column),
+                    value_type: ValueType::String,
+                    depth: 2,
+                    position: 0,
+                    column_id: 0,
+                },
+                value: Some(new_value.to_string()),
+            },
+            confirmed: true,
+        }
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut preview = ReplacePreview::new(
+            vec![
+                occurrence(0, "/name", "a"),
+                occurrence(0, "/type", "b"),
+                occurrence(2, "/name", "c"),
+                occurrence(5, "/name", "d"),
+            ],
+            ReplaceScope::Filtered,
+        );
+        assert_eq!(
+            preview.column_counts,
+            vec![("/name".to_string(), 3), ("/type".to_string(), 1)]
+        );
+        assert_eq!(preview.confirmed_count(), 4);
+        preview.occurrences[2].confirmed = false;
+        assert_eq!(preview.confirmed_count(), 3);
+        let confirmed = preview
+            .into_confirmed()
+            .into_iter()
+            .map(|(value, row_index)| (value.pointer.pointer, value.value.unwrap(), row_index))
+            .collect::<Vec<(String, String, usize)>>();
+        assert_eq!(
+            confirmed,
+            vec![
+                ("/0/name".to_string(), "a".to_string(), 0),
+                ("/0/type".to_string(), "b".to_string(), 0),
+                ("/5/name".to_string(), "d".to_string(), 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_all_confirmed() {
+        let mut preview = ReplacePreview::new(
+            vec![occurrence(0, "/name", "a"), occurrence(1, "/name", "b")],
+            ReplaceScope::All,
+        );
+        preview.set_all_confirmed(false);
+        assert_eq!(preview.confirmed_count(), 0);
+        assert!(preview.into_confirmed().is_empty());
+    }
+
+    #[test]
+    fn test_transform_new() {
+        assert!(matches!(
+            Transform::new(TransformKind::Multiply, " 1.5 ", ""),
+            Ok(Transform::Multiply(n)) if n == 1.5
+        ));
+        for operand in ["", "abc", "NaN", "inf", "-infinity", "1e400"] {
+            assert!(Transform::new(TransformKind::Add, operand, "").is_err());
+            assert!(Transform::new(TransformKind::Clamp, operand, "1").is_err());
+            assert!(Transform::new(TransformKind::Clamp, "1", operand).is_err());
+        }
+        assert!(Transform::new(TransformKind::Clamp, "2", "1").is_err());
+        assert!(matches!(
+            Transform::new(TransformKind::Round, "", ""),
+            Ok(Transform::Round(0))
+        ));
+        assert!(matches!(
+            Transform::new(TransformKind::Round, "15", ""),
+            Ok(Transform::Round(15))
+        ));
+        for decimals in ["-1", "16", "400", "1.5"] {
+            assert!(Transform::new(TransformKind::Round, decimals, "").is_err());
+        }
+        assert!(matches!(
+            Transform::new(TransformKind::Set, "42", ""),
+            Ok(Transform::Set(value)) if value == json!(42)
+        ));
+        assert!(matches!(
+            Transform::new(TransformKind::Set, "[1]", ""),
+            Ok(Transform::Set(value)) if value == json!("[1]")
+        ));
+        assert!(matches!(
+            Transform::new(TransformKind::Set, "Poring", ""),
+            Ok(Transform::Set(value)) if value == json!("Poring")
+        ));
+    }
+
+    #[test]
+    fn test_transform_apply() {
+        let transform =
+            |kind, operand: &str, operand2: &str| Transform::new(kind, operand, operand2).unwrap();
+        let multiply = transform(TransformKind::Multiply, "1.1", "");
+        assert_eq!(multiply.apply(Some("10")), Some(json!(11)));
+        assert_eq!(multiply.apply(Some("abc")), None);
+        assert_eq!(multiply.apply(None), None);
+        assert_eq!(multiply.apply(Some("NaN")), None);
+        assert_eq!(
+            transform(TransformKind::Multiply, "1e308", "").apply(Some("10")),
+            None
+        );
+        assert_eq!(
+            transform(TransformKind::Add, "1e308", "").apply(Some("1e308")),
+            None
+        );
+        assert_eq!(
+            transform(TransformKind::Add, "-0.5", "").apply(Some("2")),
+            Some(json!(1.5))
+        );
+        let round = transform(TransformKind::Round, "2", "");
+        assert_eq!(round.apply(Some("1.23456")), Some(json!(1.23)));
+        assert_eq!(round.apply(Some("1e307")), Some(json!(1e307)));
+        let clamp = transform(TransformKind::Clamp, "0", "10");
+        assert_eq!(clamp.apply(Some("-5")), Some(json!(0)));
+        assert_eq!(clamp.apply(Some("12.5")), Some(json!(10)));
+        assert_eq!(clamp.apply(Some("NaN")), None);
+        let toggle = Transform::Toggle;
+        assert_eq!(toggle.apply(Some("true")), Some(json!(false)));
+        assert_eq!(toggle.apply(Some("false")), Some(json!(true)));
+        assert_eq!(toggle.apply(Some("1")), None);
+        assert_eq!(
+            transform(TransformKind::Set, "null", "").apply(None),
+            Some(Value::Null)
+        );
+    }
+}
@@ -0,0 +1,665 @@
+//! Validation of rows against a JSON Schema describing the whole document, and inference of such
+//! schema from rows.
+//!
+//! The schema describes the opened file, rows are validated against the sub schema describing items
+//! of the array the table is built from. To keep `$ref` to definitions of the root schema working,
+//! the root schema is registered as a resource and rows are validated against a `$ref` to that sub schema.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use json_flat_parser::JsonArrayEntries;
+use jsonschema::{Resource, Validator};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde_json::{Map, Value};
+
+use crate::concat_string;
+use crate::parser::format::{JsonNode, JsonWriteOptions};
+
+// Base uri of the schema when it does not define its `$id`
+const DOCUMENT_URI: &str = "urn:json-editor:document";
+
+#[derive(Clone, Debug)]
+pub struct SchemaError {
+    // Pointer of the invalid value relative to the row, empty when the row itself is invalid
+    pub pointer: String,
+    pub message: String,
+}
+
+impl SchemaError {
+    /// Whether error is about value of `column` or about one of its children.
+    pub fn concerns_column(&self, column: &str) -> bool {
+        self.pointer.starts_with(column)
+            && (self.pointer.len() == column.len() || self.pointer.as_bytes()[column.len()] == b'/')
+    }
+}
+
+pub struct RowValidator {
+    validator: Validator,
+    pub source: String,
+}
+
+impl RowValidator {
+    /// `parent_pointer` is the location of the array, which items are rows, in the document.
+    pub fn new(schema: &Value, parent_pointer: &str, source: String) -> Result<Self, String> {
+        let items_pointer = items_schema_pointer(schema, parent_pointer)?;
+        let document_uri = schema
+            .get("$id")
+            .and_then(|id| id.as_str())
+            .unwrap_or(DOCUMENT_URI)
+            .trim_end_matches('#')
+            .to_string();
+        let document = Resource::from_contents(schema.clone())
+            .map_err(|e| format!("Invalid schema {}: {}", source, e))?;
+        let mut row_schema = Map::new();
+        if let Some(draft) = schema.get("$schema") {
+            row_schema.insert("$schema".to_string(), draft.clone());
+        }
+        row_schema.insert(
+            "$ref".to_string(),
+            Value::String(concat_string!(document_uri, "#", items_pointer)),
+        );
+        let validator = jsonschema::options()
+            .with_resource(document_uri, document)
+            .build(&Value::Object(row_schema))
+            .map_err(|e| format!("Invalid schema {}: {}", source, e))?;
+        Ok(Self { validator, source })
+    }
+
+    pub fn validate_row(&self, row: &JsonArrayEntries<String>) -> Vec<SchemaError> {
+        let Some(serialized_row) = row.entries().last().and_then(|e| e.value.as_ref()) else {
+            return vec![];
+        };
+        match serde_json::from_str::<Value>(serialized_row) {
+            Ok(instance) => self
+                .validator
+                .iter_errors(&instance)
+                .map(|error| SchemaError {
+                    pointer: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect(),
+            Err(e) => vec![SchemaError {
+                pointer: String::new(),
+                message: format!("Row is not valid json: {}", e),
+            }],
+        }
+    }
+
+    /// Errors by index of invalid rows in `rows`
+    pub fn validate_rows(
+        &self,
+        rows: &[JsonArrayEntries<String>],
+    ) -> BTreeMap<usize, Vec<SchemaError>> {
+        rows.par_iter()
+            .enumerate()
+            .filter_map(|(index, row)| {
+                let errors = self.validate_row(row);
+                if errors.is_empty() {
+                    None
+                } else {
+                    Some((index, errors))
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn load_schema(path: &Path) -> Result<Value, String> {
+    let content =
+        fs::read(path).map_err(|e| format!("Failed to read schema {}: {}", path.display(), e))?;
+    serde_json::from_slice(content.as_slice())
+        .map_err(|e| format!("Failed to parse schema {}: {}", path.display(), e))
+}
+
+/// Pointer, in the schema, to the sub schema describing items of array at `parent_pointer`.
+fn items_schema_pointer(schema: &Value, parent_pointer: &str) -> Result<String, String> {
+    let mut pointer = String::new();
+    let mut current = resolve_ref(schema, schema, &mut pointer)?;
+    let fragments = parent_pointer.split('/').skip(1);
+    for fragment in fragments.map(Some).chain([None]) {
+        // None stands for items of the array at parent pointer
+        let property = fragment.and_then(|fragment| {
+            current
+                .get("properties")
+                .and_then(|properties| properties.get(fragment))
+                .map(|property| (fragment, property))
+        });
+        if let Some((fragment, property)) = property {
+            pointer.push_str("/properties/");
+            pointer.push_str(&fragment.replace('~', "~0").replace('/', "~1"));
+            current = property;
+        } else if let Some(items) = current.get("items").filter(|items| items.is_object()) {
+            pointer.push_str("/items");
+            current = items;
+        } else if let Some(additional) = current
+            .get("additionalProperties")
+            .filter(|additional| fragment.is_some() && additional.is_object())
+        {
+            pointer.push_str("/additionalProperties");
+            current = additional;
+        } else {
+            return Err(format!(
+                "Schema does not describe array at \"{}\"",
+                parent_pointer
+            ));
+        }
+        current = resolve_ref(schema, current, &mut pointer)?;
+    }
+    Ok(pointer)
+}
+
+fn resolve_ref<'a>(
+    schema: &'a Value,
+    mut current: &'a Value,
+    pointer: &mut String,
+) -> Result<&'a Value, String> {
+    while let Some(reference) = current.get("$ref").and_then(|r| r.as_str()) {
+        let Some(local_pointer) = reference.strip_prefix('#') else {
+            return Err(format!(
+                "Only local $ref are supported, found \"{}\"",
+                reference
+            ));
+        };
+        current = schema
+            .pointer(local_pointer)
+            .ok_or_else(|| format!("Unresolvable $ref \"{}\"", reference))?;
+        *pointer = local_pointer.to_string();
+    }
+    Ok(current)
+}
+
+/// Value of `$schema` key of the root object, when there is one.
+pub fn detect_schema_reference(json: &[u8]) -> Option<String> {
+    let start = skip_whitespace(json, 0);
+    if json.get(start) != Some(&b'{') {
+        return None;
+    }
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < json.len() {
+        match json[i] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return None;
+                }
+            }
+            b'"' => {
+                let end = string_end(json, i)?;
+                if depth == 1 && &json[i + 1..end] == b"$schema" {
+                    let colon = skip_whitespace(json, end + 1);
+                    let value_start = skip_whitespace(json, colon + 1);
+                    if json.get(colon) == Some(&b':') && json.get(value_start) == Some(&b'"') {
+                        let value_end = string_end(json, value_start)?;
+                        return serde_json::from_slice(&json[value_start..=value_end]).ok();
+                    }
+                }
+                i = end;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Schema file referenced by `$schema` of `json_file`, remote schemas are not supported.
+pub fn resolve_schema_reference(reference: &str, json_file: &Path) -> Option<PathBuf> {
+    if reference.starts_with("http://") || reference.starts_with("https://") {
+        return None;
+    }
+    let path = Path::new(reference.strip_prefix("file://").unwrap_or(reference));
+    if path.is_absolute() {
+        Some(path.to_path_buf())
+    } else {
+        Some(json_file.parent()?.join(path))
+    }
+}
+
+const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+// String properties having at most this number of distinct values get an `enum`
+const ENUM_MAX_VALUES: usize = 10;
+const TYPE_NAMES: [&str; 7] = [
+    "object", "array", "string", "integer", "number", "boolean", "null",
+];
+// Bits of InferredSchema::types, in the order of TYPE_NAMES
+const OBJECT: u8 = 1 << 0;
+const ARRAY: u8 = 1 << 1;
+const STRING: u8 = 1 << 2;
+const INTEGER: u8 = 1 << 3;
+const NUMBER: u8 = 1 << 4;
+const BOOLEAN: u8 = 1 << 5;
+const NULL: u8 = 1 << 6;
+
+/// Shape of all values seen at a location of the rows.
+#[derive(Default)]
+struct InferredSchema {
+    // Bit set of indexes in TYPE_NAMES
+    types: u8,
+    values_count: usize,
+    strings_count: usize,
+    objects_count: usize,
+    properties: IndexMap<String, InferredSchema>,
+    items: Option<Box<InferredSchema>>,
+    // None once there are too many distinct values to make an enum
+    string_values: Option<BTreeSet<String>>,
+}
+
+impl InferredSchema {
+    fn add_value(&mut self, value: &JsonNode) {
+        self.values_count += 1;
+        let type_bit = match value {
+            JsonNode::Object(entries) => {
+                self.objects_count += 1;
+                for (key, value) in entries {
+                    self.properties
+                        .entry(unquote(key))
+                        .or_default()
+                        .add_value(value);
+                }
+                OBJECT
+            }
+            JsonNode::Array(elements) => {
+                let items = self.items.get_or_insert_with(Default::default);
+                elements.iter().for_each(|element| items.add_value(element));
+                ARRAY
+            }
+            JsonNode::Scalar(raw) if raw.starts_with('"') => {
+                self.strings_count += 1;
+                let first_string = self.types & STRING == 0;
+                if first_string {
+                    self.string_values = Some(BTreeSet::new());
+                }
+                if let Some(ref mut string_values) = self.string_values {
+                    string_values.insert(unquote(raw));
+                    if string_values.len() > ENUM_MAX_VALUES {
+                        self.string_values = None;
+                    }
+                }
+                STRING
+            }
+            JsonNode::Scalar(raw) => match raw.as_ref() {
+                "true" | "false" => BOOLEAN,
+                "null" => NULL,
+                // Same as serde_json, an integer is a number fitting in i64 or u64
+                number if number.parse::<i64>().is_ok() || number.parse::<u64>().is_ok() => INTEGER,
+                _ => NUMBER,
+            },
+        };
+        self.types |= type_bit;
+    }
+
+    fn merge(mut self, other: InferredSchema) -> Self {
+        let self_has_strings = self.types & STRING != 0;
+        let other_has_strings = other.types & STRING != 0;
+        self.string_values = match (self.string_values, other.string_values) {
+            (Some(mut values), Some(other_values)) => {
+                values.extend(other_values);
+                (values.len() <= ENUM_MAX_VALUES).then_some(values)
+            }
+            (values, None) if !other_has_strings => values,
+            (None, values) if !self_has_strings => values,
+            _ => None,
+        };
+        self.types |= other.types;
+        self.values_count += other.values_count;
+        self.strings_count += other.strings_count;
+        self.objects_count += other.objects_count;
+        for (key, property) in other.properties {
+            if let Some(existing) = self.properties.get_mut(&key) {
+                *existing = mem::take(existing).merge(property);
+            } else {
+                self.properties.insert(key, property);
+            }
+        }
+        self.items = match (self.items, other.items) {
+            (Some(items), Some(other_items)) => Some(Box::new(items.merge(*other_items))),
+            (items, other_items) => items.or(other_items),
+        };
+        self
+    }
+
+    /// Keywords are written in a fixed order and properties in the order they were first seen.
+    fn to_schema(&self) -> JsonNode<'static> {
+        let mut schema = vec![];
+        let mut types = self.types;
+        // integer is a subset of number
+        if types & NUMBER != 0 {
+            types &= !INTEGER;
+        }
+        let mut type_names = TYPE_NAMES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| types & (1 << i) != 0)
+            .map(|(_, name)| string_node(name))
+            .collect::<Vec<JsonNode>>();
+        match type_names.len() {
+            0 => {}
+            1 => {
+                schema.push(keyword("type", type_names.pop().unwrap()));
+            }
+            _ => {
+                schema.push(keyword("type", JsonNode::Array(type_names)));
+            }
+        }
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, property)| (Cow::Owned(quote(key)), property.to_schema()))
+                .collect::<Vec<(Cow<str>, JsonNode)>>();
+            schema.push(keyword("properties", JsonNode::Object(properties)));
+            let required = self
+                .properties
+                .iter()
+                .filter(|(_, property)| property.values_count == self.objects_count)
+                .map(|(key, _)| string_node(key))
+                .collect::<Vec<JsonNode>>();
+            if !required.is_empty() {
+                schema.push(keyword("required", JsonNode::Array(required)));
+            }
+        }
+        if let Some(ref items) = self.items {
+            schema.push(keyword("items", items.to_schema()));
+        }
+        // An enum would reject values of other types, only null can be listed along strings
+        let only_strings = self.types & !NULL == STRING;
+        if let Some(string_values) = self.string_values.as_ref().filter(|_| only_strings) {
+            // Avoid suggesting an enum when values barely repeat
+            if self.strings_count >= string_values.len() * 2 {
+                let mut values = string_values
+                    .iter()
+                    .map(|v| string_node(v))
+                    .collect::<Vec<JsonNode>>();
+                if self.types & NULL != 0 {
+                    values.push(JsonNode::Scalar(Cow::Borrowed("null")));
+                }
+                schema.push(keyword("enum", JsonNode::Array(values)));
+            }
+        }
+        JsonNode::Object(schema)
+    }
+}
+
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn unquote(raw: &str) -> String {
+    serde_json::from_str(raw).unwrap_or_else(|_| raw.trim_matches('"').to_string())
+}
+
+fn string_node(value: &str) -> JsonNode<'static> {
+    JsonNode::Scalar(Cow::Owned(quote(value)))
+}
+
+fn keyword(name: &str, value: JsonNode<'static>) -> (Cow<'static, str>, JsonNode<'static>) {
+    (Cow::Owned(quote(name)), value)
+}
+
+/// Draft 2020-12 schema of the document, inferred from all `rows` of the array at `parent_pointer`,
+/// serialized in pretty format.
+pub fn infer_schema(rows: &[JsonArrayEntries<String>], parent_pointer: &str) -> String {
+    let rows_schema = rows
+        .par_iter()
+        .fold(InferredSchema::default, |mut inferred_schema, row| {
+            let serialized_row = row.entries().last().and_then(|e| e.value.as_ref());
+            if let Some(Ok(value)) = serialized_row.map(|r| JsonNode::parse(r)) {
+                inferred_schema.add_value(&value);
+            }
+            inferred_schema
+        })
+        .reduce(InferredSchema::default, InferredSchema::merge);
+    let mut schema = vec![
+        keyword("type", string_node("array")),
+        keyword("items", rows_schema.to_schema()),
+    ];
+    // Wrap array schema so it describes the document from its root
+    for fragment in parent_pointer
+        .split('/')
+        .skip(1)
+        .collect::<Vec<&str>>()
+        .into_iter()
+        .rev()
+    {
+        schema = if fragment.parse::<usize>().is_ok() {
+            vec![
+                keyword("type", string_node("array")),
+                keyword("items", JsonNode::Object(schema)),
+            ]
+        } else {
+            vec![
+                keyword("type", string_node("object")),
+                keyword(
+                    "properties",
+                    JsonNode::Object(vec![(
+                        Cow::Owned(quote(fragment)),
+                        JsonNode::Object(schema),
+                    )]),
+                ),
+                keyword("required", JsonNode::Array(vec![string_node(fragment)])),
+            ]
+        };
+    }
+    schema.insert(0, keyword("$schema", string_node(DRAFT_2020_12)));
+    let mut serialized_schema = String::new();
+    let write_options = JsonWriteOptions {
+        pretty: true,
+        indent: "  ",
+        new_line: "\n",
+    };
+    JsonNode::Object(schema).write(&mut serialized_schema, &write_options, 0);
+    serialized_schema
+}
+
+#[inline]
+fn skip_whitespace(json: &[u8], mut i: usize) -> usize {
+    while i < json.len() && matches!(json[i], b' ' | b'\n' | b'\r' | b'\t') {
+        i += 1;
+    }
+    i
+}
+
+/// Index of closing quote of string starting at `start`
+#[inline]
+fn string_end(json: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < json.len() {
+        match json[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{
+        detect_schema_reference, infer_schema, items_schema_pointer, resolve_schema_reference,
+        RowValidator,
+    };
+    use json_flat_parser::{FlatJsonValue, JsonArrayEntries, PointerKey, ValueType};
+    use serde_json::{json, Value};
+    use std::path::Path;
+
+    fn rows(rows: &[&str]) -> Vec<JsonArrayEntries<String>> {
+        rows.iter()
+            .enumerate()
+            .map(|(index, row)| JsonArrayEntries {
+                entries: vec![FlatJsonValue {
+                    pointer: PointerKey {
+                        pointer: format!("/items/{}", index),
+                        value_type: ValueType::Object(true, 0),
+                        depth: 2,
+                        position: 0,
+                        column_id: 0,
+                    },
+                    value: Some(row.to_string()),
+                }],
+                index,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_schema_keeps_key_order() {
+        let rows = rows(&[
+            r#"{"name": "a", "id": 1, "tags": ["x"], "kind": "big"}"#,
+            r#"{"name": "bé", "id": 2.5, "kind": "big"}"#,
+            r#"{"name": null, "id": 3, "kind": "big", "zone": true}"#,
+        ]);
+        let schema = infer_schema(&rows, "/items");
+        let value: Value = serde_json::from_str(&schema).unwrap();
+        let items = &value["properties"]["items"]["items"];
+        assert_eq!(
+            items["properties"]["name"]["type"],
+            json!(["string", "null"])
+        );
+        assert_eq!(items["properties"]["id"]["type"], "number");
+        assert_eq!(items["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(items["properties"]["kind"]["enum"], json!(["big"]));
+        assert_eq!(items["required"], json!(["name", "id", "kind"]));
+        assert_eq!(value["required"], json!(["items"]));
+        let positions = ["$schema", "type", "name", "id", "tags", "kind", "zone"]
+            .map(|key| schema.find(&format!("\"{}\"", key)).unwrap());
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_inferred_schema_validates_rows() {
+        let row_values = [
+            r#"{"code": "a", "status": "open", "size": 1}"#,
+            r#"{"code": 2, "status": null, "size": 2.5}"#,
+            r#"{"code": "a", "status": "open"}"#,
+            r#"{"code": 2, "status": "closed", "size": 3}"#,
+            r#"{"code": "a", "status": "open", "size": 4}"#,
+        ];
+        let schema = infer_schema(&rows(&row_values), "/items");
+        let schema: Value = serde_json::from_str(&schema).unwrap();
+        let items = &schema["properties"]["items"]["items"];
+        // Strings mixed with numbers, an enum would reject the numbers
+        assert_eq!(
+            items["properties"]["code"]["type"],
+            json!(["string", "integer"])
+        );
+        assert!(items["properties"]["code"].get("enum").is_none());
+        assert_eq!(
+            items["properties"]["status"]["enum"],
+            json!(["closed", "open", null])
+        );
+
+        let document = json!({
+            "items": row_values
+                .iter()
+                .map(|row| serde_json::from_str::<Value>(row).unwrap())
+                .collect::<Vec<Value>>(),
+        });
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validator.is_valid(&document));
+    }
+
+    #[test]
+    fn test_validate_rows() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {"items": {"type": "array", "items": {"$ref": "#/$defs/item"}}},
+            "$defs": {
+                "item": {
+                    "type": "object",
+                    "properties": {"id": {"type": "integer"}, "tags": {"items": {"type": "string"}}},
+                    "required": ["id"],
+                }
+            }
+        });
+        let validator = RowValidator::new(&schema, "/items", "schema.json".to_string()).unwrap();
+        let rows = rows(&[
+            r#"{"id": 1, "tags": ["a"]}"#,
+            r#"{"id": "2"}"#,
+            r#"{"id": 3, "tags": ["a", 4]}"#,
+            r#"{"tags": []}"#,
+            r#"{"id": "#,
+        ]);
+        let errors = validator.validate_rows(&rows);
+        assert_eq!(
+            errors.keys().copied().collect::<Vec<usize>>(),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(errors[&1][0].pointer, "/id");
+        assert!(errors[&1][0].concerns_column("/id"));
+        assert_eq!(errors[&2][0].pointer, "/tags/1");
+        assert!(errors[&2][0].concerns_column("/tags"));
+        assert!(!errors[&2][0].concerns_column("/tag"));
+        // Missing required property is an error of the row itself
+        assert_eq!(errors[&3][0].pointer, "");
+        assert!(errors[&4][0].message.starts_with("Row is not valid json"));
+    }
+
+    #[test]
+    fn test_items_schema_pointer() {
+        let schema = json!({
+            "$ref": "#/$defs/root",
+            "$defs": {
+                "root": {"properties": {"skills": {"$ref": "#/$defs/skills"}}},
+                "skills": {"items": {"type": "object"}},
+                "map": {"additionalProperties": {"items": {"type": "string"}}},
+            }
+        });
+        assert_eq!(
+            items_schema_pointer(&schema, "/skills").unwrap(),
+            "/$defs/skills/items"
+        );
+        assert!(items_schema_pointer(&schema, "/unknown")
+            .unwrap_err()
+            .contains("does not describe array at \"/unknown\""));
+        let remote = json!({"$ref": "https://example.com/schema.json"});
+        assert!(items_schema_pointer(&remote, "")
+            .unwrap_err()
+            .starts_with("Only local $ref"));
+        let unresolvable = json!({"items": {"$ref": "#/$defs/missing"}});
+        assert_eq!(
+            items_schema_pointer(&unresolvable, "").unwrap_err(),
+            "Unresolvable $ref \"#/$defs/missing\""
+        );
+        let map =
+            json!({"properties": {"by_name": {"$ref": "#/$defs/map"}}, "$defs": schema["$defs"]});
+        assert_eq!(
+            items_schema_pointer(&map, "/by_name/poring").unwrap(),
+            "/$defs/map/additionalProperties/items"
+        );
+    }
+
+    #[test]
+    fn test_detect_schema_reference() {
+        let detect = |json: &str| detect_schema_reference(json.as_bytes());
+        assert_eq!(
+            detect(
+                r#" {"items": [{"$schema": "row.json"}], "a\"": 1, "$schema": "data.schema.json"}"#
+            ),
+            Some("data.schema.json".to_string())
+        );
+        assert_eq!(detect(r#"{"items": {"$schema": "nested.json"}}"#), None);
+        assert_eq!(detect(r#"[{"$schema": "row.json"}]"#), None);
+        assert_eq!(detect(r#"{"$schema": 1}"#), None);
+        assert_eq!(detect(r#"{"$schema": "unterminated"#), None);
+
+        let json_file = Path::new("/data/monsters.json");
+        assert_eq!(
+            resolve_schema_reference("schemas/monster.json", json_file).unwrap(),
+            Path::new("/data/schemas/monster.json")
+        );
+        assert_eq!(
+            resolve_schema_reference("file:///schemas/monster.json", json_file).unwrap(),
+            Path::new("/schemas/monster.json")
+        );
+        assert!(resolve_schema_reference("https://example.com/schema.json", json_file).is_none());
+    }
+}
@@ -5,13 +5,20 @@ use eframe::egui::{Context, Ui};
 use egui::Order;
 use json_flat_parser::lexer::Lexer;
 use json_flat_parser::parser::Parser;
-use json_flat_parser::{FlatJsonValue, ParseOptions, ParseResult, PointerKey, ValueType};
+use json_flat_parser::{
+    FlatJsonValue, JSONParser, ParseOptions, ParseResult, PointerKey, ValueType,
+};
 
 pub struct SubTable<'array> {
     name: String,
     array_table: Option<ArrayTable<'array>>,
     object_table: Option<ObjectTable>,
     row_index: usize,
+    // Pointer of this row in the document when sub table was opened, pointers of sub table entries
+    // start with it even once the row is moved
+    row_pointer: String,
+    // Table built from a copy of the document (e.g. query result), its edits are not applied to parent
+    pub detached: bool,
 }
 
 impl SubTable<'_> {
@@ -20,6 +27,7 @@ impl SubTable<'_> {
         content: String,
         parent_value_type: ValueType,
         index_in_json_entries_array: usize,
+        row_pointer: String,
         depth: u8,
     ) -> Self {
         let name = parent_pointer.pointer.clone();
@@ -39,6 +47,8 @@ impl SubTable<'_> {
                 array_table: Some(array_table),
                 object_table: None,
                 row_index: index_in_json_entries_array,
+                row_pointer,
+                detached: false,
             }
         } else {
             let options = ParseOptions::default()
@@ -54,10 +64,42 @@ impl SubTable<'_> {
                 array_table: None,
                 object_table: Some(ObjectTable::new(result.json, name)),
                 row_index: index_in_json_entries_array,
+                row_pointer,
+                detached: false,
             }
         }
     }
 
+    /// Table of a json array which is not part of the document, like query results.
+    pub fn new_detached(name: String, content: String) -> Result<Self, String> {
+        let options = ParseOptions::default().parse_array(false).max_depth(10);
+        let result = JSONParser::parse(content.as_str(), options)?.to_owned();
+        let parse_result = result.clone_except_json();
+        let (nodes, columns) = crate::parser::as_array(result)?;
+        let depth = parse_result
+            .parsing_max_depth
+            .min(parse_result.max_json_depth as u8)
+            .max(1);
+        let parent_pointer = PointerKey {
+            pointer: String::new(),
+            value_type: ValueType::Array(nodes.len()),
+            depth: 0,
+            position: 0,
+            column_id: 0,
+        };
+        let mut array_table =
+            ArrayTable::new(Some(parse_result), nodes, columns, depth, parent_pointer);
+        array_table.is_sub_table = true;
+        Ok(Self {
+            name,
+            array_table: Some(array_table),
+            object_table: None,
+            row_index: 0,
+            row_pointer: String::new(),
+            detached: true,
+        })
+    }
+
     fn parse(
         content: &str,
         options: &ParseOptions,
@@ -81,18 +123,38 @@ impl SubTable<'_> {
         self.row_index
     }
 
+    #[inline]
+    pub fn row_pointer(&self) -> &str {
+        &self.row_pointer
+    }
+
+    /// Follow its row, moved at `row_index`, `name` is the new pointer of the sub table.
+    pub fn set_row_index(&mut self, row_index: usize, name: String) {
+        self.row_index = row_index;
+        self.name = name;
+    }
+
     pub fn update_nodes(&mut self, pointer: PointerKey, value: Option<String>) {
         if let Some(ref mut array_table) = self.array_table {
-            if let Some(entry) = array_table.nodes[self.row_index]
+            // Entry belongs to the sub table row whose pointer starts its pointer
+            let row = array_table.nodes.iter_mut().find(|row| {
+                let row_pointer = crate::parser::row_pointer(&row.entries);
+                pointer
+                    .pointer
+                    .strip_prefix(row_pointer)
+                    .is_some_and(|key| key.is_empty() || key.starts_with('/'))
+            });
+            let Some(row) = row else {
+                return;
+            };
+            if let Some(entry) = row
                 .entries
                 .iter_mut()
                 .find(|entry| entry.pointer.pointer.eq(&pointer.pointer))
             {
                 entry.value = value;
             } else {
-                array_table.nodes[self.row_index]
-                    .entries
-                    .push(FlatJsonValue::<String> { pointer, value });
+                row.entries.push(FlatJsonValue::<String> { pointer, value });
             }
         } else {
             let table = self.object_table.as_mut().unwrap();
@@ -0,0 +1,409 @@
+//! View settings saved next to the opened file.
+//!
+//! The workspace is written as `.{file name}.workspace` when the file is saved or when the app
+//! exits, only when some settings differ from the defaults, and restored the next time the same
+//! file is opened. It holds the parent pointer of the
+//! displayed array, so settings of a nested array are not applied to another one. Save and backup
+//! options are restored whatever array is displayed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use egui::Color32;
+use serde_json::{json, Map, Value};
+
+use crate::array_table::ViewState;
+use crate::formatting::{FormattingCondition, FormattingRule, FormattingTarget};
+use crate::parser::{
+    sibling_path, write_atomically, BackupOptions, LineEnding, SaveFormat, SaveOptions,
+};
+
+pub fn workspace_path(file_path: &Path) -> PathBuf {
+    sibling_path(file_path, ".", ".workspace")
+}
+
+pub fn write_workspace(
+    file_path: &Path,
+    parent_pointer: &str,
+    view_state: &ViewState,
+    save_options: &SaveOptions,
+    backup_options: &BackupOptions,
+) -> std::io::Result<()> {
+    if is_default(view_state, save_options, backup_options) {
+        // Settings were reset, a previous workspace must not bring them back
+        return match fs::remove_file(workspace_path(file_path)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let workspace = to_json(parent_pointer, view_state, save_options, backup_options);
+    write_atomically(&workspace_path(file_path), None, |file| {
+        serde_json::to_writer_pretty(&mut *file, &workspace)?;
+        file.write_all(b"\n")
+    })
+}
+
+/// Depth and scroll position are not worth a workspace on their own.
+fn is_default(
+    view_state: &ViewState,
+    save_options: &SaveOptions,
+    backup_options: &BackupOptions,
+) -> bool {
+    view_state.pinned_columns.is_empty()
+        && view_state.columns_filter.is_empty()
+        && view_state.computed_columns.is_empty()
+        && view_state.sort.is_none()
+        && view_state.group_by.is_empty()
+        && view_state.formatting_rules.is_empty()
+        && view_state.id_column.is_none()
+        && view_state.column_order.is_empty()
+        && view_state.hidden_columns.is_empty()
+        && view_state.column_widths.is_empty()
+        && *save_options == SaveOptions::default()
+        && *backup_options == BackupOptions::default()
+}
+
+/// Returns parent pointer and view state, `None` when there is no workspace or it can not be read.
+pub fn read_workspace(file_path: &Path) -> Option<(String, ViewState)> {
+    from_json(&read(file_path)?)
+}
+
+/// Returns save options of the workspace, `None` when there is no workspace or it has none.
+pub fn read_save_options(file_path: &Path) -> Option<SaveOptions> {
+    save_options_from_json(read(file_path)?.get("save_options")?)
+}
+
+/// Returns backup options of the workspace, `None` when there is no workspace or it has none.
+pub fn read_backup_options(file_path: &Path) -> Option<BackupOptions> {
+    backup_options_from_json(read(file_path)?.get("backup_options")?)
+}
+
+fn read(file_path: &Path) -> Option<Value> {
+    let content = fs::read(workspace_path(file_path)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn to_json(
+    parent_pointer: &str,
+    view_state: &ViewState,
+    save_options: &SaveOptions,
+    backup_options: &BackupOptions,
+) -> Value {
+    json!({
+        "parent_pointer": parent_pointer,
+        "depth": view_state.depth,
+        "pinned_columns": view_state.pinned_columns,
+        "columns_filter": view_state.columns_filter,
+        "computed_columns": view_state
+            .computed_columns
+            .iter()
+            .map(|(name, expression)| json!({"name": name, "expression": expression}))
+            .collect::<Vec<Value>>(),
+        "sort": view_state
+            .sort
+            .as_ref()
+            .map(|(column, descending)| json!({"column": column, "descending": descending})),
+        "group_by": view_state.group_by,
+        "formatting_rules": view_state
+            .formatting_rules
+            .iter()
+            .map(|rule| {
+                json!({
+                    "column": rule.column,
+                    "condition": rule.condition.as_str(),
+                    "value": rule.value,
+                    "target": rule.target.as_str(),
+                    "color": rule.color.to_hex(),
+                })
+            })
+            .collect::<Vec<Value>>(),
+        "id_column": view_state.id_column,
+        "column_order": view_state.column_order,
+        "hidden_columns": view_state.hidden_columns,
+        "column_widths": view_state
+            .column_widths
+            .iter()
+            .map(|(name, width)| (name.clone(), json!(width)))
+            .collect::<Map<String, Value>>(),
+        "scroll_y": view_state.scroll_y,
+        "save_options": {
+            "format": save_options.format.as_str(),
+            "indent_size": save_options.indent_size,
+            "indent_with_tabs": save_options.indent_with_tabs,
+            "line_ending": save_options.line_ending.label(),
+            "trailing_new_line": save_options.trailing_new_line,
+            "sort_keys": save_options.sort_keys,
+        },
+        "backup_options": {
+            "keep_backup": backup_options.keep_backup,
+            "autosave": backup_options.autosave,
+            "autosave_interval_minutes": backup_options.autosave_interval_minutes,
+        },
+    })
+}
+
+/// Missing or invalid fields keep their default value.
+fn save_options_from_json(save_options: &Value) -> Option<SaveOptions> {
+    let save_options = save_options.as_object()?;
+    let flag = |name: &str| save_options.get(name).and_then(Value::as_bool);
+    let number = |name: &str| save_options.get(name).and_then(Value::as_u64);
+    let name = |name: &str| save_options.get(name).and_then(Value::as_str);
+    let default = SaveOptions::default();
+    Some(SaveOptions {
+        format: name("format")
+            .and_then(SaveFormat::from_name)
+            .unwrap_or(default.format),
+        indent_size: number("indent_size").map_or(default.indent_size, |size| size.min(8) as usize),
+        indent_with_tabs: flag("indent_with_tabs").unwrap_or(default.indent_with_tabs),
+        line_ending: name("line_ending")
+            .and_then(LineEnding::from_label)
+            .unwrap_or(default.line_ending),
+        trailing_new_line: flag("trailing_new_line").unwrap_or(default.trailing_new_line),
+        sort_keys: flag("sort_keys").unwrap_or(default.sort_keys),
+    })
+}
+
+/// Missing or invalid fields keep their default value.
+fn backup_options_from_json(backup_options: &Value) -> Option<BackupOptions> {
+    let backup_options = backup_options.as_object()?;
+    let flag = |name: &str| backup_options.get(name).and_then(Value::as_bool);
+    let default = BackupOptions::default();
+    Some(BackupOptions {
+        keep_backup: flag("keep_backup").unwrap_or(default.keep_backup),
+        autosave: flag("autosave").unwrap_or(default.autosave),
+        autosave_interval_minutes: backup_options
+            .get("autosave_interval_minutes")
+            .and_then(Value::as_u64)
+            .map_or(default.autosave_interval_minutes, |minutes| {
+                minutes.clamp(1, 60) as u32
+            }),
+    })
+}
+
+fn from_json(workspace: &Value) -> Option<(String, ViewState)> {
+    let parent_pointer = workspace.get("parent_pointer")?.as_str()?.to_string();
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let field = |object: &Map<String, Value>, name: &str| -> Option<String> {
+        object.get(name)?.as_str().map(str::to_string)
+    };
+    let columns_filter = workspace
+        .get("columns_filter")
+        .and_then(Value::as_object)
+        .map(|filters| {
+            filters
+                .iter()
+                .map(|(column, values)| (column.clone(), strings(Some(values))))
+                .collect::<HashMap<String, Vec<String>>>()
+        })
+        .unwrap_or_default();
+    let objects = |name: &str| -> Vec<&Map<String, Value>> {
+        workspace
+            .get(name)
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(Value::as_object).collect())
+            .unwrap_or_default()
+    };
+    let computed_columns = objects("computed_columns")
+        .into_iter()
+        .filter_map(|c| Some((field(c, "name")?, field(c, "expression")?)))
+        .collect();
+    let sort = workspace
+        .get("sort")
+        .and_then(Value::as_object)
+        .and_then(|sort| Some((field(sort, "column")?, sort.get("descending")?.as_bool()?)));
+    let formatting_rules = objects("formatting_rules")
+        .into_iter()
+        .filter_map(|rule| {
+            FormattingRule::new(
+                field(rule, "column")?,
+                FormattingCondition::from_name(&field(rule, "condition")?)?,
+                field(rule, "value").unwrap_or_default(),
+                FormattingTarget::from_name(&field(rule, "target")?)?,
+                Color32::from_hex(&field(rule, "color")?).ok()?,
+            )
+            .ok()
+        })
+        .collect();
+    let view_state = ViewState {
+        depth: workspace.get("depth")?.as_u64()?.min(u8::MAX as u64) as u8,
+        pinned_columns: strings(workspace.get("pinned_columns")),
+        columns_filter,
+        computed_columns,
+        sort,
+        group_by: strings(workspace.get("group_by")),
+        formatting_rules,
+        id_column: workspace
+            .get("id_column")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        column_order: strings(workspace.get("column_order")),
+        hidden_columns: strings(workspace.get("hidden_columns")),
+        column_widths: workspace
+            .get("column_widths")
+            .and_then(Value::as_object)
+            .map(|widths| {
+                widths
+                    .iter()
+                    .filter_map(|(name, width)| Some((name.clone(), width.as_f64()? as f32)))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        scroll_y: workspace
+            .get("scroll_y")
+            .and_then(Value::as_f64)
+            .unwrap_or_default() as f32,
+    };
+    Some((parent_pointer, view_state))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::array_table::ViewState;
+    use crate::formatting::{FormattingCondition, FormattingRule, FormattingTarget};
+    use crate::parser::{BackupOptions, LineEnding, SaveFormat, SaveOptions};
+    use crate::workspace::{
+        backup_options_from_json, from_json, save_options_from_json, to_json, workspace_path,
+        write_workspace,
+    };
+    use egui::Color32;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_round_trip() {
+        let view_state = ViewState {
+            depth: 3,
+            pinned_columns: vec!["/id".to_string()],
+            columns_filter: HashMap::from([("/type".to_string(), vec!["boss".to_string()])]),
+            computed_columns: vec![("/double".to_string(), "/hp * 2".to_string())],
+            sort: Some(("/hp".to_string(), true)),
+            group_by: vec!["/type".to_string()],
+            formatting_rules: vec![FormattingRule::new(
+                "/hp".to_string(),
+                FormattingCondition::GreaterThan,
+                "100".to_string(),
+                FormattingTarget::Row,
+                Color32::from_rgb(200, 10, 20),
+            )
+            .unwrap()],
+            id_column: Some("/id".to_string()),
+            column_order: vec!["/name".to_string(), "/id".to_string()],
+            hidden_columns: vec!["/secret".to_string()],
+            column_widths: vec![("/name".to_string(), 120.5)],
+            scroll_y: 42.0,
+        };
+        let save_options = SaveOptions {
+            format: SaveFormat::Pretty,
+            indent_size: 4,
+            line_ending: LineEnding::CrLf,
+            ..Default::default()
+        };
+        let backup_options = BackupOptions {
+            autosave: true,
+            ..Default::default()
+        };
+        let workspace = to_json("/items", &view_state, &save_options, &backup_options);
+        let (parent_pointer, restored) = from_json(&workspace).unwrap();
+        assert_eq!(parent_pointer, "/items");
+        assert_eq!(restored.depth, 3);
+        assert_eq!(restored.pinned_columns, view_state.pinned_columns);
+        assert_eq!(restored.columns_filter, view_state.columns_filter);
+        assert_eq!(restored.computed_columns, view_state.computed_columns);
+        assert_eq!(restored.sort, view_state.sort);
+        assert_eq!(restored.group_by, view_state.group_by);
+        assert_eq!(restored.formatting_rules.len(), 1);
+        let rule = &restored.formatting_rules[0];
+        assert_eq!(rule.description(), "/hp > 100 (row)");
+        assert_eq!(rule.color, Color32::from_rgb(200, 10, 20));
+        assert_eq!(restored.id_column, view_state.id_column);
+        assert_eq!(restored.column_order, view_state.column_order);
+        assert_eq!(restored.hidden_columns, view_state.hidden_columns);
+        assert_eq!(restored.column_widths, view_state.column_widths);
+        assert_eq!(restored.scroll_y, 42.0);
+
+        let restored = save_options_from_json(&workspace["save_options"]).unwrap();
+        assert!(restored.format == SaveFormat::Pretty);
+        assert_eq!(restored.indent_size, 4);
+        assert!(restored.line_ending == LineEnding::CrLf);
+        let restored = backup_options_from_json(&workspace["backup_options"]).unwrap();
+        assert!(restored.autosave);
+        assert!(!restored.keep_backup);
+    }
+
+    #[test]
+    fn test_invalid_workspace() {
+        assert!(from_json(&json!({"depth": 1})).is_none());
+        assert!(from_json(&json!({"parent_pointer": "", "depth": "1"})).is_none());
+        let (_, view_state) = from_json(&json!({
+            "parent_pointer": "",
+            "depth": 300,
+            "formatting_rules": [{"column": "/a", "condition": "?", "target": "cell", "color": "#ff0000"}],
+            "column_widths": {"/a": "wide", "/b": 10},
+        }))
+        .unwrap();
+        assert_eq!(view_state.depth, u8::MAX);
+        assert!(view_state.formatting_rules.is_empty());
+        assert_eq!(view_state.column_widths, vec![("/b".to_string(), 10.0)]);
+
+        let save_options = save_options_from_json(&json!({
+            "format": "unknown",
+            "indent_size": 100,
+        }))
+        .unwrap();
+        assert!(save_options.format == SaveFormat::Unchanged);
+        assert_eq!(save_options.indent_size, 8);
+        assert!(save_options_from_json(&json!("pretty")).is_none());
+        let backup_options =
+            backup_options_from_json(&json!({"autosave_interval_minutes": 0})).unwrap();
+        assert_eq!(backup_options.autosave_interval_minutes, 1);
+    }
+
+    #[test]
+    fn test_default_settings_are_not_written() {
+        let directory =
+            std::env::temp_dir().join(format!("json-editor-workspace-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let file_path = directory.join("data.json");
+        let mut view_state = ViewState {
+            depth: 2,
+            pinned_columns: vec![],
+            columns_filter: HashMap::new(),
+            computed_columns: vec![],
+            sort: None,
+            group_by: vec![],
+            formatting_rules: vec![],
+            id_column: None,
+            column_order: vec![],
+            hidden_columns: vec![],
+            column_widths: vec![],
+            scroll_y: 10.0,
+        };
+        let save_options = SaveOptions::default();
+        let backup_options = BackupOptions::default();
+        write_workspace(&file_path, "", &view_state, &save_options, &backup_options).unwrap();
+        assert!(!workspace_path(&file_path).exists());
+
+        view_state.hidden_columns = vec!["/secret".to_string()];
+        write_workspace(&file_path, "", &view_state, &save_options, &backup_options).unwrap();
+        assert!(workspace_path(&file_path).exists());
+
+        // Resetting settings removes the previous workspace
+        view_state.hidden_columns.clear();
+        write_workspace(&file_path, "", &view_state, &save_options, &backup_options).unwrap();
+        assert!(!workspace_path(&file_path).exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}